@@ -0,0 +1,141 @@
+use std::str::FromStr;
+use radix_common::prelude::*;
+use serde::Deserialize;
+
+/// Addresses and defaults read from a TOML config; every field can be overridden
+/// on the command line
+#[derive(Deserialize, Default, Clone)]
+pub struct Config {
+    pub network: Option<String>,
+    pub output_dir: Option<String>,
+    pub package: Option<String>,
+    pub component: Option<String>,
+    pub account: Option<String>,
+    pub base_token: Option<String>,
+    pub admin_badge: Option<String>,
+    pub lp_token: Option<String>,
+    pub long_nft: Option<String>,
+    pub short_nft: Option<String>,
+    pub fee: Option<String>,
+}
+
+impl Config {
+    /// Loads a config file, or returns an empty config when no path is given
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        match path {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Cannot read {}: {}", path, e))?;
+                toml::from_str(&contents).map_err(|e| format!("Cannot parse {}: {}", path, e))
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Applies command-line overrides on top of the file values
+    pub fn merge(self, overrides: Config) -> Self {
+        Self {
+            network: overrides.network.or(self.network),
+            output_dir: overrides.output_dir.or(self.output_dir),
+            package: overrides.package.or(self.package),
+            component: overrides.component.or(self.component),
+            account: overrides.account.or(self.account),
+            base_token: overrides.base_token.or(self.base_token),
+            admin_badge: overrides.admin_badge.or(self.admin_badge),
+            lp_token: overrides.lp_token.or(self.lp_token),
+            long_nft: overrides.long_nft.or(self.long_nft),
+            short_nft: overrides.short_nft.or(self.short_nft),
+            fee: overrides.fee.or(self.fee),
+        }
+    }
+
+    /// Resolves the network definition (defaults to the simulator)
+    pub fn network(&self) -> Result<NetworkDefinition, String> {
+        match self.network.as_deref().unwrap_or("simulator") {
+            "simulator" => Ok(NetworkDefinition::simulator()),
+            "stokenet" => Ok(NetworkDefinition::stokenet()),
+            "mainnet" => Ok(NetworkDefinition::mainnet()),
+            other => Err(format!("Unknown network: {}", other)),
+        }
+    }
+
+    fn required<'a>(value: &'a Option<String>, name: &str) -> Result<&'a str, String> {
+        value.as_deref().ok_or(format!("Missing {} (set it in the config or pass --{})", name, name.replace('_', "-")))
+    }
+
+    fn decoder(&self) -> Result<AddressBech32Decoder, String> {
+        Ok(AddressBech32Decoder::new(&self.network()?))
+    }
+
+    pub fn package(&self) -> Result<PackageAddress, String> {
+        let value = Self::required(&self.package, "package")?;
+        PackageAddress::try_from_bech32(&self.decoder()?, value)
+            .ok_or(format!("Invalid package address: {}", value))
+    }
+
+    fn component_address(&self, value: &Option<String>, name: &str) -> Result<ComponentAddress, String> {
+        let value = Self::required(value, name)?;
+        ComponentAddress::try_from_bech32(&self.decoder()?, value)
+            .ok_or(format!("Invalid {} address: {}", name, value))
+    }
+
+    fn resource_address(&self, value: &Option<String>, name: &str) -> Result<ResourceAddress, String> {
+        let value = Self::required(value, name)?;
+        ResourceAddress::try_from_bech32(&self.decoder()?, value)
+            .ok_or(format!("Invalid {} address: {}", name, value))
+    }
+
+    /// Parses a resource address given directly on the command line
+    pub fn resource(&self, value: &str) -> Result<ResourceAddress, String> {
+        ResourceAddress::try_from_bech32(&self.decoder()?, value)
+            .ok_or(format!("Invalid resource address: {}", value))
+    }
+
+    pub fn parse_component(&self, value: &str) -> Result<ComponentAddress, String> {
+        ComponentAddress::try_from_bech32(&self.decoder()?, value)
+            .ok_or(format!("Invalid component address: {}", value))
+    }
+
+    pub fn component(&self) -> Result<ComponentAddress, String> {
+        self.component_address(&self.component, "component")
+    }
+
+    pub fn account(&self) -> Result<ComponentAddress, String> {
+        self.component_address(&self.account, "account")
+    }
+
+    pub fn base_token(&self) -> Result<ResourceAddress, String> {
+        self.resource_address(&self.base_token, "base_token")
+    }
+
+    pub fn admin_badge(&self) -> Result<ResourceAddress, String> {
+        self.resource_address(&self.admin_badge, "admin_badge")
+    }
+
+    pub fn lp_token(&self) -> Result<ResourceAddress, String> {
+        self.resource_address(&self.lp_token, "lp_token")
+    }
+
+    pub fn long_nft(&self) -> Result<ResourceAddress, String> {
+        self.resource_address(&self.long_nft, "long_nft")
+    }
+
+    pub fn short_nft(&self) -> Result<ResourceAddress, String> {
+        self.resource_address(&self.short_nft, "short_nft")
+    }
+
+    /// Fee to lock from the account
+    pub fn fee(&self) -> Result<Decimal, String> {
+        parse_decimal(self.fee.as_deref().unwrap_or("10"))
+    }
+}
+
+/// Parses a decimal argument
+pub fn parse_decimal(value: &str) -> Result<Decimal, String> {
+    Decimal::from_str(value).map_err(|_| format!("Invalid decimal: {}", value))
+}
+
+/// Parses a non-fungible local ID such as `#1#` or `{...}`
+pub fn parse_local_id(value: &str) -> Result<NonFungibleLocalId, String> {
+    NonFungibleLocalId::from_str(value).map_err(|_| format!("Invalid non-fungible local ID: {}", value))
+}
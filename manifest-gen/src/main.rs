@@ -0,0 +1,752 @@
+//! Generates `.rtm` transaction manifests for every InfluxPool method.
+//!
+//! Addresses come from a TOML config (see `influxpool.example.toml`) and can be
+//! overridden per invocation. Manifests are written to `<output_dir>/<method>.rtm`
+//! or printed to stdout when no output directory is configured.
+
+mod config;
+mod manifests;
+
+use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use radix_common::prelude::*;
+use radix_transactions::prelude::*;
+
+use config::*;
+use manifests::*;
+
+#[derive(Parser)]
+#[command(name = "influxpool-manifest", about = "Generate transaction manifests for InfluxPool")]
+struct Cli {
+    /// TOML config with network and addresses
+    #[arg(long, global = true)]
+    config: Option<String>,
+
+    #[command(flatten)]
+    overrides: Overrides,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Command-line overrides for config values
+#[derive(Args)]
+struct Overrides {
+    /// simulator, stokenet or mainnet
+    #[arg(long, global = true)]
+    network: Option<String>,
+    /// Directory for generated manifests; prints to stdout when unset
+    #[arg(long, global = true)]
+    output_dir: Option<String>,
+    #[arg(long, global = true)]
+    package: Option<String>,
+    #[arg(long, global = true)]
+    component: Option<String>,
+    #[arg(long, global = true)]
+    account: Option<String>,
+    #[arg(long, global = true)]
+    base_token: Option<String>,
+    #[arg(long, global = true)]
+    admin_badge: Option<String>,
+    #[arg(long, global = true)]
+    lp_token: Option<String>,
+    #[arg(long, global = true)]
+    long_nft: Option<String>,
+    #[arg(long, global = true)]
+    short_nft: Option<String>,
+    /// Fee locked from the account (ignored on the simulator network)
+    #[arg(long, global = true)]
+    fee: Option<String>,
+}
+
+impl From<Overrides> for Config {
+    fn from(overrides: Overrides) -> Self {
+        Config {
+            network: overrides.network,
+            output_dir: overrides.output_dir,
+            package: overrides.package,
+            component: overrides.component,
+            account: overrides.account,
+            base_token: overrides.base_token,
+            admin_badge: overrides.admin_badge,
+            lp_token: overrides.lp_token,
+            long_nft: overrides.long_nft,
+            short_nft: overrides.short_nft,
+            fee: overrides.fee,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Instantiate a new HyperdrivePool component
+    InstantiateDex,
+    /// Create the pool with initial liquidity (needs the admin badge)
+    CreatePool {
+        #[arg(long)]
+        checkpoint_duration: u64,
+        #[arg(long)]
+        position_duration: u64,
+        #[command(flatten)]
+        fees: FeeArgs,
+        #[command(flatten)]
+        fee_mode: FeeModeArgs,
+        #[arg(long, value_parser = parse_decimal)]
+        min_share_reserves: Decimal,
+        /// Annualized fixed rate the curve opens at
+        #[arg(long, value_parser = parse_decimal)]
+        initial_fixed_rate: Decimal,
+        /// Project page shown by wallets for the position NFTs
+        #[arg(long)]
+        info_url: String,
+        /// Icon shown by wallets for the position NFT resources
+        #[arg(long)]
+        icon_url: String,
+        /// Image service rendering each position NFT
+        #[arg(long)]
+        image_base_url: String,
+        #[arg(long, value_parser = parse_decimal)]
+        initial_liquidity: Decimal,
+    },
+    /// Open a long with `amount` base tokens
+    OpenLong {
+        #[arg(long, value_parser = parse_decimal)]
+        amount: Decimal,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Close `bond_amount` of the long position NFT with the given local ID
+    CloseLong {
+        #[arg(long, value_parser = parse_local_id)]
+        id: NonFungibleLocalId,
+        /// Face value to close; the NFT is returned if any remains
+        #[arg(long, value_parser = parse_decimal)]
+        bond_amount: Decimal,
+        /// Abort unless at least this much base is received
+        #[arg(long, value_parser = parse_decimal)]
+        min_proceeds: Option<Decimal>,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Close several long position NFTs in one call
+    CloseLongs {
+        /// Comma-separated local IDs
+        #[arg(long, value_parser = parse_local_id, value_delimiter = ',', required = true)]
+        ids: Vec<NonFungibleLocalId>,
+        /// Abort unless at least this much base is received in total
+        #[arg(long, value_parser = parse_decimal)]
+        min_proceeds: Option<Decimal>,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Open a short for `face_value` bonds, depositing at most `max_deposit`
+    OpenShort {
+        #[arg(long, value_parser = parse_decimal)]
+        face_value: Decimal,
+        #[arg(long, value_parser = parse_decimal)]
+        max_deposit: Decimal,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Close `bond_amount` of the short position NFT with the given local ID
+    CloseShort {
+        #[arg(long, value_parser = parse_local_id)]
+        id: NonFungibleLocalId,
+        /// Face value to close; the NFT is returned if any remains
+        #[arg(long, value_parser = parse_decimal)]
+        bond_amount: Decimal,
+        /// Abort unless at least this much base is received
+        #[arg(long, value_parser = parse_decimal)]
+        min_proceeds: Option<Decimal>,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Close several short position NFTs in one call
+    CloseShorts {
+        /// Comma-separated local IDs
+        #[arg(long, value_parser = parse_local_id, value_delimiter = ',', required = true)]
+        ids: Vec<NonFungibleLocalId>,
+        /// Abort unless at least this much base is received in total
+        #[arg(long, value_parser = parse_decimal)]
+        min_proceeds: Option<Decimal>,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Merge position NFTs opened in the same checkpoint
+    MergePositions {
+        #[arg(long, value_enum)]
+        side: Side,
+        /// Comma-separated local IDs
+        #[arg(long, value_parser = parse_local_id, value_delimiter = ',', required = true)]
+        ids: Vec<NonFungibleLocalId>,
+    },
+    /// Split a position NFT into one NFT per face value
+    SplitPosition {
+        #[arg(long, value_enum)]
+        side: Side,
+        #[arg(long, value_parser = parse_local_id)]
+        id: NonFungibleLocalId,
+        /// Comma-separated face values summing to the position's face value
+        #[arg(long, value_parser = parse_decimal, value_delimiter = ',', required = true)]
+        amounts: Vec<Decimal>,
+    },
+    /// Convert a long position NFT into fungible bond tokens for its maturity
+    TokenizeLong {
+        #[arg(long, value_parser = parse_local_id)]
+        id: NonFungibleLocalId,
+    },
+    /// Convert bond tokens back into a long position NFT
+    Untokenize {
+        /// Bond token resource address
+        #[arg(long)]
+        bond_token: String,
+        #[arg(long, value_parser = parse_decimal)]
+        amount: Decimal,
+    },
+    /// Redeem matured bond tokens for base
+    RedeemBonds {
+        /// Bond token resource address
+        #[arg(long)]
+        bond_token: String,
+        #[arg(long, value_parser = parse_decimal)]
+        amount: Decimal,
+        /// Abort unless at least this much base is received
+        #[arg(long, value_parser = parse_decimal)]
+        min_proceeds: Option<Decimal>,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Look up the bond token of a checkpoint
+    GetBondResource {
+        #[arg(long)]
+        checkpoint: u64,
+    },
+    /// Convert a short position NFT into fungible yield tokens for its maturity
+    TokenizeShort {
+        #[arg(long, value_parser = parse_local_id)]
+        id: NonFungibleLocalId,
+    },
+    /// Redeem matured yield tokens for their variable interest
+    RedeemYield {
+        /// Yield token resource address
+        #[arg(long)]
+        yield_token: String,
+        #[arg(long, value_parser = parse_decimal)]
+        amount: Decimal,
+        /// Abort unless at least this much base is received
+        #[arg(long, value_parser = parse_decimal)]
+        min_proceeds: Option<Decimal>,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Look up the yield token of a checkpoint
+    GetYieldResource {
+        #[arg(long)]
+        checkpoint: u64,
+    },
+    /// Roll a matured long position NFT into a new long
+    RolloverLong {
+        #[arg(long, value_parser = parse_local_id)]
+        id: NonFungibleLocalId,
+        /// Abort unless the new position has at least this face value
+        #[arg(long, value_parser = parse_decimal)]
+        min_face_value: Decimal,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Roll a matured short position NFT into a new short of `new_face_value`
+    RolloverShort {
+        #[arg(long, value_parser = parse_local_id)]
+        id: NonFungibleLocalId,
+        #[arg(long, value_parser = parse_decimal)]
+        new_face_value: Decimal,
+        /// Most base to add on top of the settled interest
+        #[arg(long, value_parser = parse_decimal)]
+        max_deposit: Decimal,
+        /// Account credited with a share of the governance fee
+        #[arg(long)]
+        referrer: Option<String>,
+    },
+    /// Value a position NFT at its current close proceeds
+    GetPositionValue {
+        #[arg(long, value_enum)]
+        side: Side,
+        #[arg(long, value_parser = parse_local_id)]
+        id: NonFungibleLocalId,
+    },
+    /// Look up a position by ID, including one that has been burned
+    GetPositionSummary {
+        #[arg(long, value_parser = parse_local_id)]
+        id: NonFungibleLocalId,
+    },
+    /// Add `amount` base tokens of liquidity
+    AddLiquidity {
+        #[arg(long, value_parser = parse_decimal)]
+        amount: Decimal,
+        /// Abort unless at least this many LP tokens are minted
+        #[arg(long, value_parser = parse_decimal)]
+        min_lp_tokens: Option<Decimal>,
+    },
+    /// Burn `amount` LP tokens
+    RemoveLiquidity {
+        #[arg(long, value_parser = parse_decimal)]
+        amount: Decimal,
+        /// Abort unless at least this much base is withdrawn immediately
+        #[arg(long, value_parser = parse_decimal)]
+        min_base: Option<Decimal>,
+    },
+    /// Set the yield source share price (needs the admin badge)
+    UpdateSharePrice {
+        #[arg(long, value_parser = parse_decimal)]
+        share_price: Decimal,
+    },
+    /// Withdraw accrued governance fees (needs the admin badge)
+    WithdrawGovernanceFees,
+    /// Set the account governance fees are collected into (needs the admin badge)
+    SetFeeRecipient {
+        #[arg(long)]
+        recipient: String,
+    },
+    /// Deposit all governance fees into the fee recipient (needs the admin badge)
+    CollectGovernanceFees,
+    /// Set the share of a referred trade's governance fee paid to the referrer (needs the admin badge)
+    SetReferralShare {
+        #[arg(long, value_parser = parse_decimal)]
+        referral_share: Decimal,
+    },
+    /// Deposit a referrer's accrued fees into the referrer's account
+    ClaimReferralFees {
+        #[arg(long)]
+        referrer: String,
+    },
+    /// Recover a stray resource sent to the component (needs the admin badge)
+    Sweep {
+        #[arg(long)]
+        resource: String,
+    },
+    /// Switch the pool between normal, close-only and paused (needs the admin badge)
+    SetMode {
+        #[arg(value_enum)]
+        mode: Mode,
+    },
+    /// Propose new fees, executable after the fee update delay (needs the admin badge)
+    ProposeFees {
+        #[command(flatten)]
+        fees: FeeArgs,
+    },
+    /// Apply the pending fee proposal once its delay has passed
+    ExecuteFees,
+    /// Withdraw the pending fee proposal (needs the admin badge)
+    CancelFeeProposal,
+    /// Set the delay between proposing and executing fees, in epochs (needs the admin badge)
+    SetFeeUpdateDelay {
+        #[arg(long)]
+        delay: u64,
+    },
+    /// Close position NFTs at the last checkpoint price while the pool is paused
+    EmergencyClose {
+        #[arg(long, value_enum)]
+        side: Side,
+        /// Comma-separated local IDs
+        #[arg(long, value_parser = parse_local_id, value_delimiter = ',', required = true)]
+        ids: Vec<NonFungibleLocalId>,
+        /// Abort unless at least this much base is received in total
+        #[arg(long, value_parser = parse_decimal)]
+        min_proceeds: Option<Decimal>,
+    },
+    /// Burn `amount` LP tokens at the last checkpoint price while the pool is paused
+    EmergencyRemoveLiquidity {
+        #[arg(long, value_parser = parse_decimal)]
+        amount: Decimal,
+        /// Abort unless at least this much base is withdrawn immediately
+        #[arg(long, value_parser = parse_decimal)]
+        min_base: Option<Decimal>,
+    },
+    /// Call a read-only method
+    Query {
+        #[arg(value_enum)]
+        method: QueryMethod,
+    },
+    /// Call a time-weighted average over the last `window_epochs`
+    Twap {
+        #[arg(value_enum)]
+        average: Average,
+        #[arg(long)]
+        window_epochs: u64,
+    },
+    /// Call `get_rate_history` for up to `limit` observations from `from_time` on
+    RateHistory {
+        #[arg(long, default_value_t = 0)]
+        from_time: u64,
+        #[arg(long, default_value_t = 100)]
+        limit: u64,
+    },
+    /// Write manifests for every method that needs no extra arguments
+    All,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Side {
+    Long,
+    Short,
+}
+
+impl Side {
+    fn resource(self, config: &Config) -> Result<ResourceAddress, String> {
+        match self {
+            Side::Long => config.long_nft(),
+            Side::Short => config.short_nft(),
+        }
+    }
+}
+
+/// Fee parameters of `create_pool` and `propose_fees`
+#[derive(Args)]
+struct FeeArgs {
+    /// Curve fee, scaled by one minus the spot price (ϕc)
+    #[arg(long, value_parser = parse_decimal)]
+    curve_fee: Decimal,
+    /// Flat fee on matured bonds (ϕf)
+    #[arg(long, value_parser = parse_decimal)]
+    flat_fee: Decimal,
+    /// Governance share of the curve fee (ϕg,c)
+    #[arg(long, value_parser = parse_decimal)]
+    governance_curve_fee: Decimal,
+    /// Governance share of the flat fee (ϕg,f)
+    #[arg(long, value_parser = parse_decimal)]
+    governance_flat_fee: Decimal,
+    /// Governance share of zombie interest (ϕg,zombie)
+    #[arg(long, value_parser = parse_decimal)]
+    zombie_governance_fee: Decimal,
+}
+
+impl FeeArgs {
+    fn schedule(self) -> FeeSchedule {
+        FeeSchedule {
+            curve_fee: self.curve_fee,
+            flat_fee: self.flat_fee,
+            governance_curve_fee: self.governance_curve_fee,
+            governance_flat_fee: self.governance_flat_fee,
+            zombie_governance_fee: self.zombie_governance_fee,
+        }
+    }
+}
+
+/// Dynamic curve fee parameters of `create_pool`; the curve fee is fixed unless
+/// `--max-curve-fee` is given
+#[derive(Args)]
+struct FeeModeArgs {
+    /// Highest curve fee a trade can pay; selects the dynamic fee mode
+    #[arg(long, value_parser = parse_decimal)]
+    max_curve_fee: Option<Decimal>,
+    /// Lowest curve fee a trade can pay
+    #[arg(long, value_parser = parse_decimal, default_value = "0")]
+    min_curve_fee: Decimal,
+    /// Weight of trade size over effective reserves
+    #[arg(long, value_parser = parse_decimal, default_value = "0")]
+    size_multiplier: Decimal,
+    /// Weight of exposure over effective reserves
+    #[arg(long, value_parser = parse_decimal, default_value = "0")]
+    utilization_multiplier: Decimal,
+    /// Weight of checkpoint rate volatility
+    #[arg(long, value_parser = parse_decimal, default_value = "0")]
+    volatility_multiplier: Decimal,
+    /// Number of checkpoints the volatility is measured over
+    #[arg(long, default_value_t = 8)]
+    volatility_window: u64,
+}
+
+impl FeeModeArgs {
+    fn fee_mode(self) -> FeeMode {
+        match self.max_curve_fee {
+            None => FeeMode::Fixed,
+            Some(max_curve_fee) => FeeMode::Dynamic(DynamicFeeConfig {
+                size_multiplier: self.size_multiplier,
+                utilization_multiplier: self.utilization_multiplier,
+                volatility_multiplier: self.volatility_multiplier,
+                min_curve_fee: self.min_curve_fee,
+                max_curve_fee,
+                volatility_window: self.volatility_window,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Average {
+    Rate,
+    SharePrice,
+}
+
+impl Average {
+    fn method_name(self) -> &'static str {
+        match self {
+            Average::Rate => "get_twap_rate",
+            Average::SharePrice => "get_twap_share_price",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    Normal,
+    CloseOnly,
+    Paused,
+}
+
+impl Mode {
+    fn pool_mode(self) -> PoolMode {
+        match self {
+            Mode::Normal => PoolMode::Normal,
+            Mode::CloseOnly => PoolMode::CloseOnly,
+            Mode::Paused => PoolMode::Paused,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum QueryMethod {
+    GetPoolState,
+    EffectiveShareReserves,
+    GetSpotRate,
+    GetFixedRate,
+    GetPoolCount,
+    GetPositionCount,
+    GetMode,
+    GetFees,
+    GetPendingFees,
+    GetFeeRecipient,
+    CheckInvariants,
+}
+
+impl QueryMethod {
+    const ALL: [QueryMethod; 11] = [
+        QueryMethod::GetPoolState,
+        QueryMethod::EffectiveShareReserves,
+        QueryMethod::GetSpotRate,
+        QueryMethod::GetFixedRate,
+        QueryMethod::GetPoolCount,
+        QueryMethod::GetPositionCount,
+        QueryMethod::GetMode,
+        QueryMethod::GetFees,
+        QueryMethod::GetPendingFees,
+        QueryMethod::GetFeeRecipient,
+        QueryMethod::CheckInvariants,
+    ];
+
+    fn method_name(self) -> &'static str {
+        match self {
+            QueryMethod::GetPoolState => "get_pool_state",
+            QueryMethod::EffectiveShareReserves => "effective_share_reserves",
+            QueryMethod::GetSpotRate => "get_spot_rate",
+            QueryMethod::GetFixedRate => "get_fixed_rate",
+            QueryMethod::GetPoolCount => "get_pool_count",
+            QueryMethod::GetPositionCount => "get_position_count",
+            QueryMethod::GetMode => "get_mode",
+            QueryMethod::GetFees => "get_fees",
+            QueryMethod::GetPendingFees => "get_pending_fees",
+            QueryMethod::GetFeeRecipient => "get_fee_recipient",
+            QueryMethod::CheckInvariants => "check_invariants",
+        }
+    }
+}
+
+/// Writes a manifest to `<output_dir>/<name>.rtm`, or prints it
+/// Decodes the optional `--referrer` account of a trade
+fn parse_referrer(config: &Config, referrer: Option<String>) -> Result<Option<ComponentAddress>, String> {
+    referrer.map(|referrer| config.parse_component(&referrer)).transpose()
+}
+
+fn emit(config: &Config, name: &str, manifest: &TransactionManifestV1) -> Result<(), String> {
+    let text = render(config, manifest)?;
+    match &config.output_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir, e))?;
+            let path: PathBuf = [dir.as_str(), &format!("{}.rtm", name)].iter().collect();
+            std::fs::write(&path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+            eprintln!("Wrote {}", path.display());
+        }
+        None => println!("{}", text),
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let config = Config::load(cli.config.as_deref())?.merge(cli.overrides.into());
+
+    match cli.command {
+        Command::InstantiateDex => emit(&config, "instantiate_dex", &instantiate_dex(&config)?),
+        Command::CreatePool {
+            checkpoint_duration,
+            position_duration,
+            fees,
+            fee_mode,
+            min_share_reserves,
+            initial_fixed_rate,
+            info_url,
+            icon_url,
+            image_base_url,
+            initial_liquidity,
+        } => {
+            let args = CreatePoolArgs {
+                checkpoint_duration,
+                position_duration,
+                fees: fees.schedule(),
+                fee_mode: fee_mode.fee_mode(),
+                min_share_reserves,
+                initial_fixed_rate,
+                info_url,
+                icon_url,
+                image_base_url,
+                initial_liquidity,
+            };
+            emit(&config, "create_pool", &create_pool(&config, &args)?)
+        }
+        Command::OpenLong { amount, referrer } => {
+            let referrer = parse_referrer(&config, referrer)?;
+            emit(&config, "open_long", &open_long(&config, amount, referrer)?)
+        }
+        Command::CloseLong { id, bond_amount, min_proceeds, referrer } => {
+            let referrer = parse_referrer(&config, referrer)?;
+            emit(&config, "close_long", &close_long(&config, id, bond_amount, min_proceeds, referrer)?)
+        }
+        Command::CloseLongs { ids, min_proceeds, referrer } => {
+            let referrer = parse_referrer(&config, referrer)?;
+            emit(&config, "close_longs", &close_longs(&config, ids, min_proceeds, referrer)?)
+        }
+        Command::OpenShort { face_value, max_deposit, referrer } => {
+            let referrer = parse_referrer(&config, referrer)?;
+            emit(&config, "open_short", &open_short(&config, face_value, max_deposit, referrer)?)
+        }
+        Command::CloseShort { id, bond_amount, min_proceeds, referrer } => {
+            let referrer = parse_referrer(&config, referrer)?;
+            emit(&config, "close_short", &close_short(&config, id, bond_amount, min_proceeds, referrer)?)
+        }
+        Command::CloseShorts { ids, min_proceeds, referrer } => {
+            let referrer = parse_referrer(&config, referrer)?;
+            emit(&config, "close_shorts", &close_shorts(&config, ids, min_proceeds, referrer)?)
+        }
+        Command::MergePositions { side, ids } => {
+            emit(&config, "merge_positions", &merge_positions(&config, side.resource(&config)?, ids)?)
+        }
+        Command::SplitPosition { side, id, amounts } => {
+            emit(&config, "split_position", &split_position(&config, side.resource(&config)?, id, amounts)?)
+        }
+        Command::TokenizeLong { id } => emit(&config, "tokenize_long", &tokenize_long(&config, id)?),
+        Command::Untokenize { bond_token, amount } => {
+            emit(&config, "untokenize", &untokenize(&config, config.resource(&bond_token)?, amount)?)
+        }
+        Command::RedeemBonds { bond_token, amount, min_proceeds, referrer } => {
+            let bond_token = config.resource(&bond_token)?;
+            let referrer = parse_referrer(&config, referrer)?;
+            let manifest = redeem_tokens(&config, "redeem_bonds", bond_token, amount, min_proceeds, referrer)?;
+            emit(&config, "redeem_bonds", &manifest)
+        }
+        Command::GetBondResource { checkpoint } => {
+            emit(&config, "get_bond_resource", &get_checkpoint_resource(&config, "get_bond_resource", checkpoint)?)
+        }
+        Command::TokenizeShort { id } => emit(&config, "tokenize_short", &tokenize_short(&config, id)?),
+        Command::RedeemYield { yield_token, amount, min_proceeds, referrer } => {
+            let yield_token = config.resource(&yield_token)?;
+            let referrer = parse_referrer(&config, referrer)?;
+            let manifest = redeem_tokens(&config, "redeem_yield", yield_token, amount, min_proceeds, referrer)?;
+            emit(&config, "redeem_yield", &manifest)
+        }
+        Command::GetYieldResource { checkpoint } => {
+            emit(&config, "get_yield_resource", &get_checkpoint_resource(&config, "get_yield_resource", checkpoint)?)
+        }
+        Command::RolloverLong { id, min_face_value, referrer } => {
+            let referrer = parse_referrer(&config, referrer)?;
+            emit(&config, "rollover_long", &rollover_long(&config, id, min_face_value, referrer)?)
+        }
+        Command::RolloverShort { id, new_face_value, max_deposit, referrer } => {
+            let referrer = parse_referrer(&config, referrer)?;
+            emit(&config, "rollover_short", &rollover_short(&config, id, new_face_value, max_deposit, referrer)?)
+        }
+        Command::GetPositionValue { side, id } => {
+            let method = match side {
+                Side::Long => "get_long_value",
+                Side::Short => "get_short_value",
+            };
+            emit(&config, method, &get_position_value(&config, method, id)?)
+        }
+        Command::GetPositionSummary { id } => {
+            emit(&config, "get_position_summary", &get_position_value(&config, "get_position_summary", id)?)
+        }
+        Command::AddLiquidity { amount, min_lp_tokens } => {
+            emit(&config, "add_liquidity", &add_liquidity(&config, amount, min_lp_tokens)?)
+        }
+        Command::RemoveLiquidity { amount, min_base } => {
+            emit(&config, "remove_liquidity", &remove_liquidity(&config, amount, min_base)?)
+        }
+        Command::UpdateSharePrice { share_price } => {
+            emit(&config, "update_share_price", &update_share_price(&config, share_price)?)
+        }
+        Command::WithdrawGovernanceFees => {
+            emit(&config, "withdraw_governance_fees", &withdraw_governance_fees(&config)?)
+        }
+        Command::SetFeeRecipient { recipient } => {
+            emit(&config, "set_fee_recipient", &set_fee_recipient(&config, config.parse_component(&recipient)?)?)
+        }
+        Command::SetReferralShare { referral_share } => {
+            emit(&config, "set_referral_share", &set_referral_share(&config, referral_share)?)
+        }
+        Command::ClaimReferralFees { referrer } => {
+            emit(&config, "claim_referral_fees", &claim_referral_fees(&config, config.parse_component(&referrer)?)?)
+        }
+        Command::CollectGovernanceFees => {
+            emit(&config, "collect_governance_fees", &admin_call(&config, "collect_governance_fees")?)
+        }
+        Command::Sweep { resource } => emit(&config, "sweep", &sweep(&config, config.resource(&resource)?)?),
+        Command::SetMode { mode } => emit(&config, "set_mode", &set_mode(&config, mode.pool_mode())?),
+        Command::ProposeFees { fees } => {
+            emit(&config, "propose_fees", &propose_fees(&config, &fees.schedule())?)
+        }
+        Command::ExecuteFees => emit(&config, "execute_fees", &query(&config, "execute_fees")?),
+        Command::CancelFeeProposal => {
+            emit(&config, "cancel_fee_proposal", &admin_call(&config, "cancel_fee_proposal")?)
+        }
+        Command::SetFeeUpdateDelay { delay } => {
+            emit(&config, "set_fee_update_delay", &set_fee_update_delay(&config, delay)?)
+        }
+        Command::EmergencyClose { side, ids, min_proceeds } => {
+            emit(&config, "emergency_close", &emergency_close(&config, side.resource(&config)?, ids, min_proceeds)?)
+        }
+        Command::EmergencyRemoveLiquidity { amount, min_base } => {
+            emit(&config, "emergency_remove_liquidity", &emergency_remove_liquidity(&config, amount, min_base)?)
+        }
+        Command::Query { method } => {
+            emit(&config, method.method_name(), &query(&config, method.method_name())?)
+        }
+        Command::Twap { average, window_epochs } => {
+            emit(&config, average.method_name(), &twap(&config, average.method_name(), window_epochs)?)
+        }
+        Command::RateHistory { from_time, limit } => {
+            emit(&config, "get_rate_history", &rate_history(&config, from_time, limit)?)
+        }
+        Command::All => {
+            emit(&config, "instantiate_dex", &instantiate_dex(&config)?)?;
+            emit(&config, "withdraw_governance_fees", &withdraw_governance_fees(&config)?)?;
+            for method in QueryMethod::ALL {
+                emit(&config, method.method_name(), &query(&config, method.method_name())?)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    if let Err(error) = run(Cli::parse()) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
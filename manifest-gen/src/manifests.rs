@@ -0,0 +1,561 @@
+use radix_common::prelude::*;
+use radix_transactions::prelude::*;
+
+use crate::config::*;
+
+/// Blueprint name as published in the package
+const BLUEPRINT: &str = "HyperdrivePool";
+
+/// Starts a manifest and locks the fee (from the faucet on the simulator network)
+fn start(config: &Config) -> Result<ManifestBuilder, String> {
+    let builder = ManifestBuilder::new();
+    if config.network()?.id == NetworkDefinition::simulator().id {
+        Ok(builder.lock_fee_from_faucet())
+    } else {
+        Ok(builder.lock_fee(config.account()?, config.fee()?))
+    }
+}
+
+/// Deposits everything left on the worktop back into the account
+fn finish(builder: ManifestBuilder, config: &Config) -> Result<TransactionManifestV1, String> {
+    Ok(builder
+        .try_deposit_entire_worktop_or_abort(config.account()?, None)
+        .build())
+}
+
+/// Presents the admin badge, which holds every role of a component made by `instantiate_dex`
+fn prove_admin(builder: ManifestBuilder, config: &Config) -> Result<ManifestBuilder, String> {
+    Ok(builder.create_proof_from_account_of_amount(config.account()?, config.admin_badge()?, dec!(1)))
+}
+
+/// Asserts the worktop holds at least `minimum` of a resource, if a minimum is given
+fn assert_minimum(
+    builder: ManifestBuilder,
+    resource: ResourceAddress,
+    minimum: Option<Decimal>,
+) -> ManifestBuilder {
+    match minimum {
+        Some(minimum) => builder.assert_worktop_contains(resource, minimum),
+        None => builder,
+    }
+}
+
+pub fn instantiate_dex(config: &Config) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?.call_function(
+        config.package()?,
+        BLUEPRINT,
+        "instantiate_dex",
+        manifest_args!(config.base_token()?, config.admin_badge()?),
+    );
+    finish(builder, config)
+}
+
+/// Arguments of `create_pool`
+pub struct CreatePoolArgs {
+    pub checkpoint_duration: u64,
+    pub position_duration: u64,
+    pub fees: FeeSchedule,
+    pub fee_mode: FeeMode,
+    pub min_share_reserves: Decimal,
+    pub initial_fixed_rate: Decimal,
+    pub info_url: String,
+    pub icon_url: String,
+    pub image_base_url: String,
+    pub initial_liquidity: Decimal,
+}
+
+pub fn create_pool(config: &Config, args: &CreatePoolArgs) -> Result<TransactionManifestV1, String> {
+    let base_token = config.base_token()?;
+    let builder = prove_admin(start(config)?, config)?
+        .withdraw_from_account(config.account()?, base_token, args.initial_liquidity)
+        .take_from_worktop(base_token, args.initial_liquidity, "liquidity")
+        .call_method_with_name_lookup(config.component()?, "create_pool", |lookup| {
+            (
+                args.checkpoint_duration,
+                args.position_duration,
+                args.fees.clone(),
+                args.fee_mode.clone(),
+                args.min_share_reserves,
+                args.initial_fixed_rate,
+                args.info_url.clone(),
+                args.icon_url.clone(),
+                args.image_base_url.clone(),
+                lookup.bucket("liquidity"),
+            )
+        });
+    finish(builder, config)
+}
+
+pub fn open_long(
+    config: &Config,
+    amount: Decimal,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    let base_token = config.base_token()?;
+    let builder = start(config)?
+        .withdraw_from_account(config.account()?, base_token, amount)
+        .take_from_worktop(base_token, amount, "base")
+        .call_method_with_name_lookup(config.component()?, "open_long", |lookup| {
+            (lookup.bucket("base"), referrer)
+        });
+    finish(builder, config)
+}
+
+/// Withdraws one position NFT by local ID and closes `bond_amount` of its face value
+fn close_position(
+    config: &Config,
+    method: &str,
+    resource: ResourceAddress,
+    id: NonFungibleLocalId,
+    bond_amount: Decimal,
+    min_proceeds: Option<Decimal>,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?
+        .withdraw_non_fungibles_from_account(config.account()?, resource, [id.clone()])
+        .take_non_fungibles_from_worktop(resource, [id], "position")
+        .call_method_with_name_lookup(config.component()?, method, |lookup| {
+            (lookup.bucket("position"), bond_amount, referrer)
+        });
+    let builder = assert_minimum(builder, config.base_token()?, min_proceeds);
+    finish(builder, config)
+}
+
+pub fn close_long(
+    config: &Config,
+    id: NonFungibleLocalId,
+    bond_amount: Decimal,
+    min_proceeds: Option<Decimal>,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    close_position(config, "close_long", config.long_nft()?, id, bond_amount, min_proceeds, referrer)
+}
+
+pub fn rollover_long(
+    config: &Config,
+    id: NonFungibleLocalId,
+    min_face_value: Decimal,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    let resource = config.long_nft()?;
+    let builder = start(config)?
+        .withdraw_non_fungibles_from_account(config.account()?, resource, [id.clone()])
+        .take_non_fungibles_from_worktop(resource, [id], "position")
+        .call_method_with_name_lookup(config.component()?, "rollover_long", |lookup| {
+            (lookup.bucket("position"), min_face_value, referrer)
+        });
+    finish(builder, config)
+}
+
+/// Withdraws position NFTs by local ID and closes them all in one call
+fn close_positions(
+    config: &Config,
+    method: &str,
+    resource: ResourceAddress,
+    ids: Vec<NonFungibleLocalId>,
+    min_proceeds: Option<Decimal>,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?
+        .withdraw_non_fungibles_from_account(config.account()?, resource, ids.clone())
+        .take_non_fungibles_from_worktop(resource, ids, "positions")
+        .call_method_with_name_lookup(config.component()?, method, |lookup| {
+            (lookup.bucket("positions"), referrer)
+        });
+    let builder = assert_minimum(builder, config.base_token()?, min_proceeds);
+    finish(builder, config)
+}
+
+pub fn close_longs(
+    config: &Config,
+    ids: Vec<NonFungibleLocalId>,
+    min_proceeds: Option<Decimal>,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    close_positions(config, "close_longs", config.long_nft()?, ids, min_proceeds, referrer)
+}
+
+pub fn open_short(
+    config: &Config,
+    face_value: Decimal,
+    max_deposit: Decimal,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    // Only `max_deposit` is offered, so the call fails if the pool wants more
+    let base_token = config.base_token()?;
+    let builder = start(config)?
+        .withdraw_from_account(config.account()?, base_token, max_deposit)
+        .take_from_worktop(base_token, max_deposit, "deposit")
+        .call_method_with_name_lookup(config.component()?, "open_short", |lookup| {
+            (lookup.bucket("deposit"), face_value, referrer)
+        });
+    finish(builder, config)
+}
+
+pub fn close_short(
+    config: &Config,
+    id: NonFungibleLocalId,
+    bond_amount: Decimal,
+    min_proceeds: Option<Decimal>,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    close_position(config, "close_short", config.short_nft()?, id, bond_amount, min_proceeds, referrer)
+}
+
+pub fn rollover_short(
+    config: &Config,
+    id: NonFungibleLocalId,
+    new_face_value: Decimal,
+    max_deposit: Decimal,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    // Only `max_deposit` is offered on top of the settled interest
+    let resource = config.short_nft()?;
+    let base_token = config.base_token()?;
+    let builder = start(config)?
+        .withdraw_non_fungibles_from_account(config.account()?, resource, [id.clone()])
+        .take_non_fungibles_from_worktop(resource, [id], "position")
+        .withdraw_from_account(config.account()?, base_token, max_deposit)
+        .take_from_worktop(base_token, max_deposit, "deposit")
+        .call_method_with_name_lookup(config.component()?, "rollover_short", |lookup| {
+            (lookup.bucket("position"), new_face_value, lookup.bucket("deposit"), referrer)
+        });
+    finish(builder, config)
+}
+
+pub fn close_shorts(
+    config: &Config,
+    ids: Vec<NonFungibleLocalId>,
+    min_proceeds: Option<Decimal>,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    close_positions(config, "close_shorts", config.short_nft()?, ids, min_proceeds, referrer)
+}
+
+pub fn merge_positions(
+    config: &Config,
+    resource: ResourceAddress,
+    ids: Vec<NonFungibleLocalId>,
+) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?
+        .withdraw_non_fungibles_from_account(config.account()?, resource, ids.clone())
+        .take_non_fungibles_from_worktop(resource, ids, "positions")
+        .call_method_with_name_lookup(config.component()?, "merge_positions", |lookup| {
+            (lookup.bucket("positions"),)
+        });
+    finish(builder, config)
+}
+
+pub fn split_position(
+    config: &Config,
+    resource: ResourceAddress,
+    id: NonFungibleLocalId,
+    amounts: Vec<Decimal>,
+) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?
+        .withdraw_non_fungibles_from_account(config.account()?, resource, [id.clone()])
+        .take_non_fungibles_from_worktop(resource, [id], "position")
+        .call_method_with_name_lookup(config.component()?, "split_position", |lookup| {
+            (lookup.bucket("position"), amounts.clone())
+        });
+    finish(builder, config)
+}
+
+/// Withdraws one position NFT by local ID and converts it into maturity tokens
+fn tokenize_position(
+    config: &Config,
+    method: &str,
+    resource: ResourceAddress,
+    id: NonFungibleLocalId,
+) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?
+        .withdraw_non_fungibles_from_account(config.account()?, resource, [id.clone()])
+        .take_non_fungibles_from_worktop(resource, [id], "position")
+        .call_method_with_name_lookup(config.component()?, method, |lookup| {
+            (lookup.bucket("position"),)
+        });
+    finish(builder, config)
+}
+
+pub fn tokenize_long(config: &Config, id: NonFungibleLocalId) -> Result<TransactionManifestV1, String> {
+    tokenize_position(config, "tokenize_long", config.long_nft()?, id)
+}
+
+pub fn tokenize_short(config: &Config, id: NonFungibleLocalId) -> Result<TransactionManifestV1, String> {
+    tokenize_position(config, "tokenize_short", config.short_nft()?, id)
+}
+
+/// Passes `amount` of a fungible from the account to a single-bucket method
+fn call_with_tokens(
+    config: &Config,
+    method: &str,
+    resource: ResourceAddress,
+    amount: Decimal,
+) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?
+        .withdraw_from_account(config.account()?, resource, amount)
+        .take_from_worktop(resource, amount, "tokens")
+        .call_method_with_name_lookup(config.component()?, method, |lookup| {
+            (lookup.bucket("tokens"),)
+        });
+    finish(builder, config)
+}
+
+pub fn untokenize(config: &Config, bond_token: ResourceAddress, amount: Decimal) -> Result<TransactionManifestV1, String> {
+    call_with_tokens(config, "untokenize", bond_token, amount)
+}
+
+/// Redeems matured bond (`redeem_bonds`) or yield (`redeem_yield`) tokens
+pub fn redeem_tokens(
+    config: &Config,
+    method: &str,
+    token: ResourceAddress,
+    amount: Decimal,
+    min_proceeds: Option<Decimal>,
+    referrer: Option<ComponentAddress>,
+) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?
+        .withdraw_from_account(config.account()?, token, amount)
+        .take_from_worktop(token, amount, "tokens")
+        .call_method_with_name_lookup(config.component()?, method, |lookup| {
+            (lookup.bucket("tokens"), referrer)
+        });
+    let builder = assert_minimum(builder, config.base_token()?, min_proceeds);
+    finish(builder, config)
+}
+
+/// Looks up the bond (`get_bond_resource`) or yield (`get_yield_resource`) token of a checkpoint
+pub fn get_checkpoint_resource(config: &Config, method: &str, checkpoint: u64) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?.call_method(config.component()?, method, manifest_args!(checkpoint));
+    Ok(builder.build())
+}
+
+/// Calls a read-only method taking a position ID (`get_long_value`, `get_short_value`, `get_position_summary`)
+pub fn get_position_value(config: &Config, method: &str, id: NonFungibleLocalId) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?.call_method(config.component()?, method, manifest_args!(id));
+    Ok(builder.build())
+}
+
+pub fn add_liquidity(
+    config: &Config,
+    amount: Decimal,
+    min_lp_tokens: Option<Decimal>,
+) -> Result<TransactionManifestV1, String> {
+    let base_token = config.base_token()?;
+    let builder = start(config)?
+        .withdraw_from_account(config.account()?, base_token, amount)
+        .take_from_worktop(base_token, amount, "base")
+        .call_method_with_name_lookup(config.component()?, "add_liquidity", |lookup| {
+            (lookup.bucket("base"),)
+        });
+    // The LP token address is only required when a minimum is asserted
+    let builder = match min_lp_tokens {
+        Some(minimum) => builder.assert_worktop_contains(config.lp_token()?, minimum),
+        None => builder,
+    };
+    finish(builder, config)
+}
+
+/// Burns LP tokens through `remove_liquidity` or `emergency_remove_liquidity`
+fn withdraw_liquidity(
+    config: &Config,
+    method: &str,
+    amount: Decimal,
+    min_base: Option<Decimal>,
+) -> Result<TransactionManifestV1, String> {
+    let lp_token = config.lp_token()?;
+    let builder = start(config)?
+        .withdraw_from_account(config.account()?, lp_token, amount)
+        .take_from_worktop(lp_token, amount, "lp")
+        .call_method_with_name_lookup(config.component()?, method, |lookup| {
+            (lookup.bucket("lp"),)
+        });
+    let builder = assert_minimum(builder, config.base_token()?, min_base);
+    finish(builder, config)
+}
+
+pub fn remove_liquidity(
+    config: &Config,
+    amount: Decimal,
+    min_base: Option<Decimal>,
+) -> Result<TransactionManifestV1, String> {
+    withdraw_liquidity(config, "remove_liquidity", amount, min_base)
+}
+
+/// Mirror of the blueprint's `PoolMode`; the variants must stay in the same order
+#[derive(ManifestSbor, Clone, Copy)]
+pub enum PoolMode {
+    Normal,
+    CloseOnly,
+    Paused,
+}
+
+pub fn set_mode(config: &Config, mode: PoolMode) -> Result<TransactionManifestV1, String> {
+    let builder = prove_admin(start(config)?, config)?.call_method(
+        config.component()?,
+        "set_mode",
+        manifest_args!(mode),
+    );
+    Ok(builder.build())
+}
+
+/// Mirror of the blueprint's `FeeSchedule`; the fields must stay in the same order
+#[derive(ManifestSbor, Clone)]
+pub struct FeeSchedule {
+    pub curve_fee: Decimal,
+    pub flat_fee: Decimal,
+    pub governance_curve_fee: Decimal,
+    pub governance_flat_fee: Decimal,
+    pub zombie_governance_fee: Decimal,
+}
+
+/// Mirror of the blueprint's `FeeMode`; the variants must stay in the same order
+#[derive(ManifestSbor, Clone)]
+pub enum FeeMode {
+    Fixed,
+    Dynamic(DynamicFeeConfig),
+}
+
+/// Mirror of the blueprint's `DynamicFeeConfig`; the fields must stay in the same order
+#[derive(ManifestSbor, Clone)]
+pub struct DynamicFeeConfig {
+    pub size_multiplier: Decimal,
+    pub utilization_multiplier: Decimal,
+    pub volatility_multiplier: Decimal,
+    pub min_curve_fee: Decimal,
+    pub max_curve_fee: Decimal,
+    pub volatility_window: u64,
+}
+
+pub fn propose_fees(config: &Config, fees: &FeeSchedule) -> Result<TransactionManifestV1, String> {
+    let builder = prove_admin(start(config)?, config)?.call_method(
+        config.component()?,
+        "propose_fees",
+        manifest_args!(fees.clone()),
+    );
+    Ok(builder.build())
+}
+
+/// Calls an argument-free method gated on a role held by the admin badge
+pub fn admin_call(config: &Config, method: &str) -> Result<TransactionManifestV1, String> {
+    let builder = prove_admin(start(config)?, config)?.call_method(config.component()?, method, manifest_args!());
+    Ok(builder.build())
+}
+
+pub fn set_fee_update_delay(config: &Config, delay: u64) -> Result<TransactionManifestV1, String> {
+    let builder = prove_admin(start(config)?, config)?.call_method(
+        config.component()?,
+        "set_fee_update_delay",
+        manifest_args!(delay),
+    );
+    Ok(builder.build())
+}
+
+/// Closes position NFTs of one side at the last checkpoint price while the pool is paused
+pub fn emergency_close(
+    config: &Config,
+    resource: ResourceAddress,
+    ids: Vec<NonFungibleLocalId>,
+    min_proceeds: Option<Decimal>,
+) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?
+        .withdraw_non_fungibles_from_account(config.account()?, resource, ids.clone())
+        .take_non_fungibles_from_worktop(resource, ids, "positions")
+        .call_method_with_name_lookup(config.component()?, "emergency_close", |lookup| {
+            (lookup.bucket("positions"),)
+        });
+    let builder = assert_minimum(builder, config.base_token()?, min_proceeds);
+    finish(builder, config)
+}
+
+pub fn emergency_remove_liquidity(
+    config: &Config,
+    amount: Decimal,
+    min_base: Option<Decimal>,
+) -> Result<TransactionManifestV1, String> {
+    withdraw_liquidity(config, "emergency_remove_liquidity", amount, min_base)
+}
+
+pub fn update_share_price(config: &Config, share_price: Decimal) -> Result<TransactionManifestV1, String> {
+    let builder = prove_admin(start(config)?, config)?.call_method(
+        config.component()?,
+        "update_share_price",
+        manifest_args!(share_price),
+    );
+    Ok(builder.build())
+}
+
+pub fn withdraw_governance_fees(config: &Config) -> Result<TransactionManifestV1, String> {
+    let builder = prove_admin(start(config)?, config)?.call_method(
+        config.component()?,
+        "withdraw_governance_fees",
+        manifest_args!(),
+    );
+    finish(builder, config)
+}
+
+pub fn set_fee_recipient(config: &Config, fee_recipient: ComponentAddress) -> Result<TransactionManifestV1, String> {
+    let builder = prove_admin(start(config)?, config)?.call_method(
+        config.component()?,
+        "set_fee_recipient",
+        manifest_args!(fee_recipient),
+    );
+    Ok(builder.build())
+}
+
+pub fn set_referral_share(config: &Config, referral_share: Decimal) -> Result<TransactionManifestV1, String> {
+    let builder = prove_admin(start(config)?, config)?.call_method(
+        config.component()?,
+        "set_referral_share",
+        manifest_args!(referral_share),
+    );
+    Ok(builder.build())
+}
+
+/// Deposits a referrer's accrued fees into the referrer's account; needs no badge
+pub fn claim_referral_fees(config: &Config, referrer: ComponentAddress) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?.call_method(
+        config.component()?,
+        "claim_referral_fees",
+        manifest_args!(referrer),
+    );
+    Ok(builder.build())
+}
+
+/// Sweeps a stray resource out of the component into the account
+pub fn sweep(config: &Config, resource: ResourceAddress) -> Result<TransactionManifestV1, String> {
+    let builder = prove_admin(start(config)?, config)?.call_method(
+        config.component()?,
+        "sweep",
+        manifest_args!(resource),
+    );
+    finish(builder, config)
+}
+
+/// Calls a method that takes no arguments and needs no badge (the read-only methods and `execute_fees`)
+pub fn query(config: &Config, method: &str) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?.call_method(config.component()?, method, manifest_args!());
+    Ok(builder.build())
+}
+
+/// Calls `get_twap_rate` or `get_twap_share_price` over the last `window_epochs`
+pub fn twap(config: &Config, method: &str, window_epochs: u64) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?.call_method(config.component()?, method, manifest_args!(window_epochs));
+    Ok(builder.build())
+}
+
+/// Calls `get_rate_history` for up to `limit` observations recorded at or after `from_time`
+pub fn rate_history(config: &Config, from_time: u64, limit: u64) -> Result<TransactionManifestV1, String> {
+    let builder = start(config)?.call_method(
+        config.component()?,
+        "get_rate_history",
+        manifest_args!(from_time, limit),
+    );
+    Ok(builder.build())
+}
+
+/// Renders a manifest as `.rtm` text
+pub fn render(config: &Config, manifest: &TransactionManifestV1) -> Result<String, String> {
+    radix_transactions::manifest::decompile(manifest, &config.network()?)
+        .map_err(|e| format!("Cannot decompile manifest: {:?}", e))
+}
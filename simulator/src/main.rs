@@ -0,0 +1,84 @@
+mod scenario;
+mod sim;
+
+use influxpool::types::FeeSchedule;
+use scenario::*;
+use sim::*;
+
+/// Events replayed in time order: share price moves and scripted steps
+enum Event {
+    Rate(u64),
+    Step(Step),
+}
+
+impl Event {
+    fn time(&self) -> u64 {
+        match self {
+            Event::Rate(time) => *time,
+            Event::Step(step) => step.time,
+        }
+    }
+}
+
+fn run(path: &str) -> Result<(), String> {
+    let scenario = Scenario::load(path)?;
+    let params = &scenario.pool;
+    let config = SimConfig {
+        checkpoint_duration: params.checkpoint_duration,
+        position_duration: params.position_duration,
+        fees: FeeSchedule {
+            curve_fee: parse_decimal(&params.curve_fee, "curve_fee")?,
+            flat_fee: parse_decimal(&params.flat_fee, "flat_fee")?,
+            governance_curve_fee: parse_decimal(&params.governance_curve_fee, "governance_curve_fee")?,
+            governance_flat_fee: parse_decimal(&params.governance_flat_fee, "governance_flat_fee")?,
+            ..FeeSchedule::default()
+        },
+        min_share_reserves: parse_decimal(&params.min_share_reserves, "min_share_reserves")?,
+        initial_fixed_rate: parse_decimal(&params.initial_fixed_rate, "initial_fixed_rate")?,
+    };
+    let initial_liquidity = parse_decimal(&params.initial_liquidity, "initial_liquidity")?;
+
+    let mut pool = SimPool::new(&config, initial_liquidity, params.start_time);
+
+    // Rate points sort before steps at the same time so trades see the new price
+    let mut events: Vec<Event> = scenario.rate.iter().map(|point| Event::Rate(point.time))
+        .chain(scenario.step.iter().cloned().map(Event::Step))
+        .collect();
+    events.sort_by_key(|event| (event.time(), matches!(event, Event::Step(_))));
+
+    println!("{}", Snapshot::CSV_HEADER);
+    println!("{}", pool.snapshot(&config, params.start_time, "create_pool").to_csv());
+
+    for event in events {
+        let time = event.time().max(params.start_time);
+        pool.advance(&config, time, scenario.share_price_at(time)?);
+
+        let label = match &event {
+            Event::Rate(_) => "rate".to_string(),
+            Event::Step(step) => match pool.apply(&config, step) {
+                Ok(()) => step.action.name().to_string(),
+                Err(error) => {
+                    eprintln!("t={} {} rejected: {}", time, step.action.name(), error);
+                    format!("{}_rejected", step.action.name())
+                }
+            },
+        };
+
+        println!("{}", pool.snapshot(&config, time, &label).to_csv());
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 2 {
+        eprintln!("Usage: {} <scenario.toml>", args[0]);
+        std::process::exit(2);
+    }
+
+    if let Err(error) = run(&args[1]) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,107 @@
+use std::str::FromStr;
+use scrypto::prelude::*;
+use serde::Deserialize;
+
+/// A scripted backtest: pool parameters, a share price path and a series of flows
+#[derive(Deserialize)]
+pub struct Scenario {
+    pub pool: PoolParams,
+    #[serde(default)]
+    pub rate: Vec<RatePoint>,
+    #[serde(default)]
+    pub step: Vec<Step>,
+}
+
+/// Pool configuration, mirroring the arguments of `create_pool`
+#[derive(Deserialize)]
+pub struct PoolParams {
+    pub checkpoint_duration: u64,
+    pub position_duration: u64,
+    pub curve_fee: String,
+    pub flat_fee: String,
+    pub governance_curve_fee: String,
+    pub governance_flat_fee: String,
+    pub min_share_reserves: String,
+    pub initial_fixed_rate: String,
+    pub initial_liquidity: String,
+    #[serde(default)]
+    pub start_time: u64,
+}
+
+/// A point on the share price path; prices are interpolated linearly between points
+#[derive(Deserialize, Clone)]
+pub struct RatePoint {
+    pub time: u64,
+    pub share_price: String,
+}
+
+/// A single trade or LP flow at a given time
+#[derive(Deserialize, Clone)]
+pub struct Step {
+    pub time: u64,
+    pub action: Action,
+    /// Base for opens and adds, face value for shorts, LP tokens for removes
+    #[serde(default)]
+    pub amount: Option<String>,
+    /// Index of an earlier open of the same side, for closes
+    #[serde(default)]
+    pub position: Option<usize>,
+}
+
+/// Operations the simulator can replay
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    OpenLong,
+    CloseLong,
+    OpenShort,
+    CloseShort,
+    AddLiquidity,
+    RemoveLiquidity,
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::OpenLong => "open_long",
+            Action::CloseLong => "close_long",
+            Action::OpenShort => "open_short",
+            Action::CloseShort => "close_short",
+            Action::AddLiquidity => "add_liquidity",
+            Action::RemoveLiquidity => "remove_liquidity",
+        }
+    }
+}
+
+/// Parses a decimal field, naming it in the error
+pub fn parse_decimal(value: &str, field: &str) -> Result<Decimal, String> {
+    Decimal::from_str(value).map_err(|_| format!("Invalid decimal for {}: {}", field, value))
+}
+
+impl Scenario {
+    /// Loads a scenario from a TOML file
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        toml::from_str(&contents).map_err(|e| format!("Cannot parse {}: {}", path, e))
+    }
+
+    /// Share price at `time`, interpolated along the rate path
+    pub fn share_price_at(&self, time: u64) -> Result<Decimal, String> {
+        let mut previous: Option<(u64, Decimal)> = None;
+        for point in &self.rate {
+            let price = parse_decimal(&point.share_price, "share_price")?;
+            if point.time >= time {
+                return Ok(match previous {
+                    Some((start, start_price)) if point.time > start => {
+                        let progress = Decimal::from(time - start) / Decimal::from(point.time - start);
+                        start_price + (price - start_price) * progress
+                    }
+                    _ => price,
+                });
+            }
+            previous = Some((point.time, price));
+        }
+        Ok(previous.map(|(_, price)| price).unwrap_or(Decimal::ONE))
+    }
+}
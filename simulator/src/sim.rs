@@ -0,0 +1,360 @@
+use scrypto::prelude::*;
+use influxpool::curves::*;
+use influxpool::helpers::*;
+use influxpool::pool_core::*;
+use influxpool::types::*;
+
+use crate::scenario::*;
+
+/// Fee and duration settings for a simulated pool
+pub struct SimConfig {
+    pub checkpoint_duration: u64,
+    pub position_duration: u64,
+    pub fees: FeeSchedule,
+    pub min_share_reserves: Decimal,
+    pub initial_fixed_rate: Decimal,
+}
+
+/// An open long tracked by the simulator
+#[derive(Clone)]
+pub struct SimLong {
+    pub face_value: Decimal,
+    pub checkpoint: u64,
+    pub open_time: u64,
+    pub maturity_time: u64,
+}
+
+/// An open short tracked by the simulator
+#[derive(Clone)]
+pub struct SimShort {
+    pub face_value: Decimal,
+    pub checkpoint: u64,
+    pub open_time: u64,
+    pub maturity_time: u64,
+    pub initial_share_price: Decimal,
+}
+
+/// Pool state driven through `pool_core` with explicit time and share price
+#[derive(Clone)]
+pub struct SimPool {
+    pub share_reserves: Decimal,
+    pub bond_reserves: Decimal,
+    pub virtual_bond_reserves: Decimal,
+    pub zeta_adjustment: Decimal,
+    pub share_price: Decimal,
+    pub checkpoints: HashMap<u64, Checkpoint>,
+    pub current_checkpoint: u64,
+    pub short_collateral: Decimal,
+    pub lp_supply: Decimal,
+    pub governance_fees: Decimal,
+    pub longs: Vec<Option<SimLong>>,
+    pub shorts: Vec<Option<SimShort>>,
+    pub lp_contributed: Decimal,
+    pub lp_withdrawn: Decimal,
+    pub withdrawal_shares: Decimal,
+    pub trader_paid: Decimal,
+    pub trader_received: Decimal,
+}
+
+/// One row of the simulation report
+pub struct Snapshot {
+    pub time: u64,
+    pub event: String,
+    pub share_price: Decimal,
+    pub spot_rate: Decimal,
+    pub share_reserves: Decimal,
+    pub bond_reserves: Decimal,
+    pub lp_value: Decimal,
+    pub lp_pnl: Decimal,
+    pub trader_pnl: Decimal,
+    pub solvency_margin: Decimal,
+    pub governance_fees: Decimal,
+}
+
+impl Snapshot {
+    pub const CSV_HEADER: &'static str =
+        "time,event,share_price,spot_rate,share_reserves,bond_reserves,lp_value,lp_pnl,trader_pnl,solvency_margin,governance_fees";
+
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            self.time,
+            self.event,
+            self.share_price,
+            self.spot_rate,
+            self.share_reserves,
+            self.bond_reserves,
+            self.lp_value,
+            self.lp_pnl,
+            self.trader_pnl,
+            self.solvency_margin,
+            self.governance_fees
+        )
+    }
+}
+
+impl SimPool {
+    /// Seeds a pool the same way `create_pool` does
+    pub fn new(config: &SimConfig, initial_liquidity: Decimal, start_time: u64) -> Self {
+        validate_durations(config.checkpoint_duration, config.position_duration);
+        validate_liquidity_amount(initial_liquidity, config.min_share_reserves);
+        validate_initial_fixed_rate(config.initial_fixed_rate);
+
+        let mut checkpoints = HashMap::new();
+        let current_checkpoint = create_first_checkpoint(
+            &mut checkpoints,
+            config.checkpoint_duration,
+            Decimal::ONE,
+            start_time
+        );
+        let share_reserves = initial_liquidity - config.min_share_reserves;
+        let bond_reserves = calculate_bond_reserves_for_rate(
+            share_reserves,
+            Decimal::ONE,
+            config.initial_fixed_rate,
+            config.position_duration
+        );
+
+        Self {
+            share_reserves,
+            bond_reserves,
+            virtual_bond_reserves: bond_reserves,
+            zeta_adjustment: Decimal::ZERO,
+            share_price: Decimal::ONE,
+            checkpoints,
+            current_checkpoint,
+            short_collateral: Decimal::ZERO,
+            lp_supply: share_reserves,
+            governance_fees: Decimal::ZERO,
+            longs: Vec::new(),
+            shorts: Vec::new(),
+            lp_contributed: initial_liquidity,
+            lp_withdrawn: Decimal::ZERO,
+            withdrawal_shares: Decimal::ZERO,
+            trader_paid: Decimal::ZERO,
+            trader_received: Decimal::ZERO,
+        }
+    }
+
+    /// Moves the pool to `time` at the given share price
+    pub fn advance(&mut self, config: &SimConfig, time: u64, share_price: Decimal) {
+        assert!(share_price > Decimal::ZERO, "Share price must be positive");
+        self.share_price = share_price;
+        self.current_checkpoint = advance_checkpoint(
+            &mut self.checkpoints,
+            self.current_checkpoint,
+            config.checkpoint_duration,
+            share_price,
+            time
+        );
+    }
+
+    /// Replays one scripted step, returning an error for steps the pool would reject
+    pub fn apply(&mut self, config: &SimConfig, step: &Step) -> Result<(), String> {
+        let amount = match &step.amount {
+            Some(amount) => parse_decimal(amount, "amount")?,
+            None => Decimal::ZERO,
+        };
+
+        match step.action {
+            Action::OpenLong => {
+                let trade = apply_open_long(
+                    amount,
+                    &mut self.share_reserves,
+                    &mut self.bond_reserves,
+                    self.zeta_adjustment,
+                    self.share_price,
+                    &mut self.checkpoints,
+                    self.current_checkpoint,
+                    config.position_duration,
+                    &config.fees
+                );
+                self.governance_fees += trade.governance_fee;
+                self.trader_paid += amount;
+                self.longs.push(Some(SimLong {
+                    face_value: trade.face_value,
+                    checkpoint: self.current_checkpoint,
+                    open_time: step.time,
+                    maturity_time: trade.maturity_time,
+                }));
+            }
+            Action::CloseLong => {
+                let index = step.position.ok_or("close_long needs a position index")?;
+                let long = self.longs.get_mut(index).and_then(Option::take)
+                    .ok_or(format!("No open long at index {}", index))?;
+                let trade = apply_close_long(
+                    long.face_value,
+                    long.open_time,
+                    long.maturity_time,
+                    long.checkpoint,
+                    step.time,
+                    &mut self.share_reserves,
+                    &mut self.bond_reserves,
+                    &mut self.zeta_adjustment,
+                    self.share_price,
+                    &mut self.checkpoints,
+                    &config.fees
+                );
+                self.governance_fees += trade.governance_fee;
+                self.trader_received += trade.base_proceeds;
+            }
+            Action::OpenShort => {
+                if amount <= Decimal::ZERO {
+                    return Err("Face value must be positive".to_string());
+                }
+                let trade = apply_open_short(
+                    amount,
+                    &mut self.share_reserves,
+                    &mut self.bond_reserves,
+                    self.zeta_adjustment,
+                    self.share_price,
+                    &mut self.checkpoints,
+                    self.current_checkpoint,
+                    config.position_duration,
+                    &config.fees,
+                    &mut self.short_collateral
+                );
+                self.governance_fees += trade.governance_fee;
+                self.trader_paid += trade.total_deposit;
+                self.shorts.push(Some(SimShort {
+                    face_value: amount,
+                    checkpoint: self.current_checkpoint,
+                    open_time: step.time,
+                    maturity_time: trade.maturity_time,
+                    initial_share_price: self.share_price,
+                }));
+            }
+            Action::CloseShort => {
+                let index = step.position.ok_or("close_short needs a position index")?;
+                let short = self.shorts.get_mut(index).and_then(Option::take)
+                    .ok_or(format!("No open short at index {}", index))?;
+                let trade = apply_close_short(
+                    short.face_value,
+                    short.open_time,
+                    short.maturity_time,
+                    short.initial_share_price,
+                    short.checkpoint,
+                    step.time,
+                    &mut self.share_reserves,
+                    &mut self.bond_reserves,
+                    &mut self.zeta_adjustment,
+                    self.share_price,
+                    &mut self.checkpoints,
+                    &config.fees,
+                    &mut self.short_collateral
+                );
+                self.governance_fees += trade.governance_fee;
+                self.trader_received += trade.base_proceeds;
+            }
+            Action::AddLiquidity => {
+                let lp_tokens = apply_add_liquidity(
+                    amount,
+                    &mut self.share_reserves,
+                    &mut self.bond_reserves,
+                    &mut self.virtual_bond_reserves,
+                    self.share_price,
+                    self.lp_supply
+                );
+                self.lp_supply += lp_tokens;
+                self.lp_contributed += amount;
+            }
+            Action::RemoveLiquidity => {
+                if amount <= Decimal::ZERO || amount > self.lp_supply {
+                    return Err(format!("Cannot remove {} of {} LP tokens", amount, self.lp_supply));
+                }
+                let (immediate, withdrawal_shares) = apply_remove_liquidity(
+                    amount,
+                    &mut self.share_reserves,
+                    &mut self.bond_reserves,
+                    &mut self.virtual_bond_reserves,
+                    self.share_price,
+                    self.lp_supply,
+                    config.min_share_reserves,
+                    &self.checkpoints,
+                    self.current_checkpoint,
+                    config.checkpoint_duration,
+                    config.position_duration
+                );
+                self.lp_supply -= amount;
+                self.lp_withdrawn += immediate;
+                self.withdrawal_shares += withdrawal_shares;
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks every open position to market by closing it on a copy of the pool
+    fn open_position_value(&self, config: &SimConfig, time: u64) -> Decimal {
+        let mut pool = self.clone();
+        let mut value = Decimal::ZERO;
+
+        for long in self.longs.iter().flatten() {
+            value += apply_close_long(
+                long.face_value,
+                long.open_time,
+                long.maturity_time,
+                long.checkpoint,
+                time,
+                &mut pool.share_reserves,
+                &mut pool.bond_reserves,
+                &mut pool.zeta_adjustment,
+                pool.share_price,
+                &mut pool.checkpoints,
+                &config.fees
+            ).base_proceeds;
+        }
+
+        for short in self.shorts.iter().flatten() {
+            value += apply_close_short(
+                short.face_value,
+                short.open_time,
+                short.maturity_time,
+                short.initial_share_price,
+                short.checkpoint,
+                time,
+                &mut pool.share_reserves,
+                &mut pool.bond_reserves,
+                &mut pool.zeta_adjustment,
+                pool.share_price,
+                &mut pool.checkpoints,
+                &config.fees,
+                &mut pool.short_collateral
+            ).base_proceeds;
+        }
+
+        value
+    }
+
+    /// Reports LP and trader PnL and solvency at `time`
+    pub fn snapshot(&self, config: &SimConfig, time: u64, event: &str) -> Snapshot {
+        let effective_shares = calculate_effective_share_reserves(self.share_reserves, self.zeta_adjustment);
+        let lp_present_value = calculate_lp_present_value(
+            self.share_reserves,
+            self.bond_reserves,
+            self.virtual_bond_reserves,
+            self.share_price,
+            self.lp_supply
+        );
+        let lp_value = lp_present_value * self.lp_supply + self.withdrawal_shares;
+        let solvency_requirement = calculate_solvency_requirement(
+            &self.checkpoints,
+            self.current_checkpoint,
+            config.checkpoint_duration,
+            config.position_duration
+        );
+
+        Snapshot {
+            time,
+            event: event.to_string(),
+            share_price: self.share_price,
+            spot_rate: calculate_spot_rate(effective_shares, self.bond_reserves, self.share_price),
+            share_reserves: self.share_reserves,
+            bond_reserves: self.bond_reserves,
+            lp_value,
+            lp_pnl: lp_value + self.lp_withdrawn - self.lp_contributed,
+            trader_pnl: self.trader_received + self.open_position_value(config, time) - self.trader_paid,
+            solvency_margin: (self.share_reserves - config.min_share_reserves) * self.share_price - solvency_requirement,
+            governance_fees: self.governance_fees,
+        }
+    }
+}
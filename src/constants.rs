@@ -8,25 +8,35 @@ pub const DEFAULT_MIN_SHARE_RESERVES: Decimal = dec!("1000");
 #[allow(dead_code)]
 pub const MAX_FEE_PERCENTAGE: Decimal = dec!("1.0");
 
-/// Default new bond fee percentage
+/// Default curve fee percentage
 #[allow(dead_code)]
-pub const DEFAULT_NEW_BOND_FEE: Decimal = dec!("0.01"); // 1%
+pub const DEFAULT_CURVE_FEE: Decimal = dec!("0.01"); // 1%
 
-/// Default matured bond fee percentage  
+/// Default flat fee percentage
 #[allow(dead_code)]
-pub const DEFAULT_MATURED_BOND_FEE: Decimal = dec!("0.005"); // 0.5%
+pub const DEFAULT_FLAT_FEE: Decimal = dec!("0.005"); // 0.5%
 
-/// Default governance fee percentage
+/// Default governance share of the curve fee
 #[allow(dead_code)]
-pub const DEFAULT_GOVERNANCE_FEE: Decimal = dec!("0.1"); // 10% of fees
+pub const DEFAULT_GOVERNANCE_CURVE_FEE: Decimal = dec!("0.1"); // 10% of curve fees
+
+/// Default governance share of the flat fee
+#[allow(dead_code)]
+pub const DEFAULT_GOVERNANCE_FLAT_FEE: Decimal = dec!("0.1"); // 10% of flat fees
 
 /// Default zombie governance fee percentage
 #[allow(dead_code)]
 pub const DEFAULT_ZOMBIE_GOVERNANCE_FEE: Decimal = dec!("0.1"); // 10% of zombie interest
 
+/// Default share of the governance fee paid to the referrer of a trade
+pub const DEFAULT_REFERRAL_SHARE: Decimal = dec!("0.2"); // 20% of governance fees
+
 /// Default checkpoint duration (1 week in seconds)
 pub const DEFAULT_CHECKPOINT_DURATION: u64 = 604800; // 7 * 24 * 60 * 60
 
+/// Seconds in a year, the unit fixed rates are annualized over
+pub const SECONDS_PER_YEAR: u64 = 31536000; // 365 * 24 * 60 * 60
+
 /// Default position duration (1 year in seconds)
 #[allow(dead_code)]
 pub const DEFAULT_POSITION_DURATION: u64 = 31536000; // 365 * 24 * 60 * 60
@@ -37,6 +47,18 @@ pub const MIN_POSITION_DURATION: u64 = DEFAULT_CHECKPOINT_DURATION;
 /// Maximum position duration (10 years in seconds)
 pub const MAX_POSITION_DURATION: u64 = 315360000; // 10 * 365 * 24 * 60 * 60;
 
+/// Default delay between proposing and executing a fee change (one checkpoint)
+pub const DEFAULT_FEE_UPDATE_DELAY: u64 = DEFAULT_CHECKPOINT_DURATION;
+
+/// Shortest allowed fee update delay (about 1 day of 5-minute epochs)
+pub const MIN_FEE_UPDATE_DELAY: u64 = 288; // 24 * 60 / 5
+
+/// Number of rate observations kept before the oldest is overwritten
+pub const OBSERVATION_CAPACITY: u64 = 1024;
+
+/// Most rate observations returned by one history query
+pub const MAX_OBSERVATION_PAGE: u64 = 100;
+
 /// Mathematical constants
 pub mod math {
     use scrypto::prelude::*;
@@ -46,7 +68,6 @@ pub mod math {
     /// One value for calculations
     pub const ONE: Decimal = dec!("1");
     /// Small epsilon for floating point comparisons
-    #[allow(dead_code)]
     pub const EPSILON: Decimal = dec!("0.000001");
 }
 
@@ -57,6 +78,21 @@ pub mod validation {
     /// Minimum liquidity amount
     pub const MIN_LIQUIDITY: Decimal = dec!("1");
     /// Maximum reasonable fee percentage
-    #[allow(dead_code)]
     pub const MAX_REASONABLE_FEE: Decimal = dec!("0.1"); // 10%
+    /// Maximum curve fee a fee schedule may set
+    pub const MAX_CURVE_FEE: Decimal = MAX_REASONABLE_FEE;
+    /// Maximum flat fee a fee schedule may set
+    pub const MAX_FLAT_FEE: Decimal = MAX_REASONABLE_FEE;
+    /// Maximum governance share of the curve fee a fee schedule may set (5x the default)
+    pub const MAX_GOVERNANCE_CURVE_FEE: Decimal = dec!("0.5"); // 50%
+    /// Maximum governance share of the flat fee a fee schedule may set (5x the default)
+    pub const MAX_GOVERNANCE_FLAT_FEE: Decimal = dec!("0.5"); // 50%
+    /// Maximum governance share of zombie interest a fee schedule may set (5x the default)
+    pub const MAX_ZOMBIE_GOVERNANCE_FEE: Decimal = dec!("0.5"); // 50%
+    /// Maximum share of the governance fee a referrer may receive
+    pub const MAX_REFERRAL_SHARE: Decimal = dec!("0.5"); // 50%
+    /// Longest volatility window a dynamic fee may use, in checkpoints
+    pub const MAX_VOLATILITY_WINDOW: u64 = 52;
+    /// Highest fixed rate a pool may open at
+    pub const MAX_INITIAL_FIXED_RATE: Decimal = dec!("1"); // 100%
 }
@@ -31,6 +31,30 @@ pub fn trading_invariant_delta_z(delta_y: Decimal, z: Decimal, y: Decimal) -> De
     new_z - z
 }
 
+/// Implements the trading invariant for bonds sold into the pool
+/// This calculates the shares paid out by the pool when it receives Δy bonds
+/// 
+/// # Arguments
+/// * `delta_y` - Change in bond reserves (face value of bonds)
+/// * `z` - Current share reserves
+/// * `y` - Current bond reserves
+/// 
+/// # Returns
+/// * Change in share reserves (Δz) paid out by the pool
+pub fn trading_invariant_sell_delta_z(delta_y: Decimal, z: Decimal, y: Decimal) -> Decimal {
+    if z <= math::ZERO || y <= math::ZERO {
+        // If pool is empty, use 1:1 ratio
+        return delta_y;
+    }
+    
+    // For constant product curve: (z * y) = (z - Δz) * (y + Δy)
+    // Therefore: Δz = z - ((z * y) / (y + Δy))
+    let k = z * y;
+    let new_y = y + delta_y;
+    
+    z - (k / new_y)
+}
+
 /// Implements the maturity pricing function M(Δy)
 /// This calculates the share cost for bonds at maturity
 /// 
@@ -88,35 +112,64 @@ pub fn position_impact_delta_z(
     impact_new + impact_matured
 }
 
-/// Calculates fees for a position based on time remaining
+/// Implements the position impact function for bonds sold into the pool
+/// This is H(Δy, z, y, tr) with the curve leg priced by the sell-side invariant
 /// 
 /// # Arguments
 /// * `delta_y` - Face value of the position
+/// * `z` - Current share reserves
+/// * `y` - Current bond reserves
 /// * `time_remaining` - Time remaining until maturity (0 to 1)
-/// * `spot_rate` - Current spot rate
-/// * `new_bond_fee` - Fee percentage for newly minted bonds
-/// * `matured_bond_fee` - Fee percentage for matured bonds
+/// * `share_price` - Current share price
 /// 
 /// # Returns
-/// * Tuple of (new_bond_fee_amount, matured_bond_fee_amount)
-pub fn calculate_position_fees(
-    delta_y: Decimal,
+/// * Total shares paid out by the pool
+pub fn position_sale_delta_z(
+    delta_y: Decimal, 
+    z: Decimal, 
+    y: Decimal, 
     time_remaining: Decimal,
-    spot_rate: Decimal,
-    new_bond_fee: Decimal,
-    matured_bond_fee: Decimal
-) -> (Decimal, Decimal) {
-    // Calculate fees for new and matured bonds
-    let new_bond_fee_amount = new_bond_fee * 
-        (math::ONE - spot_rate) * 
-        delta_y * 
-        time_remaining;
-        
-    let matured_bond_fee_amount = matured_bond_fee * 
-        delta_y * 
-        (math::ONE - time_remaining);
-        
-    (new_bond_fee_amount, matured_bond_fee_amount)
+    share_price: Decimal
+) -> Decimal {
+    let new_bonds = delta_y * time_remaining;
+    let matured_bonds = delta_y * (math::ONE - time_remaining);
+    
+    let impact_new = if new_bonds > math::ZERO {
+        trading_invariant_sell_delta_z(new_bonds, z, y)
+    } else {
+        math::ZERO
+    };
+    
+    let impact_matured = if matured_bonds > math::ZERO {
+        maturity_pricing_delta_z(matured_bonds, share_price)
+    } else {
+        math::ZERO
+    };
+    
+    impact_new + impact_matured
+}
+
+/// Calculates the bond reserves that price bonds at a fixed rate on the curve
+/// 
+/// A bond costs `c * z / y` in base, so a price of `1 / (1 + r * t)` takes
+/// `y = c * z * (1 + r * t)`, with `t` the position duration in years.
+/// 
+/// # Arguments
+/// * `share_reserves` - Share reserves (z)
+/// * `share_price` - Current share price (c)
+/// * `fixed_rate` - Annualized fixed rate (r)
+/// * `position_duration` - Duration of positions in seconds
+/// 
+/// # Returns
+/// * Bond reserves pricing bonds at the fixed rate
+pub fn calculate_bond_reserves_for_rate(
+    share_reserves: Decimal,
+    share_price: Decimal,
+    fixed_rate: Decimal,
+    position_duration: u64
+) -> Decimal {
+    let term = Decimal::from(position_duration) / Decimal::from(SECONDS_PER_YEAR);
+    share_reserves * share_price * (math::ONE + fixed_rate * term)
 }
 
 /// Calculates the LP present value for liquidity operations
@@ -125,6 +178,7 @@ pub fn calculate_position_fees(
 /// # Arguments
 /// * `share_reserves` - Current share reserves
 /// * `bond_reserves` - Current bond reserves
+/// * `virtual_bond_reserves` - Bond reserves seeded to price the curve, owned by no one
 /// * `share_price` - Current share price
 /// * `active_lp_shares` - Total active LP shares
 /// 
@@ -133,6 +187,7 @@ pub fn calculate_position_fees(
 pub fn calculate_lp_present_value(
     share_reserves: Decimal,
     bond_reserves: Decimal,
+    virtual_bond_reserves: Decimal,
     share_price: Decimal,
     active_lp_shares: Decimal
 ) -> Decimal {
@@ -142,7 +197,7 @@ pub fn calculate_lp_present_value(
     
     // Calculate total pool value
     let share_value = share_reserves * share_price;
-    let bond_value = bond_reserves; // Bonds are valued at face value
+    let bond_value = bond_reserves - virtual_bond_reserves; // Bonds are valued at face value
     let total_value = share_value + bond_value;
     
     // Return value per LP share
@@ -195,8 +250,8 @@ pub fn calculate_short_deposit(
     share_price: Decimal,
     time_remaining: Decimal
 ) -> Decimal {
-    // Calculate impact on reserves
-    let delta_z = position_impact_delta_z(
+    // Calculate impact on reserves (the pool buys the shorted bonds)
+    let delta_z = position_sale_delta_z(
         face_value,
         effective_share_reserves,
         bond_reserves,
@@ -205,8 +260,9 @@ pub fn calculate_short_deposit(
     );
     
     // Calculate required deposit
-    // For shorts, deposit = (face_value * share_price) - (delta_z * share_price)
-    let collateral_required = face_value * share_price;
+    // Face value is paid in base at maturity, so the short backs it in base:
+    // deposit = face_value - (delta_z * share_price)
+    let collateral_required = face_value;
     let proceeds_received = delta_z * share_price;
     
     collateral_required - proceeds_received
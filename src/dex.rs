@@ -2,22 +2,28 @@ use scrypto::prelude::*;
 use crate::types::*;
 use crate::constants::*;
 use crate::helpers::*;
-use crate::curves::*;
 use crate::events::*;
+use crate::pool_core::*;
+use crate::positions::*;
+use crate::fees::*;
 
 /// Opens a long position in the Hyperdrive AMM
 /// 
 /// # Arguments
 /// * `base_tokens` - Bucket of base tokens to use for the position
 /// * `pool_state` - Current pool state (mutable)
+/// * `pool_id` - ID of the pool, recorded on the position
 /// * `long_positions_resource` - Resource address for long position NFTs
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+/// * `image_base_url` - Base URL of the NFT image service
 /// * `governance_vault` - Vault for governance fees (mutable)
 /// * `yield_source_vault` - Vault for yield source tokens (mutable)
 /// 
 /// # Returns
 /// * Long position NFT bucket
 pub fn open_long_position(
-    base_tokens: Bucket,
+    mut base_tokens: Bucket,
     share_reserves: &mut Decimal,
     bond_reserves: &mut Decimal,
     zeta_adjustment: &mut Decimal,
@@ -26,9 +32,12 @@ pub fn open_long_position(
     current_checkpoint: &mut u64,
     checkpoint_duration: u64,
     position_duration: u64,
-    new_bond_fee: Decimal,
-    governance_fee: Decimal,
+    fees: &FeeSchedule,
+    pool_id: u64,
     long_positions_resource: ResourceAddress,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    image_base_url: &str,
     yield_source: ResourceAddress,
     governance_vault: &mut Vault,
     yield_source_vault: &mut Vault
@@ -44,60 +53,41 @@ pub fn open_long_position(
         share_price
     );
     
-    // Calculate current time and maturity
+    // Apply the trade to the pool
     let current_time = Runtime::current_epoch().number();
-    let maturity_time = *current_checkpoint + position_duration;
-    let time_remaining = math::ONE; // Full term for new positions
-    
-    // Convert base tokens to shares
-    let base_amount = base_tokens.amount();
-    let share_amount = base_amount / share_price;
-    
-    // Calculate effective share reserves
-    let effective_shares = calculate_effective_share_reserves(*share_reserves, *zeta_adjustment);
-    
-    // Calculate face value using trading curve
-    let face_value = calculate_long_face_value(share_amount, effective_shares, *bond_reserves);
-    
-    // Calculate fees
-    let spot_rate = calculate_spot_rate(effective_shares, *bond_reserves, share_price);
-    let (new_bond_fee_amount, _) = calculate_position_fees(
-        face_value, 
-        time_remaining, 
-        spot_rate, 
-        new_bond_fee, 
-        math::ZERO
+    let trade = apply_open_long(
+        base_tokens.amount(),
+        share_reserves,
+        bond_reserves,
+        *zeta_adjustment,
+        share_price,
+        checkpoints,
+        *current_checkpoint,
+        position_duration,
+        fees
     );
     
-    let total_fee = new_bond_fee_amount;
-    let governance_fee_amount = total_fee * governance_fee;
-    let lp_fee = total_fee - governance_fee_amount;
-    
-    // Adjust face value for fees
-    let adjusted_face_value = face_value - lp_fee;
-    
-    // Update pool state
-    *share_reserves += share_amount;
-    *bond_reserves -= adjusted_face_value;
-    
-    // Update checkpoint data
-    update_checkpoint_long_opened(checkpoints, *current_checkpoint, adjusted_face_value, maturity_time);
-    
     // Create position NFT
-    let position_data = LongPosition {
-        face_value: adjusted_face_value,
-        checkpoint: *current_checkpoint,
-        open_time: current_time,
-        maturity_time,
-    };
+    let position_data = new_long_position(
+        &trade,
+        base_tokens.amount(),
+        share_price,
+        *current_checkpoint,
+        current_time,
+        pool_id,
+        image_base_url
+    );
     
-    let position_nft = NonFungibleResourceManager::from(long_positions_resource)
-        .mint_ruid_non_fungible(position_data).into();
+    let position_nft = mint_long_position(
+        position_data,
+        long_positions_resource,
+        position_counter,
+        position_summaries
+    );
     
-    // Handle governance fee
-    if governance_fee_amount > math::ZERO {
-        let governance_fee_bucket = yield_source_vault.take(governance_fee_amount / share_price);
-        governance_vault.put(governance_fee_bucket);
+    // Handle governance fee (fees are denominated in base)
+    if trade.governance_fee > math::ZERO {
+        governance_vault.put(base_tokens.take(trade.governance_fee));
     }
     
     // Deposit base tokens
@@ -106,18 +96,22 @@ pub fn open_long_position(
     position_nft
 }
 
-/// Closes a long position in the Hyperdrive AMM
+/// Closes all or part of a long position in the Hyperdrive AMM
 /// 
 /// # Arguments
 /// * `position_nft` - Long position NFT to close
+/// * `bond_amount` - Face value to close (up to the position's face value)
 /// * `pool_state` - Current pool state (mutable)
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `position_summaries` - Summary per position NFT
 /// * `governance_vault` - Vault for governance fees (mutable)
 /// * `yield_source_vault` - Vault for yield source tokens (mutable)
 /// 
 /// # Returns
-/// * Base token proceeds bucket
+/// * Tuple of (base token proceeds, remaining position NFT if partially closed)
 pub fn close_long_position(
     position_nft: Bucket,
+    bond_amount: Decimal,
     share_reserves: &mut Decimal,
     bond_reserves: &mut Decimal,
     zeta_adjustment: &mut Decimal,
@@ -125,13 +119,13 @@ pub fn close_long_position(
     checkpoints: &mut HashMap<u64, Checkpoint>,
     current_checkpoint: &mut u64,
     checkpoint_duration: u64,
-    new_bond_fee: Decimal,
-    matured_bond_fee: Decimal,
-    governance_fee: Decimal,
+    fees: &FeeSchedule,
     long_positions_resource: ResourceAddress,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    image_base_url: &str,
     governance_vault: &mut Vault,
     yield_source_vault: &mut Vault
-) -> Bucket {
+) -> (Bucket, Option<Bucket>) {
     // Validate input
     validate_single_nft(&position_nft, long_positions_resource, "long position");
     
@@ -145,70 +139,184 @@ pub fn close_long_position(
     
     // Get position data
     let position_data: LongPosition = position_nft.as_non_fungible().non_fungible().data();
+    validate_close_amount(bond_amount, position_data.face_value);
     
-    // Calculate time remaining
-    let current_time = Runtime::current_epoch().number();
-    let time_remaining = calculate_time_remaining(
-        current_time, 
-        position_data.open_time, 
-        position_data.maturity_time
+    // Apply the trade to the pool
+    let trade = apply_close_long(
+        bond_amount,
+        position_data.open_time,
+        position_data.maturity_time,
+        position_data.checkpoint,
+        Runtime::current_epoch().number(),
+        share_reserves,
+        bond_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        fees
     );
     
-    // Calculate proceeds from closing the position
-    let face_value = position_data.face_value;
-    let effective_shares = calculate_effective_share_reserves(*share_reserves, *zeta_adjustment);
-    
-    // Calculate impact on reserves
-    let delta_z = position_impact_delta_z(
-        face_value,
-        effective_shares,
-        *bond_reserves,
-        time_remaining,
-        share_price
+    // Burn the position NFT, or shrink it to the unclosed face value
+    let remaining_position = reduce_position(
+        position_nft,
+        position_data.face_value,
+        bond_amount,
+        position_data.cost(),
+        "Long",
+        position_data.maturity_time,
+        image_base_url,
+        long_positions_resource,
+        position_summaries
     );
     
-    // Calculate fees
-    let spot_rate = calculate_spot_rate(effective_shares, *bond_reserves, share_price);
-    let (new_bond_fee_amount, matured_bond_fee_amount) = calculate_position_fees(
-        face_value, 
-        time_remaining, 
-        spot_rate, 
-        new_bond_fee, 
-        matured_bond_fee
-    );
+    // Handle governance fee (fees are denominated in base)
+    if trade.governance_fee > math::ZERO {
+        let governance_fee_bucket = yield_source_vault.take(trade.governance_fee);
+        governance_vault.put(governance_fee_bucket);
+    }
+    
+    // Return base proceeds
+    (yield_source_vault.take(trade.base_proceeds), remaining_position)
+}
+
+/// Previews the base proceeds of closing `bond_amount` of a long position
+/// 
+/// # Arguments
+/// * `position_id` - Local ID of the long position NFT
+/// * `bond_amount` - Face value to close (up to the position's face value)
+/// * `pool_state` - Current pool state
+/// * `long_positions_resource` - Resource address for long position NFTs
+/// 
+/// # Returns
+/// * Base proceeds a close would pay now, net of fees
+pub fn preview_close_long_position(
+    position_id: &NonFungibleLocalId,
+    bond_amount: Decimal,
+    share_reserves: Decimal,
+    bond_reserves: Decimal,
+    zeta_adjustment: Decimal,
+    share_price: Decimal,
+    checkpoints: &HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule,
+    long_positions_resource: ResourceAddress
+) -> Decimal {
+    let position_data: LongPosition = NonFungibleResourceManager::from(long_positions_resource)
+        .get_non_fungible_data(position_id);
+    validate_close_amount(bond_amount, position_data.face_value);
     
-    let total_fee = new_bond_fee_amount + matured_bond_fee_amount;
-    let governance_fee_amount = total_fee * governance_fee;
-    let lp_fee = total_fee - governance_fee_amount;
+    preview_close_long(
+        bond_amount,
+        position_data.open_time,
+        position_data.maturity_time,
+        position_data.checkpoint,
+        Runtime::current_epoch().number(),
+        share_reserves,
+        bond_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        fees
+    ).base_proceeds
+}
+
+/// Values a long position at what a full close would pay now
+/// 
+/// # Arguments
+/// * `position_data` - Data of the long position NFT
+/// * `pool_state` - Current pool state
+/// 
+/// # Returns
+/// * PositionValue of the long
+pub fn long_position_value(
+    position_data: &LongPosition,
+    share_reserves: Decimal,
+    bond_reserves: Decimal,
+    zeta_adjustment: Decimal,
+    share_price: Decimal,
+    checkpoints: &HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule
+) -> PositionValue {
+    let trade = preview_close_long(
+        position_data.face_value,
+        position_data.open_time,
+        position_data.maturity_time,
+        position_data.checkpoint,
+        Runtime::current_epoch().number(),
+        share_reserves,
+        bond_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        fees
+    );
     
-    // Calculate base proceeds
-    let base_proceeds = delta_z * share_price - lp_fee;
+    PositionValue {
+        face_value: position_data.face_value,
+        close_proceeds: trade.base_proceeds,
+        time_remaining: trade.time_remaining,
+        accrued_interest: math::ZERO,
+        cost_basis: position_data.base_paid,
+        pnl: trade.base_proceeds - position_data.base_paid,
+    }
+}
+
+/// Values a short position at what a full close would pay now
+/// 
+/// # Arguments
+/// * `position_data` - Data of the short position NFT
+/// * `pool_state` - Current pool state
+/// * `short_collateral` - Outstanding short collateral in shares
+/// 
+/// # Returns
+/// * PositionValue of the short
+pub fn short_position_value(
+    position_data: &ShortPosition,
+    share_reserves: Decimal,
+    bond_reserves: Decimal,
+    zeta_adjustment: Decimal,
+    share_price: Decimal,
+    checkpoints: &HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule,
+    short_collateral: Decimal
+) -> PositionValue {
+    let current_time = Runtime::current_epoch().number();
     
-    // Update pool state
-    *share_reserves -= delta_z;
-    *bond_reserves += face_value * time_remaining;
+    let trade = preview_close_short(
+        position_data.face_value,
+        position_data.open_time,
+        position_data.maturity_time,
+        position_data.initial_share_price,
+        position_data.checkpoint,
+        current_time,
+        share_reserves,
+        bond_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        fees,
+        short_collateral
+    );
     
-    // Update zeta adjustment for matured portion
-    let matured_impact = maturity_pricing_delta_z(
-        face_value * (math::ONE - time_remaining), 
+    // Interest stops accruing to the short at maturity, as in the close itself,
+    // at the maturity price a close now would mint if none was
+    let accrual_share_price = short_accrual_share_price(
+        &mut checkpoints.clone(),
+        position_data.maturity_time,
+        current_time,
         share_price
     );
-    *zeta_adjustment -= matured_impact - (matured_bond_fee_amount * (math::ONE - governance_fee));
-    
-    // Update checkpoint data
-    update_checkpoint_long_closed(checkpoints, position_data.checkpoint, face_value, time_remaining);
+    let accrued_interest = (position_data.face_value
+        * (accrual_share_price / position_data.initial_share_price - math::ONE))
+        .max(math::ZERO);
     
-    // Burn position NFT
-    position_nft.burn();
-    
-    // Handle governance fee
-    if governance_fee_amount > math::ZERO {
-        let governance_fee_bucket = yield_source_vault.take(governance_fee_amount / share_price);
-        governance_vault.put(governance_fee_bucket);
+    PositionValue {
+        face_value: position_data.face_value,
+        close_proceeds: trade.base_proceeds,
+        time_remaining: trade.time_remaining,
+        accrued_interest,
+        cost_basis: position_data.base_paid,
+        pnl: trade.base_proceeds - position_data.base_paid,
     }
-    
-    // Return base proceeds
-    yield_source_vault.take(base_proceeds)
 }
 
 /// Opens a short position in the Hyperdrive AMM
@@ -217,7 +325,12 @@ pub fn close_long_position(
 /// * `base_tokens` - Bucket of base tokens for collateral
 /// * `face_value` - Desired face value of the short position
 /// * `pool_state` - Current pool state (mutable)
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// * `pool_id` - ID of the pool, recorded on the position
 /// * `short_positions_resource` - Resource address for short position NFTs
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+/// * `image_base_url` - Base URL of the NFT image service
 /// * `governance_vault` - Vault for governance fees (mutable)
 /// * `yield_source_vault` - Vault for yield source tokens (mutable)
 /// 
@@ -234,9 +347,13 @@ pub fn open_short_position(
     current_checkpoint: &mut u64,
     checkpoint_duration: u64,
     position_duration: u64,
-    new_bond_fee: Decimal,
-    governance_fee: Decimal,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal,
+    pool_id: u64,
     short_positions_resource: ResourceAddress,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    image_base_url: &str,
     yield_source: ResourceAddress,
     governance_vault: &mut Vault,
     yield_source_vault: &mut Vault
@@ -253,81 +370,51 @@ pub fn open_short_position(
         share_price
     );
     
-    // Calculate current time and maturity
+    // Apply the trade to the pool
     let current_time = Runtime::current_epoch().number();
-    let maturity_time = *current_checkpoint + position_duration;
-    let time_remaining = math::ONE; // Full term for new positions
-    
-    // Calculate effective share reserves
-    let effective_shares = calculate_effective_share_reserves(*share_reserves, *zeta_adjustment);
-    
-    // Calculate required deposit
-    let deposit_required = calculate_short_deposit(
+    let trade = apply_open_short(
         face_value,
-        effective_shares,
-        *bond_reserves,
+        share_reserves,
+        bond_reserves,
+        *zeta_adjustment,
         share_price,
-        time_remaining
+        checkpoints,
+        *current_checkpoint,
+        position_duration,
+        fees,
+        short_collateral
     );
     
-    // Calculate fees
-    let spot_rate = calculate_spot_rate(effective_shares, *bond_reserves, share_price);
-    let (new_bond_fee_amount, _) = calculate_position_fees(
-        face_value, 
-        time_remaining, 
-        spot_rate, 
-        new_bond_fee, 
-        math::ZERO
-    );
-    
-    let total_fee = new_bond_fee_amount;
-    let governance_fee_amount = total_fee * governance_fee;
-    let lp_fee = total_fee - governance_fee_amount;
-    
-    // Add fees to required deposit
-    let total_deposit_required = deposit_required + lp_fee;
-    
     // Ensure sufficient deposit
     assert!(
-        base_tokens.amount() >= total_deposit_required,
+        base_tokens.amount() >= trade.total_deposit,
         "Insufficient deposit for short position"
     );
     
-    // Calculate impact on reserves
-    let delta_z = position_impact_delta_z(
-        face_value,
-        effective_shares,
-        *bond_reserves,
-        time_remaining,
-        share_price
-    );
-    
-    // Update pool state
-    *share_reserves -= delta_z - (lp_fee / share_price);
-    *bond_reserves += face_value;
-    
-    // Update checkpoint data
-    update_checkpoint_short_opened(checkpoints, *current_checkpoint, face_value, maturity_time);
-    
     // Create position NFT
-    let position_data = ShortPosition {
+    let position_data = new_short_position(
         face_value,
-        checkpoint: *current_checkpoint,
-        open_time: current_time,
-        maturity_time,
-        initial_share_price: share_price,
-    };
+        &trade,
+        share_price,
+        *current_checkpoint,
+        current_time,
+        pool_id,
+        image_base_url
+    );
     
-    let position_nft = NonFungibleResourceManager::from(short_positions_resource)
-        .mint_ruid_non_fungible(position_data).into();
+    let position_nft = mint_short_position(
+        position_data,
+        short_positions_resource,
+        position_counter,
+        position_summaries
+    );
     
     // Take required deposit and return change
-    let change = base_tokens.take(base_tokens.amount() - total_deposit_required);
+    let change = base_tokens.take(base_tokens.amount() - trade.total_deposit);
     
-    // Handle governance fee
-    if governance_fee_amount > math::ZERO {
-        let governance_fee_bucket = yield_source_vault.take(governance_fee_amount / share_price);
-        governance_vault.put(governance_fee_bucket);
+    // Handle governance fee (fees are denominated in base)
+    if trade.governance_fee > math::ZERO {
+        governance_vault.put(base_tokens.take(trade.governance_fee));
     }
     
     // Deposit collateral
@@ -336,18 +423,23 @@ pub fn open_short_position(
     (position_nft, change)
 }
 
-/// Closes a short position in the Hyperdrive AMM
+/// Closes all or part of a short position in the Hyperdrive AMM
 /// 
 /// # Arguments
 /// * `position_nft` - Short position NFT to close
+/// * `bond_amount` - Face value to close (up to the position's face value)
 /// * `pool_state` - Current pool state (mutable)
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `position_summaries` - Summary per position NFT
 /// * `governance_vault` - Vault for governance fees (mutable)
 /// * `yield_source_vault` - Vault for yield source tokens (mutable)
 /// 
 /// # Returns
-/// * Base token proceeds bucket
+/// * Tuple of (base token proceeds, remaining position NFT if partially closed)
 pub fn close_short_position(
     position_nft: Bucket,
+    bond_amount: Decimal,
     share_reserves: &mut Decimal,
     bond_reserves: &mut Decimal,
     zeta_adjustment: &mut Decimal,
@@ -355,13 +447,14 @@ pub fn close_short_position(
     checkpoints: &mut HashMap<u64, Checkpoint>,
     current_checkpoint: &mut u64,
     checkpoint_duration: u64,
-    new_bond_fee: Decimal,
-    matured_bond_fee: Decimal,
-    governance_fee: Decimal,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal,
     short_positions_resource: ResourceAddress,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    image_base_url: &str,
     governance_vault: &mut Vault,
     yield_source_vault: &mut Vault
-) -> Bucket {
+) -> (Bucket, Option<Bucket>) {
     // Validate input
     validate_single_nft(&position_nft, short_positions_resource, "short position");
     
@@ -375,72 +468,625 @@ pub fn close_short_position(
     
     // Get position data
     let position_data: ShortPosition = position_nft.as_non_fungible().non_fungible().data();
+    validate_close_amount(bond_amount, position_data.face_value);
+    
+    // Apply the trade to the pool
+    let trade = apply_close_short(
+        bond_amount,
+        position_data.open_time,
+        position_data.maturity_time,
+        position_data.initial_share_price,
+        position_data.checkpoint,
+        Runtime::current_epoch().number(),
+        share_reserves,
+        bond_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        fees,
+        short_collateral
+    );
+    
+    // Burn the position NFT, or shrink it to the unclosed face value
+    let remaining_position = reduce_position(
+        position_nft,
+        position_data.face_value,
+        bond_amount,
+        position_data.cost(),
+        "Short",
+        position_data.maturity_time,
+        image_base_url,
+        short_positions_resource,
+        position_summaries
+    );
     
-    // Calculate time remaining
+    // Handle governance fee (fees are denominated in base)
+    if trade.governance_fee > math::ZERO {
+        let governance_fee_bucket = yield_source_vault.take(trade.governance_fee);
+        governance_vault.put(governance_fee_bucket);
+    }
+    
+    // Return base proceeds
+    (yield_source_vault.take(trade.base_proceeds), remaining_position)
+}
+
+/// Closes every long position in a bucket, trading once per checkpoint
+/// 
+/// # Arguments
+/// * `position_nfts` - Long position NFTs to close
+/// * `pool_state` - Current pool state (mutable)
+/// * `position_summaries` - Summary per position NFT
+/// * `governance_vault` - Vault for governance fees (mutable)
+/// * `yield_source_vault` - Vault for yield source tokens (mutable)
+/// 
+/// # Returns
+/// * Tuple of (base token proceeds, per-NFT breakdown)
+pub fn close_long_positions(
+    position_nfts: Bucket,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: &mut u64,
+    checkpoint_duration: u64,
+    fees: &FeeSchedule,
+    long_positions_resource: ResourceAddress,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    governance_vault: &mut Vault,
+    yield_source_vault: &mut Vault
+) -> (Bucket, Vec<ClosedPosition>) {
+    // Validate input
+    validate_nft_batch(&position_nfts, long_positions_resource, "long position");
+    
+    // Update checkpoint if needed
+    *current_checkpoint = update_checkpoint_if_needed(
+        checkpoints, 
+        *current_checkpoint, 
+        checkpoint_duration, 
+        share_price
+    );
+    
+    // Group positions by checkpoint so each maturity hits the curve once
+    let mut groups: BTreeMap<u64, Vec<(NonFungibleLocalId, CloseLot, Decimal)>> = BTreeMap::new();
+    for nft in position_nfts.as_non_fungible().non_fungibles::<LongPosition>() {
+        let data = nft.data();
+        groups.entry(data.checkpoint).or_default().push((nft.local_id().clone(), CloseLot {
+            face_value: data.face_value,
+            open_time: data.open_time,
+            maturity_time: data.maturity_time,
+            initial_share_price: math::ZERO,
+        }, data.base_paid));
+    }
+    
+    // Apply one trade per checkpoint
     let current_time = Runtime::current_epoch().number();
-    let time_remaining = calculate_time_remaining(
-        current_time, 
-        position_data.open_time, 
-        position_data.maturity_time
+    let mut breakdown = Vec::new();
+    for (checkpoint, positions) in groups {
+        let lots: Vec<CloseLot> = positions.iter().map(|(_, lot, _)| lot.clone()).collect();
+        let trades = apply_close_long_lots(
+            &lots,
+            checkpoint,
+            current_time,
+            share_reserves,
+            bond_reserves,
+            zeta_adjustment,
+            share_price,
+            checkpoints,
+            fees
+        );
+        breakdown.extend(closed_positions(positions, trades));
+    }
+    
+    // Burn position NFTs
+    burn_positions(position_nfts, position_summaries);
+    
+    settle_batch(breakdown, governance_vault, yield_source_vault)
+}
+
+/// Closes every short position in a bucket, trading once per checkpoint
+/// 
+/// # Arguments
+/// * `position_nfts` - Short position NFTs to close
+/// * `pool_state` - Current pool state (mutable)
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// * `position_summaries` - Summary per position NFT
+/// * `governance_vault` - Vault for governance fees (mutable)
+/// * `yield_source_vault` - Vault for yield source tokens (mutable)
+/// 
+/// # Returns
+/// * Tuple of (base token proceeds, per-NFT breakdown)
+pub fn close_short_positions(
+    position_nfts: Bucket,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: &mut u64,
+    checkpoint_duration: u64,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal,
+    short_positions_resource: ResourceAddress,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    governance_vault: &mut Vault,
+    yield_source_vault: &mut Vault
+) -> (Bucket, Vec<ClosedPosition>) {
+    // Validate input
+    validate_nft_batch(&position_nfts, short_positions_resource, "short position");
+    
+    // Update checkpoint if needed
+    *current_checkpoint = update_checkpoint_if_needed(
+        checkpoints, 
+        *current_checkpoint, 
+        checkpoint_duration, 
+        share_price
     );
     
-    // Calculate proceeds from closing the position
-    let face_value = position_data.face_value;
-    let effective_shares = calculate_effective_share_reserves(*share_reserves, *zeta_adjustment);
+    // Group positions by checkpoint so each maturity hits the curve once
+    let mut groups: BTreeMap<u64, Vec<(NonFungibleLocalId, CloseLot, Decimal)>> = BTreeMap::new();
+    for nft in position_nfts.as_non_fungible().non_fungibles::<ShortPosition>() {
+        let data = nft.data();
+        groups.entry(data.checkpoint).or_default().push((nft.local_id().clone(), CloseLot {
+            face_value: data.face_value,
+            open_time: data.open_time,
+            maturity_time: data.maturity_time,
+            initial_share_price: data.initial_share_price,
+        }, data.base_paid));
+    }
     
-    // Calculate impact on reserves
-    let delta_z = position_impact_delta_z(
-        face_value,
-        effective_shares,
-        *bond_reserves,
-        time_remaining,
+    // Apply one trade per checkpoint
+    let current_time = Runtime::current_epoch().number();
+    let mut breakdown = Vec::new();
+    for (checkpoint, positions) in groups {
+        let lots: Vec<CloseLot> = positions.iter().map(|(_, lot, _)| lot.clone()).collect();
+        let trades = apply_close_short_lots(
+            &lots,
+            checkpoint,
+            current_time,
+            share_reserves,
+            bond_reserves,
+            zeta_adjustment,
+            share_price,
+            checkpoints,
+            fees,
+            short_collateral
+        );
+        breakdown.extend(closed_positions(positions, trades));
+    }
+    
+    // Burn position NFTs
+    burn_positions(position_nfts, position_summaries);
+    
+    settle_batch(breakdown, governance_vault, yield_source_vault)
+}
+
+/// Rolls a matured long position into a new long
+/// 
+/// # Arguments
+/// * `position_nft` - Long position NFT to roll over
+/// * `min_face_value` - Minimum face value accepted for the new position
+/// * `pool_state` - Current pool state (mutable)
+/// * `pool_id` - ID of the pool, recorded on the position
+/// * `long_positions_resource` - Resource address for long position NFTs
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `governance_vault` - Vault for governance fees (mutable)
+/// * `yield_source_vault` - Vault for yield source tokens (mutable)
+/// 
+/// # Returns
+/// * Tuple of (new long position NFT, base dust bucket)
+pub fn rollover_long_position(
+    position_nft: Bucket,
+    min_face_value: Decimal,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: &mut u64,
+    checkpoint_duration: u64,
+    position_duration: u64,
+    fees: &FeeSchedule,
+    pool_id: u64,
+    long_positions_resource: ResourceAddress,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    image_base_url: &str,
+    governance_vault: &mut Vault,
+    yield_source_vault: &mut Vault
+) -> (Bucket, Bucket) {
+    // Validate input
+    validate_single_nft(&position_nft, long_positions_resource, "long position");
+    
+    let position_data: LongPosition = position_nft.as_non_fungible().non_fungible().data();
+    let current_time = Runtime::current_epoch().number();
+    validate_rollover_maturity(current_time, position_data.maturity_time);
+    
+    // Update checkpoint if needed
+    *current_checkpoint = update_checkpoint_if_needed(
+        checkpoints, 
+        *current_checkpoint, 
+        checkpoint_duration, 
         share_price
     );
     
-    // Calculate fees
-    let spot_rate = calculate_spot_rate(effective_shares, *bond_reserves, share_price);
-    let (new_bond_fee_amount, matured_bond_fee_amount) = calculate_position_fees(
-        face_value, 
-        time_remaining, 
-        spot_rate, 
-        new_bond_fee, 
-        matured_bond_fee
+    // Settle the old position and open the new one in a single step
+    let (base_rolled, trade) = apply_rollover_long(
+        position_data.face_value,
+        position_data.open_time,
+        position_data.maturity_time,
+        position_data.checkpoint,
+        current_time,
+        share_reserves,
+        bond_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        *current_checkpoint,
+        position_duration,
+        fees
+    );
+    assert!(trade.face_value >= min_face_value, "Rollover face value below minimum");
+    
+    burn_positions(position_nft, position_summaries);
+    
+    // Create position NFT
+    let position_data = new_long_position(
+        &trade,
+        base_rolled,
+        share_price,
+        *current_checkpoint,
+        current_time,
+        pool_id,
+        image_base_url
+    );
+    
+    let new_position_nft = mint_long_position(
+        position_data,
+        long_positions_resource,
+        position_counter,
+        position_summaries
     );
     
-    let total_fee = new_bond_fee_amount + matured_bond_fee_amount;
-    let governance_fee_amount = total_fee * governance_fee;
-    let lp_fee = total_fee - governance_fee_amount;
+    // Handle governance fee (fees are denominated in base)
+    if trade.governance_fee > math::ZERO {
+        governance_vault.put(yield_source_vault.take(trade.governance_fee));
+    }
     
-    // Calculate base proceeds
-    // For shorts: proceeds = (face_value * current_share_price / initial_share_price) - cost - fees
-    let share_price_ratio = share_price / position_data.initial_share_price;
-    let base_proceeds = (face_value * share_price_ratio) - 
-                       (delta_z * share_price) - 
-                       lp_fee;
+    // The whole settlement is reinvested, so no base leaves the pool
+    (new_position_nft, Bucket::new(yield_source_vault.resource_address()))
+}
+
+/// Rolls a matured short position into a new short
+/// 
+/// The settled interest is reused as deposit; `base_tokens` covers the rest.
+/// 
+/// # Arguments
+/// * `position_nft` - Short position NFT to roll over
+/// * `new_face_value` - Face value of the new short position
+/// * `base_tokens` - Bucket of base tokens topping up the deposit (the maximum spent)
+/// * `pool_state` - Current pool state (mutable)
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// * `pool_id` - ID of the pool, recorded on the position
+/// * `short_positions_resource` - Resource address for short position NFTs
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `governance_vault` - Vault for governance fees (mutable)
+/// * `yield_source_vault` - Vault for yield source tokens (mutable)
+/// 
+/// # Returns
+/// * Tuple of (new short position NFT, change bucket)
+pub fn rollover_short_position(
+    position_nft: Bucket,
+    new_face_value: Decimal,
+    mut base_tokens: Bucket,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: &mut u64,
+    checkpoint_duration: u64,
+    position_duration: u64,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal,
+    pool_id: u64,
+    short_positions_resource: ResourceAddress,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    image_base_url: &str,
+    yield_source: ResourceAddress,
+    governance_vault: &mut Vault,
+    yield_source_vault: &mut Vault
+) -> (Bucket, Bucket) {
+    // Validate input
+    validate_single_nft(&position_nft, short_positions_resource, "short position");
+    validate_resource_address(base_tokens.resource_address(), yield_source, "yield source");
+    assert!(new_face_value > math::ZERO, "Face value must be positive");
     
-    // Update pool state
-    *share_reserves += delta_z;
-    *bond_reserves -= face_value * time_remaining;
+    let position_data: ShortPosition = position_nft.as_non_fungible().non_fungible().data();
+    let current_time = Runtime::current_epoch().number();
+    validate_rollover_maturity(current_time, position_data.maturity_time);
     
-    // Update zeta adjustment for matured portion
-    let matured_impact = maturity_pricing_delta_z(
-        face_value * (math::ONE - time_remaining), 
+    // Update checkpoint if needed
+    *current_checkpoint = update_checkpoint_if_needed(
+        checkpoints, 
+        *current_checkpoint, 
+        checkpoint_duration, 
         share_price
     );
-    *zeta_adjustment += matured_impact + (matured_bond_fee_amount * (math::ONE - governance_fee) / share_price);
     
-    // Update checkpoint data
-    update_checkpoint_short_closed(checkpoints, position_data.checkpoint, face_value, time_remaining);
+    // Settle the old position and open the new one in a single step
+    let (interest, trade) = apply_rollover_short(
+        position_data.face_value,
+        position_data.initial_share_price,
+        position_data.checkpoint,
+        position_data.maturity_time,
+        current_time,
+        new_face_value,
+        share_reserves,
+        bond_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        *current_checkpoint,
+        position_duration,
+        fees,
+        short_collateral
+    );
     
-    // Burn position NFT
-    position_nft.burn();
+    // Top up the deposit from the bucket, or pay out interest the new short does not need
+    if trade.total_deposit > interest {
+        let top_up = trade.total_deposit - interest;
+        assert!(base_tokens.amount() >= top_up, "Insufficient deposit for short position");
+        yield_source_vault.put(base_tokens.take(top_up));
+    } else {
+        base_tokens.put(yield_source_vault.take(interest - trade.total_deposit));
+    }
     
-    // Handle governance fee
-    if governance_fee_amount > math::ZERO {
-        let governance_fee_bucket = yield_source_vault.take(governance_fee_amount / share_price);
-        governance_vault.put(governance_fee_bucket);
+    burn_positions(position_nft, position_summaries);
+    
+    // Create position NFT
+    let position_data = new_short_position(
+        new_face_value,
+        &trade,
+        share_price,
+        *current_checkpoint,
+        current_time,
+        pool_id,
+        image_base_url
+    );
+    
+    let new_position_nft = mint_short_position(
+        position_data,
+        short_positions_resource,
+        position_counter,
+        position_summaries
+    );
+    
+    // Handle governance fee (fees are denominated in base)
+    if trade.governance_fee > math::ZERO {
+        governance_vault.put(yield_source_vault.take(trade.governance_fee));
     }
     
-    // Return base proceeds
-    yield_source_vault.take(base_proceeds)
+    (new_position_nft, base_tokens)
+}
+
+/// Pairs each closed lot with its NFT ID and cost basis
+fn closed_positions(
+    positions: Vec<(NonFungibleLocalId, CloseLot, Decimal)>,
+    trades: Vec<PositionClosed>
+) -> Vec<ClosedPosition> {
+    positions.into_iter().zip(trades).map(|((position_id, lot, cost_basis), trade)| ClosedPosition {
+        position_id,
+        face_value: lot.face_value,
+        time_remaining: trade.time_remaining,
+        base_proceeds: trade.base_proceeds,
+        governance_fee: trade.governance_fee,
+        lp_fee: trade.lp_fee,
+        cost_basis,
+        realized_pnl: trade.base_proceeds - cost_basis,
+    }).collect()
+}
+
+/// Moves a batch's governance fees and pays out its total proceeds
+fn settle_batch(
+    breakdown: Vec<ClosedPosition>,
+    governance_vault: &mut Vault,
+    yield_source_vault: &mut Vault
+) -> (Bucket, Vec<ClosedPosition>) {
+    let total_governance_fee = breakdown.iter()
+        .fold(math::ZERO, |total, entry| total + entry.governance_fee);
+    let total_proceeds = breakdown.iter()
+        .fold(math::ZERO, |total, entry| total + entry.base_proceeds);
+    
+    // Handle governance fee (fees are denominated in base)
+    if total_governance_fee > math::ZERO {
+        governance_vault.put(yield_source_vault.take(total_governance_fee));
+    }
+    
+    (yield_source_vault.take(total_proceeds), breakdown)
+}
+
+/// Burns a fully closed position NFT or shrinks its face value, cost basis and display fields in place
+/// 
+/// # Arguments
+/// * `position_nft` - Bucket holding a single position NFT
+/// * `face_value` - Face value of the position before the close
+/// * `bond_amount` - Face value closed
+/// * `cost` - Cost basis of the position before the close
+/// * `side` - "Long" or "Short", for the display fields
+/// * `maturity_time` - Time when the position matures
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `positions_resource` - Resource address of the position NFT
+/// 
+/// # Returns
+/// * The position NFT if any face value remains, otherwise None
+fn reduce_position(
+    position_nft: Bucket,
+    face_value: Decimal,
+    bond_amount: Decimal,
+    cost: PositionCost,
+    side: &str,
+    maturity_time: u64,
+    image_base_url: &str,
+    positions_resource: ResourceAddress,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>
+) -> Option<Bucket> {
+    let remaining_face_value = face_value - bond_amount;
+    if remaining_face_value == math::ZERO {
+        burn_positions(position_nft, position_summaries);
+        return None;
+    }
+    
+    let remaining_cost = cost.scaled(remaining_face_value / face_value);
+    let local_id = position_nft.as_non_fungible().non_fungible_local_id();
+    let manager = NonFungibleResourceManager::from(positions_resource);
+    manager.update_non_fungible_data(&local_id, "face_value", remaining_face_value);
+    manager.update_non_fungible_data(&local_id, "base_paid", remaining_cost.base_paid);
+    manager.update_non_fungible_data(&local_id, "shares_paid", remaining_cost.shares_paid);
+    manager.update_non_fungible_data(&local_id, "lp_fee_paid", remaining_cost.lp_fee_paid);
+    manager.update_non_fungible_data(&local_id, "governance_fee_paid", remaining_cost.governance_fee_paid);
+    manager.update_non_fungible_data(&local_id, "name", position_name(side, remaining_face_value, maturity_time));
+    manager.update_non_fungible_data(
+        &local_id,
+        "key_image_url",
+        position_image_url(image_base_url, side, remaining_face_value, maturity_time)
+    );
+    Some(position_nft)
+}
+
+/// Builds the NFT data of a newly opened long
+/// 
+/// # Arguments
+/// * `trade` - Outcome of the open
+/// * `base_paid` - Base paid for the position, fees included
+/// * `share_price` - Share price at open
+/// * `checkpoint` - Checkpoint the position opens in
+/// * `open_time` - Time of the open
+/// * `pool_id` - ID of the pool
+/// * `image_base_url` - Base URL of the NFT image service
+/// 
+/// # Returns
+/// * LongPosition NFT data
+fn new_long_position(
+    trade: &LongOpened,
+    base_paid: Decimal,
+    share_price: Decimal,
+    checkpoint: u64,
+    open_time: u64,
+    pool_id: u64,
+    image_base_url: &str
+) -> LongPosition {
+    LongPosition {
+        name: position_name("Long", trade.face_value, trade.maturity_time),
+        key_image_url: position_image_url(image_base_url, "Long", trade.face_value, trade.maturity_time),
+        face_value: trade.face_value,
+        checkpoint,
+        open_time,
+        maturity_time: trade.maturity_time,
+        base_paid,
+        shares_paid: base_paid / share_price,
+        lp_fee_paid: trade.lp_fee,
+        governance_fee_paid: trade.governance_fee,
+        fixed_rate_locked: long_fixed_rate(trade.face_value, base_paid),
+        pool_id,
+    }
+}
+
+/// Builds the NFT data of a newly opened short
+/// 
+/// # Arguments
+/// * `face_value` - Face value of the short
+/// * `trade` - Outcome of the open
+/// * `share_price` - Share price at open
+/// * `checkpoint` - Checkpoint the position opens in
+/// * `open_time` - Time of the open
+/// * `pool_id` - ID of the pool
+/// * `image_base_url` - Base URL of the NFT image service
+/// 
+/// # Returns
+/// * ShortPosition NFT data
+fn new_short_position(
+    face_value: Decimal,
+    trade: &ShortOpened,
+    share_price: Decimal,
+    checkpoint: u64,
+    open_time: u64,
+    pool_id: u64,
+    image_base_url: &str
+) -> ShortPosition {
+    ShortPosition {
+        name: position_name("Short", face_value, trade.maturity_time),
+        key_image_url: position_image_url(image_base_url, "Short", face_value, trade.maturity_time),
+        face_value,
+        checkpoint,
+        open_time,
+        maturity_time: trade.maturity_time,
+        initial_share_price: share_price,
+        base_paid: trade.total_deposit,
+        shares_paid: trade.total_deposit / share_price,
+        lp_fee_paid: trade.lp_fee,
+        governance_fee_paid: trade.governance_fee,
+        fixed_rate_locked: short_fixed_rate(face_value, trade.total_deposit),
+        pool_id,
+    }
+}
+
+/// Quotes the curve fee rate of a trade from the pool's reserves and checkpoint history
+/// 
+/// # Arguments
+/// * `fee_mode` - Fixed or dynamic curve fee
+/// * `fees` - The pool's fee schedule
+/// * `trade_size` - Face value of the bonds the trade moves
+/// * `share_reserves` - Current share reserves
+/// * `zeta_adjustment` - Current zeta adjustment
+/// * `share_price` - Current share price
+/// * `checkpoints` - Checkpoint history
+/// * `current_checkpoint` - Current checkpoint ID
+/// * `checkpoint_duration` - Duration of each checkpoint
+/// * `position_duration` - Duration of positions
+/// 
+/// # Returns
+/// * CurveFeeQuote for the trade; volatility is only measured in dynamic mode
+pub fn quote_trade_curve_fee(
+    fee_mode: &FeeMode,
+    fees: &FeeSchedule,
+    trade_size: Decimal,
+    share_reserves: Decimal,
+    zeta_adjustment: Decimal,
+    share_price: Decimal,
+    checkpoints: &HashMap<u64, Checkpoint>,
+    current_checkpoint: u64,
+    checkpoint_duration: u64,
+    position_duration: u64
+) -> CurveFeeQuote {
+    let exposure = calculate_solvency_requirement(
+        checkpoints,
+        current_checkpoint,
+        checkpoint_duration,
+        position_duration
+    );
+    let volatility = match fee_mode {
+        FeeMode::Fixed => math::ZERO,
+        FeeMode::Dynamic(config) => calculate_rate_volatility(
+            checkpoints,
+            current_checkpoint,
+            checkpoint_duration,
+            config.volatility_window
+        ),
+    };
+
+    quote_curve_fee(
+        fee_mode,
+        fees.curve_fee,
+        trade_size,
+        calculate_effective_share_reserves(share_reserves, zeta_adjustment),
+        share_price,
+        exposure,
+        volatility
+    )
 }
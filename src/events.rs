@@ -2,6 +2,7 @@ use scrypto::prelude::*;
 use crate::types::*;
 use crate::constants::*;
 use crate::helpers::*;
+use crate::pool_core::*;
 
 /// Updates the current checkpoint if needed based on current time
 /// 
@@ -19,207 +20,13 @@ pub fn update_checkpoint_if_needed(
     checkpoint_duration: u64,
     share_price: Decimal
 ) -> u64 {
-    let current_time = Runtime::current_epoch().number();
-    let calculated_checkpoint = calculate_current_checkpoint(current_time, checkpoint_duration);
-    
-    if should_update_checkpoint(current_checkpoint, calculated_checkpoint) {
-        // Create new checkpoint
-        let new_checkpoint = Checkpoint {
-            start_time: calculated_checkpoint,
-            share_price,
-            long_positions: math::ZERO,
-            short_positions: math::ZERO,
-            avg_long_maturity: math::ZERO,
-            avg_short_maturity: math::ZERO,
-            is_minted: true,
-        };
-        
-        checkpoints.insert(calculated_checkpoint, new_checkpoint);
-        calculated_checkpoint
-    } else {
-        current_checkpoint
-    }
-}
-
-/// Updates checkpoint data when a long position is opened
-/// 
-/// # Arguments
-/// * `checkpoints` - Mutable reference to the checkpoints HashMap
-/// * `checkpoint_id` - Checkpoint ID to update
-/// * `face_value` - Face value of the long position
-/// * `maturity_time` - Maturity time of the position
-pub fn update_checkpoint_long_opened(
-    checkpoints: &mut HashMap<u64, Checkpoint>,
-    checkpoint_id: u64,
-    face_value: Decimal,
-    maturity_time: u64
-) {
-    if let Some(checkpoint) = checkpoints.get_mut(&checkpoint_id) {
-        let old_total = checkpoint.long_positions;
-        let new_total = old_total + face_value;
-        
-        // Update weighted average maturity
-        if new_total > math::ZERO {
-            let old_weighted_maturity = checkpoint.avg_long_maturity * old_total;
-            let new_weighted_maturity = Decimal::from(maturity_time) * face_value;
-            checkpoint.avg_long_maturity = (old_weighted_maturity + new_weighted_maturity) / new_total;
-        }
-        
-        checkpoint.long_positions = new_total;
-    }
-}
-
-/// Updates checkpoint data when a long position is closed
-/// 
-/// # Arguments
-/// * `checkpoints` - Mutable reference to the checkpoints HashMap
-/// * `checkpoint_id` - Checkpoint ID to update
-/// * `face_value` - Face value of the long position being closed
-/// * `time_remaining` - Time remaining for the position
-pub fn update_checkpoint_long_closed(
-    checkpoints: &mut HashMap<u64, Checkpoint>,
-    checkpoint_id: u64,
-    face_value: Decimal,
-    time_remaining: Decimal
-) {
-    if let Some(checkpoint) = checkpoints.get_mut(&checkpoint_id) {
-        checkpoint.long_positions -= face_value;
-        
-        // Update average maturity (simplified)
-        if checkpoint.long_positions > math::ZERO {
-            // This is a simplification - actual implementation would recalculate
-            // the weighted average maturity properly
-            checkpoint.avg_long_maturity = time_remaining;
-        } else {
-            checkpoint.avg_long_maturity = math::ZERO;
-        }
-    }
-}
-
-/// Updates checkpoint data when a short position is opened
-/// 
-/// # Arguments
-/// * `checkpoints` - Mutable reference to the checkpoints HashMap
-/// * `checkpoint_id` - Checkpoint ID to update
-/// * `face_value` - Face value of the short position
-/// * `maturity_time` - Maturity time of the position
-pub fn update_checkpoint_short_opened(
-    checkpoints: &mut HashMap<u64, Checkpoint>,
-    checkpoint_id: u64,
-    face_value: Decimal,
-    maturity_time: u64
-) {
-    if let Some(checkpoint) = checkpoints.get_mut(&checkpoint_id) {
-        let old_total = checkpoint.short_positions;
-        let new_total = old_total + face_value;
-        
-        // Update weighted average maturity
-        if new_total > math::ZERO {
-            let old_weighted_maturity = checkpoint.avg_short_maturity * old_total;
-            let new_weighted_maturity = Decimal::from(maturity_time) * face_value;
-            checkpoint.avg_short_maturity = (old_weighted_maturity + new_weighted_maturity) / new_total;
-        }
-        
-        checkpoint.short_positions = new_total;
-    }
-}
-
-/// Updates checkpoint data when a short position is closed
-/// 
-/// # Arguments
-/// * `checkpoints` - Mutable reference to the checkpoints HashMap
-/// * `checkpoint_id` - Checkpoint ID to update
-/// * `face_value` - Face value of the short position being closed
-/// * `time_remaining` - Time remaining for the position
-pub fn update_checkpoint_short_closed(
-    checkpoints: &mut HashMap<u64, Checkpoint>,
-    checkpoint_id: u64,
-    face_value: Decimal,
-    time_remaining: Decimal
-) {
-    if let Some(checkpoint) = checkpoints.get_mut(&checkpoint_id) {
-        checkpoint.short_positions -= face_value;
-        
-        // Update average maturity (simplified)
-        if checkpoint.short_positions > math::ZERO {
-            // This is a simplification - actual implementation would recalculate
-            // the weighted average maturity properly
-            checkpoint.avg_short_maturity = time_remaining;
-        } else {
-            checkpoint.avg_short_maturity = math::ZERO;
-        }
-    }
-}
-
-/// Collects zombie interest and updates pool state
-/// 
-/// # Arguments
-/// * `zombie_share_reserves` - Current zombie share reserves
-/// * `zombie_base_reserves` - Current zombie base reserves
-/// * `share_price` - Current share price
-/// * `zombie_governance_fee` - Zombie governance fee percentage
-/// 
-/// # Returns
-/// * Tuple of (total_zombie_interest, governance_portion, lp_portion, new_zombie_share_reserves)
-#[allow(dead_code)]
-pub fn collect_zombie_interest(
-    zombie_share_reserves: Decimal,
-    zombie_base_reserves: Decimal,
-    share_price: Decimal,
-    zombie_governance_fee: Decimal
-) -> (Decimal, Decimal, Decimal, Decimal) {
-    // Calculate zombie interest
-    let zombie_interest = (share_price * zombie_share_reserves) - zombie_base_reserves;
-    
-    if zombie_interest <= math::ZERO {
-        return (math::ZERO, math::ZERO, math::ZERO, zombie_share_reserves);
-    }
-    
-    // Calculate governance and LP portions
-    let governance_portion = zombie_interest * zombie_governance_fee;
-    let lp_portion = zombie_interest - governance_portion;
-    
-    // Update zombie share reserves
-    let new_zombie_share_reserves = zombie_base_reserves / share_price;
-    
-    (zombie_interest, governance_portion, lp_portion, new_zombie_share_reserves)
-}
-
-/// Calculates solvency requirement for active checkpoints
-/// 
-/// # Arguments
-/// * `checkpoints` - Reference to the checkpoints HashMap
-/// * `current_checkpoint` - Current checkpoint ID
-/// * `checkpoint_duration` - Duration of each checkpoint
-/// * `position_duration` - Duration of positions
-/// 
-/// # Returns
-/// * Total solvency requirement
-pub fn calculate_solvency_requirement(
-    checkpoints: &HashMap<u64, Checkpoint>,
-    current_checkpoint: u64,
-    checkpoint_duration: u64,
-    position_duration: u64
-) -> Decimal {
-    let mut solvency_requirement = math::ZERO;
-    
-    // Calculate checkpoints per term
-    let checkpoints_per_term = position_duration / checkpoint_duration;
-    
-    // Calculate solvency requirement for each active checkpoint
-    for i in 0..checkpoints_per_term {
-        let checkpoint_id = current_checkpoint.saturating_sub(i * checkpoint_duration);
-        
-        if let Some(checkpoint) = checkpoints.get(&checkpoint_id) {
-            // Calculate checkpoint solvency requirement
-            let checkpoint_requirement = (checkpoint.long_positions - checkpoint.short_positions)
-                .max(math::ZERO);
-                
-            solvency_requirement += checkpoint_requirement;
-        }
-    }
-    
-    solvency_requirement
+    advance_checkpoint(
+        checkpoints,
+        current_checkpoint,
+        checkpoint_duration,
+        share_price,
+        Runtime::current_epoch().number()
+    )
 }
 
 /// Initializes the first checkpoint for a new pool
@@ -236,21 +43,12 @@ pub fn initialize_first_checkpoint(
     checkpoint_duration: u64,
     initial_share_price: Decimal
 ) -> u64 {
-    let current_time = Runtime::current_epoch().number();
-    let current_checkpoint = calculate_current_checkpoint(current_time, checkpoint_duration);
-    
-    let initial_checkpoint = Checkpoint {
-        start_time: current_checkpoint,
-        share_price: initial_share_price,
-        long_positions: math::ZERO,
-        short_positions: math::ZERO,
-        avg_long_maturity: math::ZERO,
-        avg_short_maturity: math::ZERO,
-        is_minted: true,
-    };
-    
-    checkpoints.insert(current_checkpoint, initial_checkpoint);
-    current_checkpoint
+    create_first_checkpoint(
+        checkpoints,
+        checkpoint_duration,
+        initial_share_price,
+        Runtime::current_epoch().number()
+    )
 }
 
 /// Gets the current pool state for external queries
@@ -299,3 +97,47 @@ pub fn get_pool_state(
         current_checkpoint,
     }
 }
+
+/// Reconciles the yield source vault against the pool's internal accounting
+/// 
+/// Every tracked balance is in shares and valued at the current share price.
+/// Share price updates credit those shares without moving base into the vault,
+/// so the yield they accrued is deducted from the expected balance.
+/// 
+/// # Arguments
+/// * `vault_balance` - Base tokens held in the yield source vault
+/// * `share_reserves` - Current share reserves
+/// * `min_share_reserves` - Minimum share reserves held outside `share_reserves`
+/// * `share_price` - Current share price
+/// * `zombie_share_reserves` - Zombie share reserves
+/// * `short_collateral` - Outstanding short collateral in shares
+/// * `accrued_yield` - Base credited to the tracked shares by share price updates
+/// 
+/// # Returns
+/// * InvariantReport with the expected balance and discrepancy
+pub fn check_pool_invariants(
+    vault_balance: Decimal,
+    share_reserves: Decimal,
+    min_share_reserves: Decimal,
+    share_price: Decimal,
+    zombie_share_reserves: Decimal,
+    short_collateral: Decimal,
+    accrued_yield: Decimal
+) -> InvariantReport {
+    let share_reserves_value = (share_reserves + min_share_reserves) * share_price;
+    let zombie_reserves_value = zombie_share_reserves * share_price;
+    let short_collateral_value = short_collateral * share_price;
+    let expected_balance = share_reserves_value + zombie_reserves_value + short_collateral_value - accrued_yield;
+    let discrepancy = vault_balance - expected_balance;
+    
+    InvariantReport {
+        vault_balance,
+        expected_balance,
+        discrepancy,
+        share_reserves_value,
+        zombie_reserves_value,
+        short_collateral_value,
+        accrued_yield,
+        is_consistent: discrepancy >= -math::EPSILON && discrepancy <= math::EPSILON,
+    }
+}
@@ -0,0 +1,249 @@
+use scrypto::prelude::*;
+use crate::types::*;
+use crate::constants::*;
+
+// Fees are split the way Hyperdrive splits them. For a trade of Δy bonds with
+// time remaining tr, at spot price p = min(c·ze / y, 1):
+//
+//   curve fee             = ϕc · (1 - p) · Δy · tr
+//   flat fee              = ϕf · Δy · (1 - tr)
+//   governance curve fee  = ϕg,c · curve fee
+//   governance flat fee   = ϕg,f · flat fee
+//
+// The curve fee scales with the discount the bonds trade at, so it never exceeds
+// ϕc of the fixed interest; the flat fee is charged on the matured part at face
+// value. The LPs keep whatever governance does not take.
+
+/// Calculates the spot price of a bond in base
+///
+/// # Arguments
+/// * `effective_share_reserves` - Effective share reserves (ze)
+/// * `bond_reserves` - Bond reserves (y)
+/// * `share_price` - Current share price (c)
+///
+/// # Returns
+/// * Spot price between 0 and 1, or 1 when the reserves cannot price bonds
+pub fn calculate_spot_price(
+    effective_share_reserves: Decimal,
+    bond_reserves: Decimal,
+    share_price: Decimal
+) -> Decimal {
+    if effective_share_reserves <= math::ZERO || bond_reserves <= math::ZERO {
+        return math::ONE;
+    }
+
+    // p = c * ze / y, the price the curve quotes for one bond, capped at face value
+    (share_price * effective_share_reserves / bond_reserves).min(math::ONE)
+}
+
+/// Calculates the curve fee on the unmatured part of a trade
+///
+/// # Arguments
+/// * `delta_y` - Face value traded
+/// * `time_remaining` - Time remaining until maturity (0 to 1)
+/// * `spot_price` - Spot price of a bond before the trade
+/// * `curve_fee` - Curve fee percentage (ϕc)
+///
+/// # Returns
+/// * Curve fee in base
+pub fn calculate_curve_fee(
+    delta_y: Decimal,
+    time_remaining: Decimal,
+    spot_price: Decimal,
+    curve_fee: Decimal
+) -> Decimal {
+    curve_fee * (math::ONE - spot_price) * delta_y * time_remaining
+}
+
+/// Calculates the flat fee on the matured part of a trade
+///
+/// # Arguments
+/// * `delta_y` - Face value traded
+/// * `time_remaining` - Time remaining until maturity (0 to 1)
+/// * `flat_fee` - Flat fee percentage (ϕf)
+///
+/// # Returns
+/// * Flat fee in base
+pub fn calculate_flat_fee(delta_y: Decimal, time_remaining: Decimal, flat_fee: Decimal) -> Decimal {
+    flat_fee * delta_y * (math::ONE - time_remaining)
+}
+
+/// Calculates every fee charged on a trade and the governance share of each
+///
+/// # Arguments
+/// * `delta_y` - Face value traded
+/// * `time_remaining` - Time remaining until maturity (0 to 1)
+/// * `spot_price` - Spot price of a bond before the trade
+/// * `fees` - Fee schedule charged on the trade
+///
+/// # Returns
+/// * TradeFees with the curve and flat fees and their governance shares
+pub fn calculate_trade_fees(
+    delta_y: Decimal,
+    time_remaining: Decimal,
+    spot_price: Decimal,
+    fees: &FeeSchedule
+) -> TradeFees {
+    let curve_fee = calculate_curve_fee(delta_y, time_remaining, spot_price, fees.curve_fee);
+    let flat_fee = calculate_flat_fee(delta_y, time_remaining, fees.flat_fee);
+
+    TradeFees {
+        curve_fee,
+        flat_fee,
+        governance_curve_fee: curve_fee * fees.governance_curve_fee,
+        governance_flat_fee: flat_fee * fees.governance_flat_fee,
+    }
+}
+
+// In dynamic mode the curve fee rate itself moves with the state of the pool.
+// For a trade moving Δy face value against effective share reserves ze at share
+// price c, with net exposure e (also face value) and rate volatility σ:
+//
+//   size         = Δy / (ze · c)
+//   utilization  = e / (ze · c)
+//   ϕc'          = clamp(ϕc · (1 + ks · size + ku · utilization + kv · σ), ϕmin, ϕmax)
+//
+// ϕc' replaces ϕc in the curve fee above; the flat and governance fees are unchanged.
+
+/// Calculates a trade's size or the pool's exposure relative to its effective reserves
+///
+/// # Arguments
+/// * `amount` - Trade size or exposure, in face value
+/// * `effective_share_reserves` - Effective share reserves (ze)
+/// * `share_price` - Current share price (c)
+///
+/// # Returns
+/// * Amount over ze · c, or 0 when the pool has no effective reserves
+pub fn calculate_reserve_ratio(amount: Decimal, effective_share_reserves: Decimal, share_price: Decimal) -> Decimal {
+    let reserves = effective_share_reserves * share_price;
+    if reserves <= math::ZERO {
+        return math::ZERO;
+    }
+
+    amount / reserves
+}
+
+/// Calculates the volatility of the rate earned by shares over recent checkpoints
+///
+/// The rate for each checkpoint is the share price growth since the checkpoint
+/// before it; the volatility is the mean absolute deviation of those rates.
+/// Checkpoints that were never minted are skipped.
+///
+/// # Arguments
+/// * `checkpoints` - Checkpoint history
+/// * `current_checkpoint` - Start time of the current checkpoint
+/// * `checkpoint_duration` - Duration of each checkpoint in seconds
+/// * `window` - Number of checkpoints to look back over
+///
+/// # Returns
+/// * Rate volatility per checkpoint, or 0 with fewer than two rates
+pub fn calculate_rate_volatility(
+    checkpoints: &HashMap<u64, Checkpoint>,
+    current_checkpoint: u64,
+    checkpoint_duration: u64,
+    window: u64
+) -> Decimal {
+    let mut rates = Vec::new();
+    for i in 0..window {
+        let offset = i * checkpoint_duration;
+        if offset + checkpoint_duration > current_checkpoint {
+            break;
+        }
+
+        let later = checkpoints.get(&(current_checkpoint - offset));
+        let earlier = checkpoints.get(&(current_checkpoint - offset - checkpoint_duration));
+        if let (Some(later), Some(earlier)) = (later, earlier) {
+            if earlier.share_price > math::ZERO {
+                rates.push(later.share_price / earlier.share_price - math::ONE);
+            }
+        }
+    }
+
+    if rates.len() < 2 {
+        return math::ZERO;
+    }
+
+    let count = Decimal::from(rates.len() as u64);
+    let mean = rates.iter().fold(math::ZERO, |sum, rate| sum + *rate) / count;
+    rates.iter().fold(math::ZERO, |sum, rate| sum + (*rate - mean).checked_abs().unwrap()) / count
+}
+
+/// Quotes the curve fee rate a trade pays under the pool's fee mode
+///
+/// # Arguments
+/// * `fee_mode` - Fixed or dynamic curve fee
+/// * `curve_fee` - Curve fee percentage of the fee schedule (ϕc)
+/// * `trade_size` - Face value of the bonds the trade moves
+/// * `effective_share_reserves` - Effective share reserves (ze)
+/// * `share_price` - Current share price (c)
+/// * `exposure` - Net exposure of the pool across open checkpoints
+/// * `volatility` - Rate volatility from the checkpoint history
+///
+/// # Returns
+/// * CurveFeeQuote with the inputs of the formula and the resulting rate
+pub fn quote_curve_fee(
+    fee_mode: &FeeMode,
+    curve_fee: Decimal,
+    trade_size: Decimal,
+    effective_share_reserves: Decimal,
+    share_price: Decimal,
+    exposure: Decimal,
+    volatility: Decimal
+) -> CurveFeeQuote {
+    let size = calculate_reserve_ratio(trade_size, effective_share_reserves, share_price);
+    let utilization = calculate_reserve_ratio(exposure, effective_share_reserves, share_price);
+
+    let quoted_fee = match fee_mode {
+        FeeMode::Fixed => curve_fee,
+        FeeMode::Dynamic(config) => calculate_dynamic_curve_fee(config, curve_fee, size, utilization, volatility),
+    };
+
+    CurveFeeQuote {
+        base_curve_fee: curve_fee,
+        size,
+        utilization,
+        volatility,
+        curve_fee: quoted_fee,
+    }
+}
+
+/// Calculates the dynamic curve fee rate, capped at the configured bounds
+///
+/// # Arguments
+/// * `config` - Dynamic fee multipliers and bounds
+/// * `curve_fee` - Curve fee percentage of the fee schedule (ϕc)
+/// * `size` - Trade size over ze · c
+/// * `utilization` - Exposure over ze · c
+/// * `volatility` - Rate volatility from the checkpoint history
+///
+/// # Returns
+/// * Curve fee percentage between the configured minimum and maximum
+pub fn calculate_dynamic_curve_fee(
+    config: &DynamicFeeConfig,
+    curve_fee: Decimal,
+    size: Decimal,
+    utilization: Decimal,
+    volatility: Decimal
+) -> Decimal {
+    let scale = math::ONE
+        + config.size_multiplier * size
+        + config.utilization_multiplier * utilization
+        + config.volatility_multiplier * volatility;
+
+    (curve_fee * scale).max(config.min_curve_fee).min(config.max_curve_fee)
+}
+
+/// Returns the fee schedule a trade is charged, with the quoted curve fee rate
+///
+/// # Arguments
+/// * `fees` - The pool's fee schedule
+/// * `quote` - Curve fee quoted for the trade
+///
+/// # Returns
+/// * The fee schedule with its curve fee replaced by the quoted rate
+pub fn trade_fee_schedule(fees: &FeeSchedule, quote: &CurveFeeQuote) -> FeeSchedule {
+    FeeSchedule {
+        curve_fee: quote.curve_fee,
+        ..fees.clone()
+    }
+}
@@ -0,0 +1,229 @@
+use scrypto::prelude::*;
+
+// Import the pool blueprint and shared modules
+use crate::influxpool::hyperdrive_pool::HyperdrivePool;
+use crate::types::*;
+use crate::constants::*;
+use crate::helpers::*;
+
+/// Most long positions the vault may hold, so every position fits in one read
+///
+/// Only a roll's new long adds a position, and rolls merge positions per checkpoint.
+const MAX_VAULT_POSITIONS: u32 = 100;
+
+#[blueprint]
+mod fixed_rate_vault {
+    enable_method_auth! {
+        roles {
+            keeper => updatable_by: [OWNER];
+        },
+        methods {
+            deposit => PUBLIC;
+            withdraw => PUBLIC;
+            roll => restrict_to: [keeper];
+            get_total_value => PUBLIC;
+            get_share_price => PUBLIC;
+            get_share_resource => PUBLIC;
+        }
+    }
+
+    struct FixedRateVault {
+        pool: Global<HyperdrivePool>,            // Pool the vault lends into
+        yield_source: ResourceAddress,           // Base token of the pool
+        long_positions_resource: ResourceAddress, // Long position NFTs of the pool
+        share_resource: FungibleResourceManager, // Fungible vault shares
+        idle_vault: Vault,                       // Base waiting for the next roll
+        positions: NonFungibleVault,             // Long positions held by the vault
+    }
+
+    impl FixedRateVault {
+        /// Creates a vault that earns the fixed rate of a pool
+        ///
+        /// # Arguments
+        /// * `pool_address` - Component address of an initialized HyperdrivePool
+        /// * `admin_badge` - Badge for administrative operations, also the initial keeper
+        ///
+        /// # Returns
+        /// * Global<FixedRateVault> - The vault component
+        pub fn instantiate(pool_address: ComponentAddress, admin_badge: ResourceAddress) -> Global<FixedRateVault> {
+            let pool: Global<HyperdrivePool> = pool_address.into();
+            let yield_source = pool.get_yield_source();
+            let long_positions_resource = pool.get_long_positions_resource();
+
+            let (address_reservation, component_address) =
+                Runtime::allocate_component_address(FixedRateVault::blueprint_id());
+            let global_component_caller_badge =
+                NonFungibleGlobalId::global_caller_badge(component_address);
+
+            // Create vault shares with the component as minter
+            let share_resource = ResourceBuilder::new_fungible(OwnerRole::None)
+                .divisibility(DIVISIBILITY_MAXIMUM)
+                .metadata(metadata!(
+                    init {
+                        "name" => "Hyperdrive Fixed Rate Vault Share", locked;
+                        "symbol" => "HDFRV", locked;
+                    }
+                ))
+                .mint_roles(mint_roles! {
+                    minter => rule!(require(global_component_caller_badge.clone()));
+                    minter_updater => rule!(deny_all);
+                })
+                .burn_roles(burn_roles! {
+                    burner => rule!(require(global_component_caller_badge.clone()));
+                    burner_updater => rule!(deny_all);
+                })
+                .create_with_no_initial_supply();
+
+            Self {
+                pool,
+                yield_source,
+                long_positions_resource,
+                share_resource,
+                idle_vault: Vault::new(yield_source),
+                positions: NonFungibleVault::new(long_positions_resource),
+            }
+            .instantiate()
+            .prepare_to_globalize(OwnerRole::Fixed(rule!(require(admin_badge))))
+            .roles(roles! {
+                keeper => rule!(require(admin_badge));
+            })
+            .with_address(address_reservation)
+            .globalize()
+        }
+
+        /// Deposits base tokens for vault shares at the marked share price
+        ///
+        /// The base stays idle until the next roll lends it into the pool.
+        pub fn deposit(&mut self, base_tokens: Bucket) -> Bucket {
+            validate_resource_address(base_tokens.resource_address(), self.yield_source, "yield source");
+            assert!(base_tokens.amount() > math::ZERO, "Deposit amount must be positive");
+
+            // Price shares before the deposit changes the vault's value
+            let share_amount = base_tokens.amount() / self.get_share_price();
+            self.idle_vault.put(base_tokens);
+
+            self.share_resource.mint(share_amount).into()
+        }
+
+        /// Burns vault shares for their pro-rata part of the idle base and of every long
+        ///
+        /// The longs are closed through the pool, so the withdrawer bears the closing fees.
+        pub fn withdraw(&mut self, shares: Bucket) -> Bucket {
+            validate_resource_address(shares.resource_address(), self.share_resource.address(), "vault share");
+            assert!(shares.amount() > math::ZERO, "Share amount must be positive");
+
+            let fraction = shares.amount() / self.total_shares();
+            shares.burn();
+
+            let idle_amount = self.idle_vault.amount() * fraction;
+            let mut proceeds = self.idle_vault.take(idle_amount);
+            for position_id in self.position_ids() {
+                let bond_amount = self.position_data(&position_id).face_value * fraction;
+                if bond_amount == math::ZERO {
+                    continue;
+                }
+
+                let position_nft = self.positions.take_non_fungible(&position_id);
+                let (base_tokens, remaining_position) = self.pool.close_long(position_nft.into(), bond_amount, None);
+                proceeds.put(base_tokens);
+                if let Some(remaining_position) = remaining_position {
+                    self.positions.put(remaining_position.as_non_fungible());
+                }
+            }
+
+            proceeds
+        }
+
+        /// Rolls matured longs into the current term and lends out the idle base (keeper only)
+        ///
+        /// # Arguments
+        /// * `min_term_rate` - Lowest fixed rate over one term, face value over base less
+        ///   one, that any rolled or newly opened long may lock
+        pub fn roll(&mut self, min_term_rate: Decimal) {
+            assert!(min_term_rate >= math::ZERO, "Minimum term rate cannot be negative");
+            let current_time = Runtime::current_epoch().number();
+            let min_face_ratio = math::ONE + min_term_rate;
+
+            // Roll every matured long into a new long
+            for position_id in self.position_ids() {
+                if self.position_data(&position_id).maturity_time > current_time {
+                    continue;
+                }
+
+                let min_face_value = self.position_data(&position_id).face_value * min_face_ratio;
+                let position_nft = self.positions.take_non_fungible(&position_id);
+                let (new_position, dust) = self.pool.rollover_long(position_nft.into(), min_face_value, None);
+                self.positions.put(new_position.as_non_fungible());
+                self.idle_vault.put(dust);
+            }
+
+            // Lend the idle base at the current fixed rate
+            if self.idle_vault.amount() > math::ZERO {
+                assert!(
+                    self.positions.amount() < Decimal::from(MAX_VAULT_POSITIONS),
+                    "Vault cannot hold more than {} positions",
+                    MAX_VAULT_POSITIONS
+                );
+                let base_amount = self.idle_vault.amount();
+                let new_position = self.pool.open_long(self.idle_vault.take_all(), None);
+                let position_data: LongPosition = new_position.as_non_fungible().non_fungible().data();
+                assert!(position_data.face_value >= base_amount * min_face_ratio, "Fixed rate below minimum");
+                self.positions.put(new_position.as_non_fungible());
+            }
+
+            // Keep one position per checkpoint
+            let mut by_checkpoint: BTreeMap<u64, Vec<NonFungibleLocalId>> = BTreeMap::new();
+            for position_id in self.position_ids() {
+                by_checkpoint.entry(self.position_data(&position_id).checkpoint)
+                    .or_default()
+                    .push(position_id);
+            }
+            for position_ids in by_checkpoint.into_values().filter(|ids| ids.len() > 1) {
+                let position_nfts = self.positions.take_non_fungibles(&position_ids.into_iter().collect());
+                let merged = self.pool.merge_positions(position_nfts.into());
+                self.positions.put(merged.as_non_fungible());
+            }
+        }
+
+        /// Gets the marked value of the vault: idle base plus what closing every long would pay now
+        pub fn get_total_value(&self) -> Decimal {
+            self.position_ids().iter().fold(self.idle_vault.amount(), |total, position_id| {
+                let face_value = self.position_data(position_id).face_value;
+                total + self.pool.preview_close_long(position_id.clone(), face_value)
+            })
+        }
+
+        /// Gets the base value of one vault share
+        pub fn get_share_price(&self) -> Decimal {
+            self.share_price_of(self.get_total_value())
+        }
+
+        /// Gets the vault share resource address
+        pub fn get_share_resource(&self) -> ResourceAddress {
+            self.share_resource.address()
+        }
+
+        /// Divides a vault value among the outstanding shares, one to one before any exist
+        fn share_price_of(&self, total_value: Decimal) -> Decimal {
+            let total_shares = self.total_shares();
+            if total_shares == math::ZERO {
+                return math::ONE;
+            }
+            assert!(total_value > math::ZERO, "Vault has shares but no value");
+            total_value / total_shares
+        }
+
+        fn total_shares(&self) -> Decimal {
+            self.share_resource.total_supply().unwrap_or(math::ZERO)
+        }
+
+        fn position_ids(&self) -> Vec<NonFungibleLocalId> {
+            self.positions.non_fungible_local_ids(MAX_VAULT_POSITIONS).into_iter().collect()
+        }
+
+        fn position_data(&self, position_id: &NonFungibleLocalId) -> LongPosition {
+            NonFungibleResourceManager::from(self.long_positions_resource)
+                .get_non_fungible_data(position_id)
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use scrypto::prelude::*;
+use crate::types::*;
+use crate::constants::*;
+use crate::helpers::*;
+
+/// Validates a fee schedule against the per-fee maxima
+/// 
+/// # Arguments
+/// * `fees` - Fee schedule set at pool creation or proposed later
+pub fn validate_fee_schedule(fees: &FeeSchedule) {
+    validate_fee_bound(fees.curve_fee, validation::MAX_CURVE_FEE, "Curve fee");
+    validate_fee_bound(fees.flat_fee, validation::MAX_FLAT_FEE, "Flat fee");
+    validate_fee_bound(fees.governance_curve_fee, validation::MAX_GOVERNANCE_CURVE_FEE, "Governance curve fee");
+    validate_fee_bound(fees.governance_flat_fee, validation::MAX_GOVERNANCE_FLAT_FEE, "Governance flat fee");
+    validate_fee_bound(fees.zombie_governance_fee, validation::MAX_ZOMBIE_GOVERNANCE_FEE, "Zombie governance fee");
+}
+
+/// Validates a fee mode's multipliers and curve fee bounds
+/// 
+/// # Arguments
+/// * `fee_mode` - Fee mode selected at pool creation
+pub fn validate_fee_mode(fee_mode: &FeeMode) {
+    if let FeeMode::Dynamic(config) = fee_mode {
+        assert!(
+            config.size_multiplier >= math::ZERO
+                && config.utilization_multiplier >= math::ZERO
+                && config.volatility_multiplier >= math::ZERO,
+            "Dynamic fee multipliers must not be negative"
+        );
+        validate_fee_bound(config.max_curve_fee, validation::MAX_CURVE_FEE, "Maximum dynamic curve fee");
+        assert!(
+            config.min_curve_fee >= math::ZERO && config.min_curve_fee <= config.max_curve_fee,
+            "Minimum dynamic curve fee must be between 0 and the maximum"
+        );
+        assert!(
+            config.volatility_window >= 2 && config.volatility_window <= validation::MAX_VOLATILITY_WINDOW,
+            "Volatility window must be between 2 and {} checkpoints",
+            validation::MAX_VOLATILITY_WINDOW
+        );
+    }
+}
+
+/// Validates a fee update delay
+/// 
+/// # Arguments
+/// * `delay` - Delay between proposing and executing a fee change, in epochs
+pub fn validate_fee_update_delay(delay: u64) {
+    assert!(delay >= MIN_FEE_UPDATE_DELAY, "Fee update delay must be at least {} epochs", MIN_FEE_UPDATE_DELAY);
+}
+
+/// Creates a fee proposal that becomes executable after the delay
+/// 
+/// # Arguments
+/// * `fees` - Proposed fee schedule
+/// * `pending_fees` - Pending proposal, which must be empty
+/// * `fee_update_delay` - Delay between proposing and executing, in epochs
+/// * `current_time` - Current epoch time
+/// 
+/// # Returns
+/// * The new proposal
+pub fn propose_fee_schedule(
+    fees: FeeSchedule,
+    pending_fees: &Option<FeeProposal>,
+    fee_update_delay: u64,
+    current_time: u64
+) -> FeeProposal {
+    assert!(pending_fees.is_none(), "A fee proposal is already pending");
+    validate_fee_schedule(&fees);
+
+    FeeProposal {
+        fees,
+        proposed_time: current_time,
+        executable_time: current_time + fee_update_delay,
+    }
+}
+
+/// Takes the pending fee proposal once its delay has passed
+/// 
+/// # Arguments
+/// * `pending_fees` - Pending proposal (mutable, cleared on success)
+/// * `current_time` - Current epoch time
+/// 
+/// # Returns
+/// * Fee schedule to apply
+pub fn take_executable_fees(pending_fees: &mut Option<FeeProposal>, current_time: u64) -> FeeSchedule {
+    let proposal = pending_fees.as_ref().expect("No fee proposal pending");
+    assert!(current_time >= proposal.executable_time, "Fee proposal delay has not passed");
+
+    pending_fees.take().unwrap().fees
+}
+
+fn validate_fee_bound(fee: Decimal, max_fee: Decimal, fee_name: &str) {
+    assert!(
+        fee >= math::ZERO && fee <= max_fee,
+        "{} must be between 0 and {}",
+        fee_name,
+        max_fee
+    );
+}
+
+/// Deposits all governance fees into the fee recipient
+/// 
+/// # Arguments
+/// * `fee_recipient` - Account receiving governance fees, if configured
+/// * `governance_vault` - Vault containing governance fees (mutable)
+/// 
+/// # Returns
+/// * Tuple of (recipient, amount deposited)
+pub fn collect_governance_fees(
+    fee_recipient: Option<ComponentAddress>,
+    governance_vault: &mut Vault
+) -> (ComponentAddress, Decimal) {
+    let fee_recipient = fee_recipient.expect("Fee recipient not set");
+    let fees = governance_vault.take_all();
+    let amount = fees.amount();
+
+    let mut recipient: Global<Account> = fee_recipient.into();
+    recipient.try_deposit_or_abort(fees, None);
+    (fee_recipient, amount)
+}
+
+/// Validates the share of the governance fee paid to referrers
+/// 
+/// # Arguments
+/// * `referral_share` - Share of each referred trade's governance fee
+pub fn validate_referral_share(referral_share: Decimal) {
+    validate_fee_bound(referral_share, validation::MAX_REFERRAL_SHARE, "Referral share");
+}
+
+/// Validates a trade's referrer before anything accrues to it
+/// 
+/// The referrer must be an account so its fees can always be claimed, and cannot
+/// be the fee recipient, which pays the referral share.
+/// 
+/// # Arguments
+/// * `referrer` - Component credited with the trade
+/// * `fee_recipient` - Account receiving governance fees, if configured
+pub fn validate_referrer(referrer: ComponentAddress, fee_recipient: Option<ComponentAddress>) {
+    validate_account_address(referrer, "Referrer");
+    assert!(fee_recipient != Some(referrer), "Referrer cannot be the fee recipient");
+}
+
+/// Moves the referrer's share of a trade's governance fee into their referral vault
+/// 
+/// Nothing is accrued, and no vault is created, when the share rounds to zero.
+/// 
+/// # Arguments
+/// * `referrer` - Component credited with the trade
+/// * `referral_share` - Share of the governance fee paid to referrers
+/// * `governance_fee` - Governance fee charged on the trade
+/// * `governance_vault` - Vault containing governance fees (mutable)
+/// * `referral_vaults` - Accrued fees per referrer
+/// 
+/// # Returns
+/// * Fee accrued to the referrer
+pub fn accrue_referral_fee(
+    referrer: ComponentAddress,
+    referral_share: Decimal,
+    governance_fee: Decimal,
+    governance_vault: &mut Vault,
+    referral_vaults: &KeyValueStore<ComponentAddress, Vault>
+) -> Decimal {
+    let amount = governance_fee * referral_share;
+    if amount <= math::ZERO {
+        return math::ZERO;
+    }
+
+    let fee = governance_vault.take(amount);
+
+    if referral_vaults.get(&referrer).is_some() {
+        referral_vaults.get_mut(&referrer).unwrap().put(fee);
+    } else {
+        referral_vaults.insert(referrer, Vault::with_bucket(fee));
+    }
+    amount
+}
+
+/// Deposits everything a referrer has accrued into their account
+/// 
+/// # Arguments
+/// * `referrer` - Account the fees were accrued to
+/// * `referral_vaults` - Accrued fees per referrer
+/// 
+/// # Returns
+/// * Amount deposited
+pub fn claim_referral_fees(
+    referrer: ComponentAddress,
+    referral_vaults: &KeyValueStore<ComponentAddress, Vault>
+) -> Decimal {
+    let fees = referral_vaults
+        .get_mut(&referrer)
+        .expect("No referral fees accrued")
+        .take_all();
+    let amount = fees.amount();
+
+    let mut account: Global<Account> = referrer.into();
+    account.try_deposit_or_abort(fees, None);
+    amount
+}
+
+/// Validates that a resource may be swept out of the component
+/// 
+/// Base, LP and position resources, and every bond and yield token the pool
+/// minted, are never sweepable.
+/// 
+/// # Arguments
+/// * `resource` - Resource to sweep
+/// * `protected_resources` - Base, LP and position resources of the pool
+/// * `bond_checkpoints` - Checkpoint per bond token
+/// * `yield_checkpoints` - Checkpoint per yield token
+pub fn validate_sweepable(
+    resource: ResourceAddress,
+    protected_resources: &[ResourceAddress],
+    bond_checkpoints: &KeyValueStore<ResourceAddress, u64>,
+    yield_checkpoints: &KeyValueStore<ResourceAddress, u64>
+) {
+    assert!(
+        !protected_resources.contains(&resource)
+            && bond_checkpoints.get(&resource).is_none()
+            && yield_checkpoints.get(&resource).is_none(),
+        "Pool resources cannot be swept"
+    );
+}
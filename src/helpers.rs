@@ -1,5 +1,5 @@
 use scrypto::prelude::*;
-//use crate::types::*;
+use crate::types::{Checkpoint, PoolMode};
 use crate::constants::*;
 
 /// Calculates the effective share reserves (ze = z - ζ)
@@ -14,6 +14,35 @@ pub fn calculate_effective_share_reserves(share_reserves: Decimal, zeta_adjustme
     share_reserves - zeta_adjustment
 }
 
+/// Calculates the annualized fixed rate a long opened now would lock, before fees
+/// 
+/// The curve prices a bond at c * ze / y, so one base buys y / (c * ze) face value
+/// over a term. The inverse of `calculate_bond_reserves_for_rate`.
+/// 
+/// # Arguments
+/// * `effective_share_reserves` - Effective share reserves (ze)
+/// * `bond_reserves` - Bond reserves (y)
+/// * `share_price` - Current share price (c)
+/// * `position_duration` - Duration of positions
+/// 
+/// # Returns
+/// * Annualized fixed rate, or 0 when the reserves cannot price bonds
+pub fn calculate_fixed_rate(
+    effective_share_reserves: Decimal,
+    bond_reserves: Decimal,
+    share_price: Decimal,
+    position_duration: u64
+) -> Decimal {
+    if effective_share_reserves <= math::ZERO || bond_reserves <= math::ZERO || position_duration == 0 {
+        return math::ZERO;
+    }
+
+    // r = (y - c * ze) / (c * ze * t), with t the term in years
+    let share_value = effective_share_reserves * share_price;
+    let term_years = Decimal::from(position_duration) / Decimal::from(SECONDS_PER_YEAR);
+    (bond_reserves - share_value) / (share_value * term_years)
+}
+
 /// Calculates the current spot rate based on pool state
 /// 
 /// # Arguments
@@ -44,17 +73,28 @@ pub fn calculate_spot_rate(
     (numerator / denominator) - math::ONE
 }
 
-/// Validates fee parameters
+/// Validates that the pool mode accepts new positions and liquidity
 /// 
 /// # Arguments
-/// * `fee` - Fee percentage to validate
-/// * `fee_name` - Name of the fee for error messages
-pub fn validate_fee(fee: Decimal, fee_name: &str) {
-    assert!(
-        fee >= math::ZERO && fee <= MAX_FEE_PERCENTAGE,
-        "{} must be between 0 and 1", 
-        fee_name
-    );
+/// * `mode` - Current pool mode
+pub fn validate_opens_allowed(mode: PoolMode) {
+    assert!(mode.allows_opens(), "Pool is not accepting new positions or liquidity");
+}
+
+/// Validates that the pool mode accepts closes and other non-emergency operations
+/// 
+/// # Arguments
+/// * `mode` - Current pool mode
+pub fn validate_closes_allowed(mode: PoolMode) {
+    assert!(mode.allows_closes(), "Pool is paused");
+}
+
+/// Validates that the pool is paused, the only mode with emergency exits
+/// 
+/// # Arguments
+/// * `mode` - Current pool mode
+pub fn validate_paused(mode: PoolMode) {
+    assert!(mode == PoolMode::Paused, "Emergency exits are only available while the pool is paused");
 }
 
 /// Validates duration parameters
@@ -81,6 +121,19 @@ pub fn validate_durations(checkpoint_duration: u64, position_duration: u64) {
     );
 }
 
+/// Validates the fixed rate a pool opens at
+/// 
+/// # Arguments
+/// * `initial_fixed_rate` - Annualized fixed rate
+pub fn validate_initial_fixed_rate(initial_fixed_rate: Decimal) {
+    assert!(initial_fixed_rate >= math::ZERO, "Initial fixed rate cannot be negative");
+    assert!(
+        initial_fixed_rate <= validation::MAX_INITIAL_FIXED_RATE,
+        "Initial fixed rate must be at most {}",
+        validation::MAX_INITIAL_FIXED_RATE
+    );
+}
+
 /// Validates liquidity amount
 /// 
 /// # Arguments
@@ -112,6 +165,19 @@ pub fn validate_resource_address(
     );
 }
 
+/// Validates that a component address is an account, so deposits into it cannot abort
+/// 
+/// # Arguments
+/// * `address` - Component address to check
+/// * `name` - Name of the address for error messages
+pub fn validate_account_address(address: ComponentAddress, name: &str) {
+    assert!(
+        address.as_node_id().is_global_account(),
+        "{} must be an account",
+        name
+    );
+}
+
 /// Calculates time remaining for a position
 /// 
 /// # Arguments
@@ -183,3 +249,106 @@ pub fn validate_single_nft(
     validate_resource_address(bucket.resource_address(), expected_resource, nft_name);
     assert!(bucket.amount() == dec!("1"), "Can only process one {} at a time", nft_name);
 }
+
+/// Validates that a bucket holds at least one NFT of the expected resource
+/// 
+/// # Arguments
+/// * `bucket` - Bucket to validate
+/// * `expected_resource` - Expected NFT resource address
+/// * `nft_name` - Name of the NFT for error messages
+pub fn validate_nft_batch(
+    bucket: &Bucket, 
+    expected_resource: ResourceAddress, 
+    nft_name: &str
+) {
+    validate_resource_address(bucket.resource_address(), expected_resource, nft_name);
+    assert!(!bucket.is_empty(), "Bucket must contain at least one {}", nft_name);
+}
+
+/// Validates the face value requested in a (partial) close
+/// 
+/// # Arguments
+/// * `bond_amount` - Face value to close
+/// * `face_value` - Face value held by the position
+pub fn validate_close_amount(bond_amount: Decimal, face_value: Decimal) {
+    assert!(bond_amount > math::ZERO, "Bond amount must be positive");
+    assert!(bond_amount <= face_value, "Bond amount exceeds position face value");
+}
+
+/// Validates that a position has matured, so it settles at face value when rolled
+/// 
+/// # Arguments
+/// * `current_time` - Current time
+/// * `maturity_time` - Time when the position matures
+pub fn validate_rollover_maturity(current_time: u64, maturity_time: u64) {
+    assert!(current_time >= maturity_time, "Position must be matured to roll over");
+}
+
+/// Fixed rate a long receives over the term, net of fees
+/// 
+/// # Arguments
+/// * `face_value` - Face value of the long
+/// * `base_paid` - Base paid for the long, fees included
+/// 
+/// # Returns
+/// * Face value over base paid, less one (zero when nothing was paid)
+pub fn long_fixed_rate(face_value: Decimal, base_paid: Decimal) -> Decimal {
+    if base_paid <= math::ZERO {
+        return math::ZERO;
+    }
+    face_value / base_paid - math::ONE
+}
+
+/// Fixed rate a short pays over the term, net of fees
+/// 
+/// # Arguments
+/// * `face_value` - Face value of the short
+/// * `base_paid` - Base deposited for the short, fees included
+/// 
+/// # Returns
+/// * Face value over the implied bond sale price, less one (zero if the deposit covers the face value)
+pub fn short_fixed_rate(face_value: Decimal, base_paid: Decimal) -> Decimal {
+    let sale_price = face_value - base_paid;
+    if sale_price <= math::ZERO {
+        return math::ZERO;
+    }
+    face_value / sale_price - math::ONE
+}
+
+/// Display name of a position NFT, e.g. "Long 991 maturing at epoch 31449600"
+/// 
+/// # Arguments
+/// * `side` - "Long" or "Short"
+/// * `face_value` - Face value of the position
+/// * `maturity_time` - Epoch when the position matures
+/// 
+/// # Returns
+/// * Name shown by wallets
+pub fn position_name(side: &str, face_value: Decimal, maturity_time: u64) -> String {
+    format!("{} {} maturing at epoch {}", side, display_amount(face_value), maturity_time)
+}
+
+/// Display image of a position NFT, rendered from its side, face value and maturity epoch
+/// 
+/// # Arguments
+/// * `image_base_url` - Base URL of the image service
+/// * `side` - "Long" or "Short"
+/// * `face_value` - Face value of the position
+/// * `maturity_time` - Epoch when the position matures
+/// 
+/// # Returns
+/// * Image URL shown by wallets
+pub fn position_image_url(image_base_url: &str, side: &str, face_value: Decimal, maturity_time: u64) -> Url {
+    Url::of(format!(
+        "{}?side={}&face={}&maturity_epoch={}",
+        image_base_url,
+        side.to_lowercase(),
+        display_amount(face_value),
+        maturity_time
+    ))
+}
+
+/// Rounds an amount to two decimals for display
+fn display_amount(amount: Decimal) -> Decimal {
+    amount.checked_round(2, RoundingMode::ToNearestMidpointAwayFromZero).unwrap_or(amount)
+}
@@ -5,25 +5,91 @@ use crate::types::*;
 use crate::helpers::*;
 use crate::events::*;
 use crate::dex::*;
+use crate::positions::*;
+use crate::tokens::*;
 use crate::liquidity::*;
+use crate::governance::*;
+use crate::fees::*;
+use crate::curves::{calculate_bond_reserves_for_rate, calculate_long_face_value};
+use crate::oracle::*;
+use crate::constants::*;
 
 #[blueprint]
+#[events(ModeChangedEvent, FeesProposedEvent, FeesUpdatedEvent, FeeProposalCancelledEvent, GovernanceFeesCollectedEvent, ReferralFeeAccruedEvent, ReferralFeesClaimedEvent)]
 mod hyperdrive_pool {
     enable_method_auth! {
+        roles {
+            fee_manager => updatable_by: [fee_manager_updater];
+            fee_manager_updater => updatable_by: [fee_manager_updater];
+            pauser => updatable_by: [pauser_updater];
+            pauser_updater => updatable_by: [pauser_updater];
+            oracle_updater => updatable_by: [oracle_updater_updater];
+            oracle_updater_updater => updatable_by: [oracle_updater_updater];
+            keeper => updatable_by: [keeper_updater];
+            keeper_updater => updatable_by: [keeper_updater];
+            pool_creator => updatable_by: [pool_creator_updater];
+            pool_creator_updater => updatable_by: [pool_creator_updater];
+        },
         methods {
-            create_pool => PUBLIC;
+            create_pool => restrict_to: [pool_creator];
             open_long => PUBLIC;
             close_long => PUBLIC;
             open_short => PUBLIC;
             close_short => PUBLIC;
+            close_longs => PUBLIC;
+            close_shorts => PUBLIC;
+            merge_positions => PUBLIC;
+            split_position => PUBLIC;
+            tokenize_long => PUBLIC;
+            untokenize => PUBLIC;
+            redeem_bonds => PUBLIC;
+            get_bond_resource => PUBLIC;
+            tokenize_short => PUBLIC;
+            redeem_yield => PUBLIC;
+            get_yield_resource => PUBLIC;
+            rollover_long => PUBLIC;
+            rollover_short => PUBLIC;
             add_liquidity => PUBLIC;
             remove_liquidity => PUBLIC;
             get_pool_state => PUBLIC;
             effective_share_reserves => PUBLIC;
             get_spot_rate => PUBLIC;
-            update_share_price => PUBLIC;
+            get_fixed_rate => PUBLIC;
+            preview_close_long => PUBLIC;
+            get_long_value => PUBLIC;
+            get_short_value => PUBLIC;
+            get_twap_rate => PUBLIC;
+            get_twap_share_price => PUBLIC;
+            get_rate_history => PUBLIC;
+            quote_curve_fee => PUBLIC;
+            get_yield_source => PUBLIC;
+            get_long_positions_resource => PUBLIC;
+            get_position_count => PUBLIC;
+            get_position_summary => PUBLIC;
+            update_share_price => restrict_to: [oracle_updater];
+            set_mode => restrict_to: [pauser];
+            get_mode => PUBLIC;
+            emergency_close => PUBLIC;
+            emergency_remove_liquidity => PUBLIC;
+            propose_fees => restrict_to: [fee_manager];
+            execute_fees => PUBLIC;
+            cancel_fee_proposal => restrict_to: [fee_manager];
+            set_fee_update_delay => restrict_to: [OWNER];
+            get_fees => PUBLIC;
+            get_fee_mode => PUBLIC;
+            get_pending_fees => PUBLIC;
+            set_fee_recipient => restrict_to: [fee_manager];
+            get_fee_recipient => PUBLIC;
+            collect_governance_fees => restrict_to: [keeper];
+            set_referral_share => restrict_to: [fee_manager];
+            get_referral_share => PUBLIC;
+            get_referral_fees => PUBLIC;
+            claim_referral_fees => PUBLIC;
+            deposit_stray => PUBLIC;
+            sweep => restrict_to: [fee_manager];
             get_pool_count => PUBLIC;
-            withdraw_governance_fees => restrict_to: [OWNER];
+            check_invariants => PUBLIC;
+            withdraw_governance_fees => restrict_to: [fee_manager];
         }
     }
 
@@ -44,11 +110,17 @@ mod hyperdrive_pool {
         pool_id: u64,
         share_reserves: Decimal,         // z: Share reserves
         bond_reserves: Decimal,          // y: Bond reserves
+        virtual_bond_reserves: Decimal,  // Bonds seeded at creation to price the curve
         zeta_adjustment: Decimal,        // ζ: Zeta adjustment
         share_price: Decimal,            // c: Current share price
         
         // Bond token
-        bond_resource_address: Option<ResourceAddress>,
+        bond_resources: KeyValueStore<u64, ResourceAddress>,   // Per-maturity bond token by checkpoint
+        bond_checkpoints: KeyValueStore<ResourceAddress, u64>, // Checkpoint by bond token
+        
+        // Yield tokens
+        yield_resources: KeyValueStore<u64, ResourceAddress>,   // Per-maturity yield token by checkpoint
+        yield_checkpoints: KeyValueStore<ResourceAddress, u64>, // Checkpoint by yield token
         
         // LP tokens
         active_lp_shares_address: Option<ResourceAddress>,
@@ -62,10 +134,16 @@ mod hyperdrive_pool {
         current_checkpoint: u64,         // Current checkpoint ID
         
         // Fees
-        new_bond_fee: Decimal,           // ϕn: Fee for newly minted bonds
-        matured_bond_fee: Decimal,       // ϕm: Fee for matured bonds
-        governance_fee: Decimal,         // ϕg: Governance fee portion
-        zombie_governance_fee: Decimal,  // ϕg,zombie: Zombie interest governance fee
+        fees: FeeSchedule,               // ϕc, ϕf, ϕg,c, ϕg,f and ϕg,zombie
+        fee_mode: FeeMode,               // Fixed or dynamic curve fee
+        pending_fees: Option<FeeProposal>, // Fee change waiting out the delay
+        fee_update_delay: u64,           // Epochs between proposing and executing a fee change
+        referral_share: Decimal,         // Share of a referred trade's governance fee paid to the referrer
+        referral_vaults: KeyValueStore<ComponentAddress, Vault>, // Accrued fees per referrer
+        
+        // Rate oracle
+        observations: KeyValueStore<u64, RateObservation>, // Ring buffer of rate observations by slot
+        observation_count: u64,          // Observations ever written
         
         // Minimum reserves
         min_share_reserves: Decimal,     // zmin: Minimum share reserves
@@ -74,9 +152,14 @@ mod hyperdrive_pool {
         zombie_share_reserves: Decimal,  // zzombie: Zombie share reserves
         zombie_base_reserves: Decimal,   // xzombie: Zombie base reserves
         
+        // Short collateral
+        short_collateral: Decimal,       // Shares backing outstanding shorts
+        
+        // Share price growth the yield source vault does not hold in base
+        accrued_yield: Decimal,          // Base credited to tracked shares by share price updates
+        
         // Vaults
         yield_source_vault: Option<Vault>,       // Holds the base tokens
-        bond_vault: Option<Vault>,               // Holds bond tokens
         active_lp_vault: Option<Vault>,          // Holds active LP tokens
         withdrawal_vault: Option<Vault>,         // Holds withdrawal tokens
         ready_withdrawal_vault: Option<Vault>,   // Holds ready withdrawal tokens
@@ -85,11 +168,24 @@ mod hyperdrive_pool {
         // Position tracking
         long_positions_resource: Option<ResourceAddress>,  // NFT for long positions
         short_positions_resource: Option<ResourceAddress>, // NFT for short positions
+        image_base_url: String,                            // Image service for position NFTs
+        
+        // Governance fee routing
+        fee_recipient: Option<ComponentAddress>,           // Account collected governance fees go to
+        stray_vaults: KeyValueStore<ResourceAddress, Vault>, // Tokens sent to the component by mistake
+        
+        // Emergency controls
+        mode: PoolMode,                  // Which operations the pool accepts
+        position_counter: u64,                             // Last integer ID minted for a position NFT
+        position_summaries: KeyValueStore<NonFungibleLocalId, PositionSummary>, // Outlives burned NFTs
     }
     
     impl HyperdrivePool {
         /// Creates the component with minimal parameters 
         /// 
+        /// Every role is given to the admin badge; use `instantiate_with_roles` to
+        /// hand roles such as `keeper` to separate badges.
+        /// 
         /// # Arguments
         /// * `yield_source` - Resource address of the yield-bearing asset
         /// * `admin_badge` - Badge for administrative operations
@@ -97,6 +193,23 @@ mod hyperdrive_pool {
         /// # Returns
         /// * Global<HyperdrivePool> - The Hyperdrive AMM component
         pub fn instantiate_dex(yield_source: ResourceAddress, admin_badge: ResourceAddress) -> Global<HyperdrivePool> {
+            Self::instantiate_with_roles(yield_source, admin_badge, PoolRoles::single_badge(admin_badge))
+        }
+        
+        /// Creates the component with a separate access rule and updater rule per role
+        /// 
+        /// # Arguments
+        /// * `yield_source` - Resource address of the yield-bearing asset
+        /// * `admin_badge` - Badge for administrative operations, the component owner
+        /// * `roles` - Rules for the fee_manager, pauser, oracle_updater, keeper and pool_creator roles
+        /// 
+        /// # Returns
+        /// * Global<HyperdrivePool> - The Hyperdrive AMM component
+        pub fn instantiate_with_roles(
+            yield_source: ResourceAddress,
+            admin_badge: ResourceAddress,
+            roles: PoolRoles
+        ) -> Global<HyperdrivePool> {
             Self {
                 yield_source,
                 admin_badge,
@@ -108,10 +221,14 @@ mod hyperdrive_pool {
                 pool_id: 0,
                 share_reserves: Decimal::ZERO,
                 bond_reserves: Decimal::ZERO,
+                virtual_bond_reserves: Decimal::ZERO,
                 zeta_adjustment: Decimal::ZERO,
                 share_price: Decimal::ONE,
                 
-                bond_resource_address: None,
+                bond_resources: KeyValueStore::new(),
+                bond_checkpoints: KeyValueStore::new(),
+                yield_resources: KeyValueStore::new(),
+                yield_checkpoints: KeyValueStore::new(),
                 active_lp_shares_address: None,
                 withdrawal_shares_address: None,
                 ready_withdrawal_shares_address: None,
@@ -121,17 +238,23 @@ mod hyperdrive_pool {
                 position_duration: 0,
                 current_checkpoint: 0,
                 
-                new_bond_fee: Decimal::ZERO,
-                matured_bond_fee: Decimal::ZERO,
-                governance_fee: Decimal::ZERO,
-                zombie_governance_fee: Decimal::ZERO,
+                fees: FeeSchedule::default(),
+                fee_mode: FeeMode::Fixed,
+                pending_fees: None,
+                fee_update_delay: DEFAULT_FEE_UPDATE_DELAY,
+                referral_share: DEFAULT_REFERRAL_SHARE,
+                referral_vaults: KeyValueStore::new(),
+                
+                observations: KeyValueStore::new(),
+                observation_count: 0,
                 
                 min_share_reserves: Decimal::ZERO,
                 zombie_share_reserves: Decimal::ZERO,
                 zombie_base_reserves: Decimal::ZERO,
+                short_collateral: Decimal::ZERO,
+                accrued_yield: Decimal::ZERO,
                 
                 yield_source_vault: None,
-                bond_vault: None,
                 active_lp_vault: None,
                 withdrawal_vault: None,
                 ready_withdrawal_vault: None,
@@ -139,22 +262,42 @@ mod hyperdrive_pool {
                 
                 long_positions_resource: None,
                 short_positions_resource: None,
+                image_base_url: String::new(),
+                fee_recipient: None,
+                stray_vaults: KeyValueStore::new(),
+                mode: PoolMode::Normal,
+                position_counter: 0,
+                position_summaries: KeyValueStore::new(),
             }
             .instantiate()
             .prepare_to_globalize(OwnerRole::Fixed(rule!(require(admin_badge))))
+            .roles(roles! {
+                fee_manager => roles.fee_manager.rule;
+                fee_manager_updater => roles.fee_manager.updater;
+                pauser => roles.pauser.rule;
+                pauser_updater => roles.pauser.updater;
+                oracle_updater => roles.oracle_updater.rule;
+                oracle_updater_updater => roles.oracle_updater.updater;
+                keeper => roles.keeper.rule;
+                keeper_updater => roles.keeper.updater;
+                pool_creator => roles.pool_creator.rule;
+                pool_creator_updater => roles.pool_creator.updater;
+            })
             .globalize()
         }
         
-        /// Creates a new Hyperdrive pool with full configuration 
+        /// Creates a new Hyperdrive pool with full configuration (pool_creator only)
         
         /// # Arguments
         /// * `checkpoint_duration` - Duration of each checkpoint in seconds
         /// * `position_duration` - Duration of positions in seconds
-        /// * `new_bond_fee` - Fee percentage for newly minted bonds (ϕn)
-        /// * `matured_bond_fee` - Fee percentage for matured bonds (ϕm)
-        /// * `governance_fee` - Governance fee portion (ϕg)
-        /// * `zombie_governance_fee` - Zombie interest governance fee (ϕg,zombie)
+        /// * `fees` - Curve, flat, governance and zombie governance fees, each within its maximum
+        /// * `fee_mode` - Whether the curve fee is fixed or scales with size, utilization and volatility
         /// * `min_share_reserves` - Minimum share reserves (zmin)
+        /// * `initial_fixed_rate` - Annualized fixed rate the curve opens at
+        /// * `info_url` - Project page shown by wallets for the position NFTs
+        /// * `icon_url` - Icon shown by wallets for the position NFT resources
+        /// * `image_base_url` - Image service rendering each position NFT
         /// * `initial_liquidity` - Initial liquidity to seed the pool
         
         /// # Returns
@@ -163,22 +306,24 @@ mod hyperdrive_pool {
             &mut self,
             checkpoint_duration: u64,
             position_duration: u64,
-            new_bond_fee: Decimal,
-            matured_bond_fee: Decimal,
-            governance_fee: Decimal,
-            zombie_governance_fee: Decimal,
+            fees: FeeSchedule,
+            fee_mode: FeeMode,
             min_share_reserves: Decimal,
+            initial_fixed_rate: Decimal,
+            info_url: String,
+            icon_url: String,
+            image_base_url: String,
             initial_liquidity: Bucket,
         ) -> Bucket {
             // Ensure only one pool per component for now (can be extended later)
             assert!(!self.is_initialized, "Pool already initialized");
+            validate_opens_allowed(self.mode);
             
             // Validate parameters
             validate_durations(checkpoint_duration, position_duration);
-            validate_fee(new_bond_fee, "New bond fee");
-            validate_fee(matured_bond_fee, "Matured bond fee");
-            validate_fee(governance_fee, "Governance fee");
-            validate_fee(zombie_governance_fee, "Zombie governance fee");
+            validate_fee_schedule(&fees);
+            validate_fee_mode(&fee_mode);
+            validate_initial_fixed_rate(initial_fixed_rate);
             validate_resource_address(initial_liquidity.resource_address(), self.yield_source, "initial liquidity");
             validate_liquidity_amount(initial_liquidity.amount(), min_share_reserves);
 
@@ -191,27 +336,6 @@ mod hyperdrive_pool {
             let global_component_caller_badge = 
                 NonFungibleGlobalId::global_caller_badge(component_address);
                 
-            // Create bond resource with component as minter
-            let bond_resource = ResourceBuilder::new_fungible(OwnerRole::None)
-                .divisibility(DIVISIBILITY_MAXIMUM)
-                .metadata(metadata!(
-                    init {
-                        "name" => format!("Hyperdrive Bond Token - Pool {}", self.pool_id), locked;
-                        "symbol" => format!("HDB-{}", self.pool_id), locked;
-                    }
-                ))
-                .mint_roles(mint_roles! {
-                    minter => rule!(require(global_component_caller_badge.clone()));
-                    minter_updater => rule!(deny_all);
-                })
-                .burn_roles(burn_roles! {
-                    burner => rule!(require(global_component_caller_badge.clone()));
-                    burner_updater => rule!(deny_all);
-                })
-                .create_with_no_initial_supply();
-
-            self.bond_resource_address = Some(bond_resource.address());
-                
             // Create LP token resources with component as minter
             let active_lp_shares = ResourceBuilder::new_fungible(OwnerRole::None)
                 .divisibility(DIVISIBILITY_MAXIMUM)
@@ -274,11 +398,15 @@ mod hyperdrive_pool {
             self.ready_withdrawal_shares_address = Some(ready_withdrawal_shares.address());
 
             // Create position NFT resources with component as minter
-            let long_positions = ResourceBuilder::new_ruid_non_fungible::<LongPosition>(OwnerRole::None)
+            let long_positions = ResourceBuilder::new_integer_non_fungible::<LongPosition>(OwnerRole::None)
                 .metadata(metadata!(
                     init {
                         "name" => format!("Hyperdrive Long Position - Pool {}", self.pool_id), locked;
                         "symbol" => format!("HDLG-{}", self.pool_id), locked;
+                        "description" => format!("Fixed-rate long positions in Hyperdrive pool {}, one NFT per position", self.pool_id), locked;
+                        "icon_url" => Url::of(icon_url.clone()), locked;
+                        "info_url" => Url::of(info_url.clone()), locked;
+                        "key_image_url" => Url::of(format!("{}?side=long", image_base_url)), locked;
                     }
                 ))
                 .mint_roles(mint_roles! {
@@ -297,11 +425,15 @@ mod hyperdrive_pool {
 
             self.long_positions_resource = Some(long_positions.address());
 
-            let short_positions = ResourceBuilder::new_ruid_non_fungible::<ShortPosition>(OwnerRole::None)
+            let short_positions = ResourceBuilder::new_integer_non_fungible::<ShortPosition>(OwnerRole::None)
                 .metadata(metadata!(
                     init {
                         "name" => format!("Hyperdrive Short Position - Pool {}", self.pool_id), locked;
                         "symbol" => format!("HDSH-{}", self.pool_id), locked;
+                        "description" => format!("Short positions paying the fixed rate in Hyperdrive pool {}, one NFT per position", self.pool_id), locked;
+                        "icon_url" => Url::of(icon_url.clone()), locked;
+                        "info_url" => Url::of(info_url.clone()), locked;
+                        "key_image_url" => Url::of(format!("{}?side=short", image_base_url)), locked;
                     }
                 ))
                 .mint_roles(mint_roles! {
@@ -319,23 +451,30 @@ mod hyperdrive_pool {
                 .create_with_no_initial_supply();
 
             self.short_positions_resource = Some(short_positions.address());
+            self.image_base_url = image_base_url;
             
             // Set pool parameters
             self.checkpoint_duration = checkpoint_duration;
             self.position_duration = position_duration;
-            self.new_bond_fee = new_bond_fee;
-            self.matured_bond_fee = matured_bond_fee;
-            self.governance_fee = governance_fee;
-            self.zombie_governance_fee = zombie_governance_fee;
+            self.fees = fees;
+            self.fee_mode = fee_mode;
             self.min_share_reserves = min_share_reserves;
             
             // Calculate initial share reserves (minus minimum reserves)
             let initial_amount = initial_liquidity.amount();
             self.share_reserves = initial_amount - min_share_reserves;
-            self.bond_reserves = Decimal::ZERO;
             self.zeta_adjustment = Decimal::ZERO;
             self.share_price = Decimal::ONE; // Initial share price
             
+            // Seed virtual bond reserves so the curve opens at the initial fixed rate
+            self.bond_reserves = calculate_bond_reserves_for_rate(
+                self.share_reserves,
+                self.share_price,
+                initial_fixed_rate,
+                position_duration
+            );
+            self.virtual_bond_reserves = self.bond_reserves;
+            
             // Initialize checkpoints
             self.current_checkpoint = initialize_first_checkpoint(
                 &mut self.checkpoints, 
@@ -345,14 +484,14 @@ mod hyperdrive_pool {
             
             // Initialize vaults
             self.yield_source_vault = Some(Vault::with_bucket(initial_liquidity));
-            self.bond_vault = Some(Vault::new(self.bond_resource_address.unwrap()));
             self.active_lp_vault = Some(Vault::new(self.active_lp_shares_address.unwrap()));
             self.withdrawal_vault = Some(Vault::new(self.withdrawal_shares_address.unwrap()));
             self.ready_withdrawal_vault = Some(Vault::new(self.ready_withdrawal_shares_address.unwrap()));
             self.governance_vault = Some(Vault::new(self.yield_source));
             
-            // Mark as initialized
+            // Mark as initialized and start the rate history
             self.is_initialized = true;
+            self.observe();
             
             // Register pool in the registry
             self.pools.insert(self.pool_id, component_address);
@@ -363,11 +502,15 @@ mod hyperdrive_pool {
             initial_lp_tokens
         }
         
-        /// Opens a long position
-        pub fn open_long(&mut self, base_tokens: Bucket) -> Bucket {
+        /// Opens a long position, crediting `referrer` with a share of the governance fee
+        pub fn open_long(&mut self, base_tokens: Bucket, referrer: Option<ComponentAddress>) -> Bucket {
             assert!(self.is_initialized, "Pool not initialized");
+            validate_opens_allowed(self.mode);
+            let volume = self.long_face_value(base_tokens.amount());
+            let fees = self.trade_fees(volume);
+            let governance_before = self.governance_amount();
             
-            open_long_position(
+            let result = open_long_position(
                 base_tokens,
                 &mut self.share_reserves,
                 &mut self.bond_reserves,
@@ -377,21 +520,40 @@ mod hyperdrive_pool {
                 &mut self.current_checkpoint,
                 self.checkpoint_duration,
                 self.position_duration,
-                self.new_bond_fee,
-                self.governance_fee,
+                &fees,
+                self.pool_id,
                 self.long_positions_resource.unwrap(),
+                &mut self.position_counter,
+                &self.position_summaries,
+                &self.image_base_url,
                 self.yield_source,
                 self.governance_vault.as_mut().unwrap(),
                 self.yield_source_vault.as_mut().unwrap()
-            )
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
         }
         
-        /// Closes a long position
-        pub fn close_long(&mut self, position_nft: Bucket) -> Bucket {
+        /// Closes `bond_amount` of a long position's face value, returning the
+        /// proceeds and the position NFT if any face value remains
+        /// 
+        /// Any `referrer` is credited with a share of the governance fee.
+        pub fn close_long(
+            &mut self,
+            position_nft: Bucket,
+            bond_amount: Decimal,
+            referrer: Option<ComponentAddress>
+        ) -> (Bucket, Option<Bucket>) {
             assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            let volume = bond_amount;
+            let fees = self.trade_fees(volume);
+            let governance_before = self.governance_amount();
             
-            close_long_position(
+            let result = close_long_position(
                 position_nft,
+                bond_amount,
                 &mut self.share_reserves,
                 &mut self.bond_reserves,
                 &mut self.zeta_adjustment,
@@ -399,20 +561,32 @@ mod hyperdrive_pool {
                 &mut self.checkpoints,
                 &mut self.current_checkpoint,
                 self.checkpoint_duration,
-                self.new_bond_fee,
-                self.matured_bond_fee,
-                self.governance_fee,
+                &fees,
                 self.long_positions_resource.unwrap(),
+                &self.position_summaries,
+                &self.image_base_url,
                 self.governance_vault.as_mut().unwrap(),
                 self.yield_source_vault.as_mut().unwrap()
-            )
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
         }
         
-        /// Opens a short position
-        pub fn open_short(&mut self, base_tokens: Bucket, face_value: Decimal) -> (Bucket, Bucket) {
+        /// Opens a short position, crediting `referrer` with a share of the governance fee
+        pub fn open_short(
+            &mut self,
+            base_tokens: Bucket,
+            face_value: Decimal,
+            referrer: Option<ComponentAddress>
+        ) -> (Bucket, Bucket) {
             assert!(self.is_initialized, "Pool not initialized");
+            validate_opens_allowed(self.mode);
+            let volume = face_value;
+            let fees = self.trade_fees(volume);
+            let governance_before = self.governance_amount();
             
-            open_short_position(
+            let result = open_short_position(
                 base_tokens,
                 face_value,
                 &mut self.share_reserves,
@@ -423,21 +597,41 @@ mod hyperdrive_pool {
                 &mut self.current_checkpoint,
                 self.checkpoint_duration,
                 self.position_duration,
-                self.new_bond_fee,
-                self.governance_fee,
+                &fees,
+                &mut self.short_collateral,
+                self.pool_id,
                 self.short_positions_resource.unwrap(),
+                &mut self.position_counter,
+                &self.position_summaries,
+                &self.image_base_url,
                 self.yield_source,
                 self.governance_vault.as_mut().unwrap(),
                 self.yield_source_vault.as_mut().unwrap()
-            )
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
         }
         
-        /// Closes a short position
-        pub fn close_short(&mut self, position_nft: Bucket) -> Bucket {
+        /// Closes `bond_amount` of a short position's face value, returning the
+        /// proceeds and the position NFT if any face value remains
+        /// 
+        /// Any `referrer` is credited with a share of the governance fee.
+        pub fn close_short(
+            &mut self,
+            position_nft: Bucket,
+            bond_amount: Decimal,
+            referrer: Option<ComponentAddress>
+        ) -> (Bucket, Option<Bucket>) {
             assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            let volume = bond_amount;
+            let fees = self.trade_fees(volume);
+            let governance_before = self.governance_amount();
             
-            close_short_position(
+            let result = close_short_position(
                 position_nft,
+                bond_amount,
                 &mut self.share_reserves,
                 &mut self.bond_reserves,
                 &mut self.zeta_adjustment,
@@ -445,41 +639,376 @@ mod hyperdrive_pool {
                 &mut self.checkpoints,
                 &mut self.current_checkpoint,
                 self.checkpoint_duration,
-                self.new_bond_fee,
-                self.matured_bond_fee,
-                self.governance_fee,
+                &fees,
+                &mut self.short_collateral,
                 self.short_positions_resource.unwrap(),
+                &self.position_summaries,
+                &self.image_base_url,
                 self.governance_vault.as_mut().unwrap(),
                 self.yield_source_vault.as_mut().unwrap()
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
+        }
+        
+        /// Closes every long position in the bucket, returning the total proceeds
+        /// and a per-NFT breakdown
+        /// 
+        /// Any `referrer` is credited with a share of the governance fee.
+        pub fn close_longs(
+            &mut self,
+            position_nfts: Bucket,
+            referrer: Option<ComponentAddress>
+        ) -> (Bucket, Vec<ClosedPosition>) {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            let volume = total_long_face_value(&position_nfts);
+            let fees = self.trade_fees(volume);
+            let governance_before = self.governance_amount();
+            
+            let result = close_long_positions(
+                position_nfts,
+                &mut self.share_reserves,
+                &mut self.bond_reserves,
+                &mut self.zeta_adjustment,
+                self.share_price,
+                &mut self.checkpoints,
+                &mut self.current_checkpoint,
+                self.checkpoint_duration,
+                &fees,
+                self.long_positions_resource.unwrap(),
+                &self.position_summaries,
+                self.governance_vault.as_mut().unwrap(),
+                self.yield_source_vault.as_mut().unwrap()
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
+        }
+        
+        /// Closes every short position in the bucket, returning the total proceeds
+        /// and a per-NFT breakdown
+        /// 
+        /// Any `referrer` is credited with a share of the governance fee.
+        pub fn close_shorts(
+            &mut self,
+            position_nfts: Bucket,
+            referrer: Option<ComponentAddress>
+        ) -> (Bucket, Vec<ClosedPosition>) {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            let volume = total_short_face_value(&position_nfts);
+            let fees = self.trade_fees(volume);
+            let governance_before = self.governance_amount();
+            
+            let result = close_short_positions(
+                position_nfts,
+                &mut self.share_reserves,
+                &mut self.bond_reserves,
+                &mut self.zeta_adjustment,
+                self.share_price,
+                &mut self.checkpoints,
+                &mut self.current_checkpoint,
+                self.checkpoint_duration,
+                &fees,
+                &mut self.short_collateral,
+                self.short_positions_resource.unwrap(),
+                &self.position_summaries,
+                self.governance_vault.as_mut().unwrap(),
+                self.yield_source_vault.as_mut().unwrap()
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
+        }
+        
+        /// Merges long or short position NFTs from one checkpoint into a single NFT
+        pub fn merge_positions(&mut self, position_nfts: Bucket) -> Bucket {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            
+            if position_nfts.resource_address() == self.short_positions_resource.unwrap() {
+                merge_short_positions(
+                    position_nfts,
+                    self.short_positions_resource.unwrap(),
+                    &self.image_base_url,
+                    &mut self.position_counter,
+                    &self.position_summaries
+                )
+            } else {
+                merge_long_positions(
+                    position_nfts,
+                    self.long_positions_resource.unwrap(),
+                    &self.image_base_url,
+                    &mut self.position_counter,
+                    &self.position_summaries
+                )
+            }
+        }
+        
+        /// Splits a long or short position NFT into one NFT per face value in `amounts`
+        pub fn split_position(&mut self, position_nft: Bucket, amounts: Vec<Decimal>) -> Bucket {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            
+            if position_nft.resource_address() == self.short_positions_resource.unwrap() {
+                split_short_position(
+                    position_nft,
+                    amounts,
+                    self.short_positions_resource.unwrap(),
+                    &self.image_base_url,
+                    &mut self.position_counter,
+                    &self.position_summaries
+                )
+            } else {
+                split_long_position(
+                    position_nft,
+                    amounts,
+                    self.long_positions_resource.unwrap(),
+                    &self.image_base_url,
+                    &mut self.position_counter,
+                    &self.position_summaries
+                )
+            }
+        }
+        
+        /// Burns a long position NFT and mints fungible bond tokens for its maturity
+        pub fn tokenize_long(&mut self, position_nft: Bucket) -> Bucket {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            
+            tokenize_long_position(
+                position_nft,
+                self.pool_id,
+                self.long_positions_resource.unwrap(),
+                &self.position_summaries,
+                &self.bond_resources,
+                &self.bond_checkpoints
+            )
+        }
+        
+        /// Burns bond tokens and mints a long position NFT for the same maturity
+        pub fn untokenize(&mut self, bond_tokens: Bucket) -> Bucket {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            
+            untokenize_bonds(
+                bond_tokens,
+                self.position_duration,
+                self.pool_id,
+                self.long_positions_resource.unwrap(),
+                &mut self.position_counter,
+                &self.position_summaries,
+                &self.image_base_url,
+                &self.bond_checkpoints
             )
         }
         
+        /// Redeems matured bond tokens for base, crediting `referrer` with a share of
+        /// the governance fee
+        pub fn redeem_bonds(&mut self, bond_tokens: Bucket, referrer: Option<ComponentAddress>) -> Bucket {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            let volume = bond_tokens.amount();
+            let governance_before = self.governance_amount();
+            
+            let result = redeem_bond_tokens(
+                bond_tokens,
+                &mut self.share_reserves,
+                &mut self.bond_reserves,
+                &mut self.zeta_adjustment,
+                self.share_price,
+                &mut self.checkpoints,
+                &mut self.current_checkpoint,
+                self.checkpoint_duration,
+                self.position_duration,
+                &self.fees,
+                &self.bond_checkpoints,
+                self.governance_vault.as_mut().unwrap(),
+                self.yield_source_vault.as_mut().unwrap()
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
+        }
+        
+        /// Returns the bond token of a checkpoint, if any long from it was tokenized
+        pub fn get_bond_resource(&self, checkpoint: u64) -> Option<ResourceAddress> {
+            self.bond_resources.get(&checkpoint).map(|resource| *resource)
+        }
+        
+        /// Burns a short position NFT and mints fungible yield tokens for its maturity
+        pub fn tokenize_short(&mut self, position_nft: Bucket) -> Bucket {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            
+            let result = tokenize_short_position(
+                position_nft,
+                self.pool_id,
+                &mut self.share_reserves,
+                &self.checkpoints,
+                &mut self.short_collateral,
+                self.short_positions_resource.unwrap(),
+                &self.position_summaries,
+                &self.yield_resources,
+                &self.yield_checkpoints
+            );
+            self.observe();
+            result
+        }
+        
+        /// Redeems matured yield tokens for the variable interest they accrued,
+        /// crediting `referrer` with a share of the governance fee
+        pub fn redeem_yield(&mut self, yield_tokens: Bucket, referrer: Option<ComponentAddress>) -> Bucket {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            let volume = yield_tokens.amount();
+            let governance_before = self.governance_amount();
+            
+            let result = redeem_yield_tokens(
+                yield_tokens,
+                &mut self.share_reserves,
+                &mut self.zeta_adjustment,
+                self.share_price,
+                &mut self.checkpoints,
+                &mut self.current_checkpoint,
+                self.checkpoint_duration,
+                self.position_duration,
+                &self.fees,
+                &mut self.short_collateral,
+                &self.yield_checkpoints,
+                self.governance_vault.as_mut().unwrap(),
+                self.yield_source_vault.as_mut().unwrap()
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
+        }
+        
+        /// Returns the yield token of a checkpoint, if any short from it was tokenized
+        pub fn get_yield_resource(&self, checkpoint: u64) -> Option<ResourceAddress> {
+            self.yield_resources.get(&checkpoint).map(|resource| *resource)
+        }
+        
+        /// Rolls a matured long into a new long in the current
+        /// checkpoint, returning the new position NFT and any base dust
+        /// 
+        /// Any `referrer` is credited with a share of the governance fee.
+        pub fn rollover_long(
+            &mut self,
+            position_nft: Bucket,
+            min_face_value: Decimal,
+            referrer: Option<ComponentAddress>
+        ) -> (Bucket, Bucket) {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_opens_allowed(self.mode);
+            let volume = total_long_face_value(&position_nft);
+            let fees = self.trade_fees(volume);
+            let governance_before = self.governance_amount();
+            
+            let result = rollover_long_position(
+                position_nft,
+                min_face_value,
+                &mut self.share_reserves,
+                &mut self.bond_reserves,
+                &mut self.zeta_adjustment,
+                self.share_price,
+                &mut self.checkpoints,
+                &mut self.current_checkpoint,
+                self.checkpoint_duration,
+                self.position_duration,
+                &fees,
+                self.pool_id,
+                self.long_positions_resource.unwrap(),
+                &mut self.position_counter,
+                &self.position_summaries,
+                &self.image_base_url,
+                self.governance_vault.as_mut().unwrap(),
+                self.yield_source_vault.as_mut().unwrap()
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
+        }
+        
+        /// Rolls a matured short into a new short of `new_face_value`,
+        /// reusing its settled interest as deposit and returning the new NFT and change
+        /// 
+        /// Any `referrer` is credited with a share of the governance fee.
+        pub fn rollover_short(
+            &mut self,
+            position_nft: Bucket,
+            new_face_value: Decimal,
+            max_deposit: Bucket,
+            referrer: Option<ComponentAddress>
+        ) -> (Bucket, Bucket) {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_opens_allowed(self.mode);
+            let volume = new_face_value;
+            let fees = self.trade_fees(volume);
+            let governance_before = self.governance_amount();
+            
+            let result = rollover_short_position(
+                position_nft,
+                new_face_value,
+                max_deposit,
+                &mut self.share_reserves,
+                &mut self.bond_reserves,
+                &mut self.zeta_adjustment,
+                self.share_price,
+                &mut self.checkpoints,
+                &mut self.current_checkpoint,
+                self.checkpoint_duration,
+                self.position_duration,
+                &fees,
+                &mut self.short_collateral,
+                self.pool_id,
+                self.short_positions_resource.unwrap(),
+                &mut self.position_counter,
+                &self.position_summaries,
+                &self.image_base_url,
+                self.yield_source,
+                self.governance_vault.as_mut().unwrap(),
+                self.yield_source_vault.as_mut().unwrap()
+            );
+            self.accrue_referral(referrer, volume, governance_before);
+            self.observe();
+            result
+        }
+        
         /// Adds liquidity to the pool
         pub fn add_liquidity(&mut self, base_tokens: Bucket) -> Bucket {
             assert!(self.is_initialized, "Pool not initialized");
+            validate_opens_allowed(self.mode);
             
-            add_liquidity(
+            let result = add_liquidity(
                 base_tokens,
                 &mut self.share_reserves,
                 &mut self.bond_reserves,
+                &mut self.virtual_bond_reserves,
                 self.share_price,
-                self.active_lp_vault.as_ref().unwrap().amount(),
+                self.active_lp_supply(),
                 self.active_lp_shares_address.unwrap(),
                 self.yield_source,
                 self.yield_source_vault.as_mut().unwrap()
-            )
+            );
+            self.observe();
+            result
         }
         
         /// Removes liquidity from the pool
         pub fn remove_liquidity(&mut self, lp_tokens: Bucket) -> (Bucket, Bucket) {
             assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
             
-            remove_liquidity(
+            let result = remove_liquidity(
                 lp_tokens,
                 &mut self.share_reserves,
                 &mut self.bond_reserves,
+                &mut self.virtual_bond_reserves,
                 self.share_price,
-                self.active_lp_vault.as_ref().unwrap().amount(),
+                self.active_lp_supply(),
                 self.min_share_reserves,
                 &self.checkpoints,
                 self.current_checkpoint,
@@ -488,7 +1017,9 @@ mod hyperdrive_pool {
                 self.active_lp_shares_address.unwrap(),
                 self.withdrawal_shares_address.unwrap(),
                 self.yield_source_vault.as_mut().unwrap()
-            )
+            );
+            self.observe();
+            result
         }
         
         /// Gets the current pool state
@@ -500,7 +1031,7 @@ mod hyperdrive_pool {
                 self.bond_reserves,
                 self.zeta_adjustment,
                 self.share_price,
-                self.active_lp_vault.as_ref().unwrap().amount(),
+                self.active_lp_supply(),
                 self.withdrawal_vault.as_ref().unwrap().amount(),
                 self.ready_withdrawal_vault.as_ref().unwrap().amount(),
                 self.zombie_share_reserves,
@@ -522,21 +1053,495 @@ mod hyperdrive_pool {
             calculate_spot_rate(effective_shares, self.bond_reserves, self.share_price)
         }
         
-        /// Updates the share price from the yield source
+        /// Gets the annualized fixed rate a long opened now would lock, before fees
+        pub fn get_fixed_rate(&self) -> Decimal {
+            assert!(self.is_initialized, "Pool not initialized");
+            calculate_fixed_rate(
+                self.effective_share_reserves(),
+                self.bond_reserves,
+                self.share_price,
+                self.position_duration
+            )
+        }
+        
+        /// Previews the base proceeds of closing `bond_amount` of a long position now
+        pub fn preview_close_long(&self, position_id: NonFungibleLocalId, bond_amount: Decimal) -> Decimal {
+            assert!(self.is_initialized, "Pool not initialized");
+            let fees = self.trade_fees(bond_amount);
+            
+            preview_close_long_position(
+                &position_id,
+                bond_amount,
+                self.share_reserves,
+                self.bond_reserves,
+                self.zeta_adjustment,
+                self.share_price,
+                &self.checkpoints,
+                &fees,
+                self.long_positions_resource.unwrap()
+            )
+        }
+        
+        /// Gets the current value of a long position NFT
+        pub fn get_long_value(&self, position_id: NonFungibleLocalId) -> PositionValue {
+            assert!(self.is_initialized, "Pool not initialized");
+            let position: LongPosition = NonFungibleResourceManager::from(self.long_positions_resource.unwrap())
+                .get_non_fungible_data(&position_id);
+            let fees = self.trade_fees(position.face_value);
+            
+            long_position_value(
+                &position,
+                self.share_reserves,
+                self.bond_reserves,
+                self.zeta_adjustment,
+                self.share_price,
+                &self.checkpoints,
+                &fees
+            )
+        }
+        
+        /// Gets the current value of a short position NFT
+        pub fn get_short_value(&self, position_id: NonFungibleLocalId) -> PositionValue {
+            assert!(self.is_initialized, "Pool not initialized");
+            let position: ShortPosition = NonFungibleResourceManager::from(self.short_positions_resource.unwrap())
+                .get_non_fungible_data(&position_id);
+            let fees = self.trade_fees(position.face_value);
+            
+            short_position_value(
+                &position,
+                self.share_reserves,
+                self.bond_reserves,
+                self.zeta_adjustment,
+                self.share_price,
+                &self.checkpoints,
+                &fees,
+                self.short_collateral
+            )
+        }
+        
+        /// Gets the time-weighted average annualized fixed rate over the last `window_epochs`
+        pub fn get_twap_rate(&self, window_epochs: u64) -> Decimal {
+            assert!(self.is_initialized, "Pool not initialized");
+            self.twaps(window_epochs).0
+        }
+        
+        /// Gets the time-weighted average share price over the last `window_epochs`
+        pub fn get_twap_share_price(&self, window_epochs: u64) -> Decimal {
+            assert!(self.is_initialized, "Pool not initialized");
+            self.twaps(window_epochs).1
+        }
+        
+        /// Gets up to `limit` rate observations from `from_time` on, oldest first
+        /// 
+        /// Only the most recent observations are kept; page through longer ranges by
+        /// passing the last returned time plus one as the next `from_time`.
+        pub fn get_rate_history(&self, from_time: u64, limit: u64) -> Vec<RateObservation> {
+            observation_history(&self.observations, self.observation_count, from_time, limit)
+        }
+        
+        /// Quotes the curve fee rate a trade of `trade_size` face value would pay now
+        pub fn quote_curve_fee(&self, trade_size: Decimal) -> CurveFeeQuote {
+            assert!(self.is_initialized, "Pool not initialized");
+            self.curve_fee_quote(trade_size)
+        }
+        
+        /// Gets the base token resource address
+        pub fn get_yield_source(&self) -> ResourceAddress {
+            self.yield_source
+        }
+        
+        /// Gets the long position NFT resource address
+        pub fn get_long_positions_resource(&self) -> ResourceAddress {
+            assert!(self.is_initialized, "Pool not initialized");
+            self.long_positions_resource.unwrap()
+        }
+        
+        /// Gets the number of position NFTs minted, which is also the latest position ID
+        pub fn get_position_count(&self) -> u64 {
+            self.position_counter
+        }
+        
+        /// Gets the summary of a position NFT, including one that has been burned
+        pub fn get_position_summary(&self, position_id: NonFungibleLocalId) -> Option<PositionSummary> {
+            self.position_summaries.get(&position_id).map(|summary| summary.clone())
+        }
+        
+        /// Updates the share price from the yield source (oracle_updater only)
         pub fn update_share_price(&mut self, new_share_price: Decimal) {
             assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            let previous_share_price = self.share_price;
             update_share_price_from_yield_source(new_share_price, &mut self.share_price);
+            self.accrued_yield += self.tracked_shares() * (self.share_price - previous_share_price);
+            self.observe();
+        }
+        
+        /// Sets the pool mode (pauser only)
+        /// 
+        /// CloseOnly blocks opens, rollovers and liquidity adds; Paused blocks every
+        /// state-changing method except the emergency exits. Read-only methods work
+        /// in every mode.
+        pub fn set_mode(&mut self, mode: PoolMode) {
+            let previous_mode = self.mode;
+            self.mode = mode;
+            Runtime::emit_event(ModeChangedEvent { previous_mode, mode });
+        }
+        
+        /// Gets the pool mode
+        pub fn get_mode(&self) -> PoolMode {
+            self.mode
+        }
+        
+        /// Closes every long or short position NFT in the bucket while the pool is
+        /// paused, at the last checkpoint's share price and without fees
+        pub fn emergency_close(&mut self, position_nfts: Bucket) -> (Bucket, Vec<ClosedPosition>) {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_paused(self.mode);
+            
+            let share_price = self.last_checkpoint_share_price();
+            let result = if position_nfts.resource_address() == self.short_positions_resource.unwrap() {
+                close_short_positions(
+                    position_nfts,
+                    &mut self.share_reserves,
+                    &mut self.bond_reserves,
+                    &mut self.zeta_adjustment,
+                    share_price,
+                    &mut self.checkpoints,
+                    &mut self.current_checkpoint,
+                    self.checkpoint_duration,
+                    &FeeSchedule::default(),
+                    &mut self.short_collateral,
+                    self.short_positions_resource.unwrap(),
+                    &self.position_summaries,
+                    self.governance_vault.as_mut().unwrap(),
+                    self.yield_source_vault.as_mut().unwrap()
+                )
+            } else {
+                close_long_positions(
+                    position_nfts,
+                    &mut self.share_reserves,
+                    &mut self.bond_reserves,
+                    &mut self.zeta_adjustment,
+                    share_price,
+                    &mut self.checkpoints,
+                    &mut self.current_checkpoint,
+                    self.checkpoint_duration,
+                    &FeeSchedule::default(),
+                    self.long_positions_resource.unwrap(),
+                    &self.position_summaries,
+                    self.governance_vault.as_mut().unwrap(),
+                    self.yield_source_vault.as_mut().unwrap()
+                )
+            };
+            self.observe();
+            result
+        }
+        
+        /// Removes liquidity while the pool is paused, at the last checkpoint's share price
+        pub fn emergency_remove_liquidity(&mut self, lp_tokens: Bucket) -> (Bucket, Bucket) {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_paused(self.mode);
+            
+            let share_price = self.last_checkpoint_share_price();
+            let result = remove_liquidity(
+                lp_tokens,
+                &mut self.share_reserves,
+                &mut self.bond_reserves,
+                &mut self.virtual_bond_reserves,
+                share_price,
+                self.active_lp_supply(),
+                self.min_share_reserves,
+                &self.checkpoints,
+                self.current_checkpoint,
+                self.checkpoint_duration,
+                self.position_duration,
+                self.active_lp_shares_address.unwrap(),
+                self.withdrawal_shares_address.unwrap(),
+                self.yield_source_vault.as_mut().unwrap()
+            );
+            self.observe();
+            result
+        }
+        
+        /// Gets the share price recorded by the current checkpoint, which predates any
+        /// price pushed after the last checkpoint was minted
+        fn last_checkpoint_share_price(&self) -> Decimal {
+            self.checkpoints.get(&self.current_checkpoint)
+                .expect("Current checkpoint not found")
+                .share_price
+        }
+        
+        /// Proposes new fees, executable once the fee update delay has passed (fee_manager only)
+        /// 
+        /// Each fee is bounded by its maximum in `constants::validation`. Only one
+        /// proposal can be pending; cancel it to propose another.
+        pub fn propose_fees(&mut self, fees: FeeSchedule) {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            
+            let proposal = propose_fee_schedule(
+                fees,
+                &self.pending_fees,
+                self.fee_update_delay,
+                Runtime::current_epoch().number()
+            );
+            Runtime::emit_event(FeesProposedEvent {
+                fees: proposal.fees.clone(),
+                executable_time: proposal.executable_time,
+            });
+            self.pending_fees = Some(proposal);
+        }
+        
+        /// Applies the pending fee proposal once its delay has passed
+        /// 
+        /// Anyone may execute: the outcome was fixed and announced when it was proposed.
+        pub fn execute_fees(&mut self) {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            
+            let fees = take_executable_fees(&mut self.pending_fees, Runtime::current_epoch().number());
+            let previous_fees = std::mem::replace(&mut self.fees, fees.clone());
+            Runtime::emit_event(FeesUpdatedEvent { previous_fees, fees });
+        }
+        
+        /// Withdraws the pending fee proposal (fee_manager only); allowed in every mode
+        pub fn cancel_fee_proposal(&mut self) {
+            let proposal = self.pending_fees.take().expect("No fee proposal pending");
+            Runtime::emit_event(FeeProposalCancelledEvent { fees: proposal.fees });
+        }
+        
+        /// Sets the delay between proposing and executing a fee change (admin only)
+        /// 
+        /// A pending proposal keeps the executable time it was given.
+        pub fn set_fee_update_delay(&mut self, fee_update_delay: u64) {
+            validate_closes_allowed(self.mode);
+            validate_fee_update_delay(fee_update_delay);
+            self.fee_update_delay = fee_update_delay;
+        }
+        
+        /// Gets the fees in effect
+        pub fn get_fees(&self) -> FeeSchedule {
+            self.fees.clone()
+        }
+        
+        /// Gets whether the curve fee is fixed or dynamic
+        pub fn get_fee_mode(&self) -> FeeMode {
+            self.fee_mode.clone()
+        }
+        
+        /// Gets the pending fee proposal, if any, and when it can take effect
+        pub fn get_pending_fees(&self) -> Option<FeeProposal> {
+            self.pending_fees.clone()
+        }
+        
+        /// Withdraws governance fees (fee_manager only)
+        pub fn withdraw_governance_fees(&mut self) -> Bucket {
+            assert!(self.is_initialized, "Pool not initialized");
+            validate_closes_allowed(self.mode);
+            withdraw_governance_fees(self.governance_vault.as_mut().unwrap())
+        }
+        
+        /// Sets the account that `collect_governance_fees` deposits into (fee_manager only)
+        pub fn set_fee_recipient(&mut self, fee_recipient: ComponentAddress) {
+            validate_closes_allowed(self.mode);
+            validate_account_address(fee_recipient, "Fee recipient");
+            self.fee_recipient = Some(fee_recipient);
+        }
+        
+        /// Gets the account governance fees are collected into, if set
+        pub fn get_fee_recipient(&self) -> Option<ComponentAddress> {
+            self.fee_recipient
         }
         
-        /// Withdraws governance fees (admin only)
-        pub fn withdraw_governance_fees(&mut self, auth: Proof) -> Bucket {
+        /// Deposits all governance fees into the fee recipient (keeper only)
+        pub fn collect_governance_fees(&mut self) {
             assert!(self.is_initialized, "Pool not initialized");
-            withdraw_governance_fees(self.admin_badge, auth, self.governance_vault.as_mut().unwrap())
+            validate_closes_allowed(self.mode);
+            
+            let (fee_recipient, amount) = collect_governance_fees(
+                self.fee_recipient,
+                self.governance_vault.as_mut().unwrap()
+            );
+            Runtime::emit_event(GovernanceFeesCollectedEvent { fee_recipient, amount });
+        }
+        
+        /// Sets the share of a referred trade's governance fee paid to the referrer (fee_manager only)
+        pub fn set_referral_share(&mut self, referral_share: Decimal) {
+            validate_closes_allowed(self.mode);
+            validate_referral_share(referral_share);
+            self.referral_share = referral_share;
+        }
+        
+        /// Gets the share of a referred trade's governance fee paid to the referrer
+        pub fn get_referral_share(&self) -> Decimal {
+            self.referral_share
+        }
+        
+        /// Gets the fees accrued to a referrer and not yet claimed
+        pub fn get_referral_fees(&self, referrer: ComponentAddress) -> Decimal {
+            self.referral_vaults.get(&referrer).map(|vault| vault.amount()).unwrap_or_default()
+        }
+        
+        /// Deposits a referrer's accrued fees into the referrer's account
+        pub fn claim_referral_fees(&mut self, referrer: ComponentAddress) {
+            validate_closes_allowed(self.mode);
+            
+            let amount = claim_referral_fees(referrer, &self.referral_vaults);
+            Runtime::emit_event(ReferralFeesClaimedEvent { referrer, amount });
+        }
+        
+        /// Holds tokens sent to the pool by mistake until they are swept
+        /// 
+        /// Pool resources are rejected so they are never stranded outside the pool's vaults.
+        pub fn deposit_stray(&mut self, bucket: Bucket) {
+            validate_closes_allowed(self.mode);
+            let resource = bucket.resource_address();
+            validate_sweepable(resource, &self.protected_resources(), &self.bond_checkpoints, &self.yield_checkpoints);
+            
+            if self.stray_vaults.get(&resource).is_some() {
+                self.stray_vaults.get_mut(&resource).unwrap().put(bucket);
+            } else {
+                self.stray_vaults.insert(resource, Vault::with_bucket(bucket));
+            }
+        }
+        
+        /// Recovers every token of a resource held outside the pool's vaults (fee_manager only)
+        /// 
+        /// Base, LP, position, bond and yield resources can never be swept.
+        pub fn sweep(&mut self, resource: ResourceAddress) -> Bucket {
+            validate_closes_allowed(self.mode);
+            validate_sweepable(resource, &self.protected_resources(), &self.bond_checkpoints, &self.yield_checkpoints);
+            
+            match self.stray_vaults.get_mut(&resource) {
+                Some(mut vault) => vault.take_all(),
+                None => Bucket::new(resource),
+            }
+        }
+        
+        /// Gets the resources held in the pool's own vaults or minted by the pool
+        fn protected_resources(&self) -> Vec<ResourceAddress> {
+            vec![
+                Some(self.yield_source),
+                self.active_lp_shares_address,
+                self.withdrawal_shares_address,
+                self.ready_withdrawal_shares_address,
+                self.long_positions_resource,
+                self.short_positions_resource,
+            ].into_iter().flatten().collect()
+        }
+        
+        /// Quotes the curve fee rate of a trade under the pool's fee mode
+        fn curve_fee_quote(&self, trade_size: Decimal) -> CurveFeeQuote {
+            quote_trade_curve_fee(
+                &self.fee_mode,
+                &self.fees,
+                trade_size,
+                self.share_reserves,
+                self.zeta_adjustment,
+                self.share_price,
+                &self.checkpoints,
+                self.current_checkpoint,
+                self.checkpoint_duration,
+                self.position_duration
+            )
+        }
+        
+        /// Gets the fee schedule a trade of `trade_size` face value is charged
+        /// 
+        /// Every trade is sized by the bonds it moves, so a long open passes the
+        /// face value its base buys rather than the base itself.
+        fn trade_fees(&self, trade_size: Decimal) -> FeeSchedule {
+            trade_fee_schedule(&self.fees, &self.curve_fee_quote(trade_size))
+        }
+        
+        /// Estimates the face value `base_amount` buys on the curve, before fees
+        fn long_face_value(&self, base_amount: Decimal) -> Decimal {
+            calculate_long_face_value(
+                base_amount / self.share_price,
+                calculate_effective_share_reserves(self.share_reserves, self.zeta_adjustment),
+                self.bond_reserves
+            )
+        }
+        
+        /// Gets every share the yield source vault backs: reserves, zombie reserves
+        /// and short collateral
+        fn tracked_shares(&self) -> Decimal {
+            self.share_reserves + self.min_share_reserves + self.zombie_share_reserves + self.short_collateral
+        }
+        
+        /// Records the fixed rate and share price after a state change
+        fn observe(&mut self) {
+            let fixed_rate = calculate_fixed_rate(
+                calculate_effective_share_reserves(self.share_reserves, self.zeta_adjustment),
+                self.bond_reserves,
+                self.share_price,
+                self.position_duration
+            );
+            record_observation(
+                &self.observations,
+                &mut self.observation_count,
+                fixed_rate,
+                self.share_price,
+                Runtime::current_epoch().number()
+            );
+        }
+        
+        /// Gets the average fixed rate and share price over the last `window_epochs`
+        fn twaps(&self, window_epochs: u64) -> (Decimal, Decimal) {
+            calculate_twaps(
+                &self.observations,
+                self.observation_count,
+                window_epochs,
+                Runtime::current_epoch().number()
+            )
+        }
+        
+        /// Gets the governance fees held by the pool
+        fn governance_amount(&self) -> Decimal {
+            self.governance_vault.as_ref().unwrap().amount()
+        }
+        
+        /// Accrues the referrer's share of the governance fee charged since `governance_before`
+        fn accrue_referral(&mut self, referrer: Option<ComponentAddress>, volume: Decimal, governance_before: Decimal) {
+            if let Some(referrer) = referrer {
+                validate_referrer(referrer, self.fee_recipient);
+                let governance_fee = self.governance_amount() - governance_before;
+                let fee = accrue_referral_fee(
+                    referrer,
+                    self.referral_share,
+                    governance_fee,
+                    self.governance_vault.as_mut().unwrap(),
+                    &self.referral_vaults
+                );
+                Runtime::emit_event(ReferralFeeAccruedEvent { referrer, volume, fee });
+            }
+        }
+        
+        /// Gets the outstanding supply of active LP tokens
+        fn active_lp_supply(&self) -> Decimal {
+            ResourceManager::from(self.active_lp_shares_address.unwrap())
+                .total_supply()
+                .unwrap_or_default()
         }
         
         /// Gets the total number of pools created
         pub fn get_pool_count(&self) -> u64 {
             self.pool_counter
         }
+        
+        /// Reports the discrepancy between vault balances and internal accounting
+        pub fn check_invariants(&self) -> InvariantReport {
+            assert!(self.is_initialized, "Pool not initialized");
+            
+            check_pool_invariants(
+                self.yield_source_vault.as_ref().unwrap().amount(),
+                self.share_reserves,
+                self.min_share_reserves,
+                self.share_price,
+                self.zombie_share_reserves,
+                self.short_collateral,
+                self.accrued_yield
+            )
+        }
     }
 }
\ No newline at end of file
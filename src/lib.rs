@@ -1,8 +1,15 @@
 pub mod influxpool;
 pub mod types;
 mod constants;
-mod helpers;
+pub mod helpers;
 pub mod curves;
+pub mod fees;
+pub mod pool_core;
 mod events;
 pub mod dex;
-pub mod liquidity;
\ No newline at end of file
+pub mod positions;
+pub mod tokens;
+pub mod liquidity;
+pub mod governance;
+pub mod oracle;
+pub mod fixed_rate_vault;
\ No newline at end of file
@@ -3,7 +3,7 @@ use crate::types::*;
 use crate::constants::*;
 use crate::helpers::*;
 use crate::curves::*;
-use crate::events::*;
+use crate::pool_core::*;
 
 /// Adds liquidity to the Hyperdrive AMM pool
 /// 
@@ -19,6 +19,7 @@ pub fn add_liquidity(
     base_tokens: Bucket,
     share_reserves: &mut Decimal,
     bond_reserves: &mut Decimal,
+    virtual_bond_reserves: &mut Decimal,
     share_price: Decimal,
     active_lp_shares: Decimal,
     active_lp_shares_resource: ResourceAddress,
@@ -31,28 +32,15 @@ pub fn add_liquidity(
     let base_amount = base_tokens.amount();
     validate_liquidity_amount(base_amount, validation::MIN_LIQUIDITY);
     
-    // Convert base tokens to shares
-    let share_amount = base_amount / share_price;
-    
-    // Calculate LP tokens to mint
-    let lp_tokens_to_mint = if active_lp_shares <= math::ZERO {
-        // First liquidity provision - mint 1:1 with shares
-        share_amount
-    } else {
-        // Calculate based on current pool value
-        let lp_present_value = calculate_lp_present_value(
-            *share_reserves,
-            *bond_reserves,
-            share_price,
-            active_lp_shares
-        );
-        
-        // LP tokens = (share_amount * share_price) / lp_present_value
-        (share_amount * share_price) / lp_present_value
-    };
-    
-    // Update pool state
-    *share_reserves += share_amount;
+    // Apply the deposit to the pool
+    let lp_tokens_to_mint = apply_add_liquidity(
+        base_amount,
+        share_reserves,
+        bond_reserves,
+        virtual_bond_reserves,
+        share_price,
+        active_lp_shares
+    );
     
     // Deposit base tokens
     yield_source_vault.put(base_tokens);
@@ -76,6 +64,7 @@ pub fn remove_liquidity(
     lp_tokens: Bucket,
     share_reserves: &mut Decimal,
     bond_reserves: &mut Decimal,
+    virtual_bond_reserves: &mut Decimal,
     share_price: Decimal,
     active_lp_shares: Decimal,
     min_share_reserves: Decimal,
@@ -94,38 +83,21 @@ pub fn remove_liquidity(
     assert!(lp_amount > math::ZERO, "LP amount must be positive");
     assert!(lp_amount <= active_lp_shares, "Insufficient LP tokens");
     
-    // Calculate LP present value
-    let lp_present_value = calculate_lp_present_value(
-        *share_reserves,
-        *bond_reserves,
+    // Apply the withdrawal to the pool
+    let (immediate_withdrawal, withdrawal_shares_amount) = apply_remove_liquidity(
+        lp_amount,
+        share_reserves,
+        bond_reserves,
+        virtual_bond_reserves,
         share_price,
-        active_lp_shares
-    );
-    
-    // Calculate total value to withdraw
-    let total_value_to_withdraw = lp_amount * lp_present_value;
-    
-    // Calculate solvency requirement
-    let solvency_requirement = calculate_solvency_requirement(
+        active_lp_shares,
+        min_share_reserves,
         checkpoints,
         current_checkpoint,
         checkpoint_duration,
         position_duration
     );
     
-    // Calculate available liquidity for immediate withdrawal
-    let available_share_value = (*share_reserves - min_share_reserves - (solvency_requirement / share_price))
-        .max(math::ZERO) * share_price;
-    
-    // Determine immediate withdrawal amount
-    let immediate_withdrawal = total_value_to_withdraw.min(available_share_value);
-    let withdrawal_shares_amount = total_value_to_withdraw - immediate_withdrawal;
-    
-    // Update pool state
-    if immediate_withdrawal > math::ZERO {
-        *share_reserves -= immediate_withdrawal / share_price;
-    }
-    
     // Burn LP tokens
     lp_tokens.burn();
     
@@ -159,6 +131,7 @@ pub fn remove_liquidity(
 pub fn distribute_excess_idle_liquidity(
     share_reserves: &mut Decimal,
     bond_reserves: &mut Decimal,
+    virtual_bond_reserves: &mut Decimal,
     zeta_adjustment: &mut Decimal,
     share_price: Decimal,
     withdrawal_shares: Decimal,
@@ -190,6 +163,7 @@ pub fn distribute_excess_idle_liquidity(
     let lp_present_value = calculate_lp_present_value(
         *share_reserves,
         *bond_reserves,
+        *virtual_bond_reserves,
         share_price,
         active_lp_shares
     );
@@ -209,6 +183,7 @@ pub fn distribute_excess_idle_liquidity(
     let share_ratio = *share_reserves / (*share_reserves + (idle_liquidity / share_price));
     *zeta_adjustment = *zeta_adjustment * share_ratio;
     *bond_reserves = *bond_reserves * share_ratio;
+    *virtual_bond_reserves = *virtual_bond_reserves * share_ratio;
     
     // Mint ready withdrawal shares
     let ready_shares = FungibleResourceManager::from(ready_withdrawal_shares_resource)
@@ -322,22 +297,13 @@ pub fn update_share_price_from_yield_source(
     *share_price = new_share_price;
 }
 
-/// Withdraws governance fees (admin only)
+/// Withdraws governance fees (fee_manager only, enforced by the method's role)
 /// 
 /// # Arguments
-/// * `admin_badge` - Admin badge for authorization
 /// * `governance_vault` - Vault containing governance fees (mutable)
 /// 
 /// # Returns
 /// * All governance fees as a bucket
-pub fn withdraw_governance_fees(
-    admin_badge: ResourceAddress,
-    auth: Proof,
-    governance_vault: &mut Vault
-) -> Bucket {
-    // Validate admin authorization
-    auth.check(admin_badge);
-    
-    // Take all governance fees
+pub fn withdraw_governance_fees(governance_vault: &mut Vault) -> Bucket {
     governance_vault.take_all()
 }
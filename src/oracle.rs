@@ -0,0 +1,209 @@
+use scrypto::prelude::*;
+use crate::types::*;
+use crate::constants::*;
+
+// The oracle keeps a ring buffer of the last OBSERVATION_CAPACITY observations,
+// keyed by slot. Each observation carries the running sums of the fixed rate and
+// the share price, each weighted by the epochs it was in effect, so the average
+// over any window inside the buffer is
+//
+//   TWAP = (cumulative(now) - cumulative(now - window)) / window
+//
+// where a cumulative between two observations is extrapolated from the earlier
+// one, since both values only change when an observation is written.
+
+/// Calculates the observation that follows `last` at `time`
+///
+/// # Arguments
+/// * `last` - Most recent observation
+/// * `fixed_rate` - Annualized fixed rate from `time` on
+/// * `share_price` - Share price from `time` on
+/// * `time` - Current epoch
+///
+/// # Returns
+/// * New observation with the accumulators carried forward to `time`
+pub fn accumulate_observation(
+    last: &RateObservation,
+    fixed_rate: Decimal,
+    share_price: Decimal,
+    time: u64
+) -> RateObservation {
+    let (cumulative_rate, cumulative_share_price) = cumulatives_at(last, time);
+
+    RateObservation {
+        time,
+        fixed_rate,
+        share_price,
+        cumulative_rate,
+        cumulative_share_price,
+    }
+}
+
+/// Extrapolates an observation's accumulators to a later time
+///
+/// # Arguments
+/// * `observation` - Latest observation at or before `time`
+/// * `time` - Time to extrapolate to
+///
+/// # Returns
+/// * Tuple of (cumulative rate, cumulative share price) at `time`
+pub fn cumulatives_at(observation: &RateObservation, time: u64) -> (Decimal, Decimal) {
+    let elapsed = Decimal::from(time - observation.time);
+
+    (
+        observation.cumulative_rate + observation.fixed_rate * elapsed,
+        observation.cumulative_share_price + observation.share_price * elapsed,
+    )
+}
+
+/// Records the pool's fixed rate and share price in the observation ring buffer
+///
+/// A second observation at the same time replaces the first without moving
+/// the accumulators.
+///
+/// # Arguments
+/// * `observations` - Observation per ring buffer slot
+/// * `observation_count` - Number of observations ever written (mutable)
+/// * `fixed_rate` - Annualized fixed rate after the state change
+/// * `share_price` - Share price after the state change
+/// * `time` - Current time
+pub fn record_observation(
+    observations: &KeyValueStore<u64, RateObservation>,
+    observation_count: &mut u64,
+    fixed_rate: Decimal,
+    share_price: Decimal,
+    time: u64
+) {
+    if *observation_count == 0 {
+        observations.insert(0, RateObservation {
+            time,
+            fixed_rate,
+            share_price,
+            cumulative_rate: math::ZERO,
+            cumulative_share_price: math::ZERO,
+        });
+        *observation_count = 1;
+        return;
+    }
+
+    let last_slot = (*observation_count - 1) % OBSERVATION_CAPACITY;
+    let last = observations.get(&last_slot).unwrap().clone();
+    let observation = accumulate_observation(&last, fixed_rate, share_price, time);
+
+    if last.time == time {
+        observations.insert(last_slot, observation);
+    } else {
+        observations.insert(*observation_count % OBSERVATION_CAPACITY, observation);
+        *observation_count += 1;
+    }
+}
+
+/// Calculates the time-weighted average fixed rate and share price over a window
+///
+/// # Arguments
+/// * `observations` - Observation per ring buffer slot
+/// * `observation_count` - Number of observations ever written
+/// * `window_epochs` - Length of the window ending now, in epochs
+/// * `current_time` - Current epoch
+///
+/// # Returns
+/// * Tuple of (average fixed rate, average share price) over the window
+pub fn calculate_twaps(
+    observations: &KeyValueStore<u64, RateObservation>,
+    observation_count: u64,
+    window_epochs: u64,
+    current_time: u64
+) -> (Decimal, Decimal) {
+    assert!(window_epochs > 0, "TWAP window must be positive");
+    assert!(observation_count > 0, "No rate observations recorded");
+    assert!(window_epochs <= current_time, "TWAP window exceeds the observation history");
+
+    let stored = stored_observations(observation_count);
+    let latest = observation_at(observations, observation_count, stored - 1);
+    let (end_rate, end_share_price) = cumulatives_at(&latest, current_time);
+
+    let start_time = current_time - window_epochs;
+    let start = latest_observation_at_or_before(observations, observation_count, start_time)
+        .expect("TWAP window exceeds the observation history");
+    let (start_rate, start_share_price) = cumulatives_at(&start, start_time);
+
+    let window = Decimal::from(window_epochs);
+    ((end_rate - start_rate) / window, (end_share_price - start_share_price) / window)
+}
+
+/// Returns stored observations from `from_time` on, oldest first
+///
+/// # Arguments
+/// * `observations` - Observation per ring buffer slot
+/// * `observation_count` - Number of observations ever written
+/// * `from_time` - Earliest observation time to return
+/// * `limit` - Most observations to return, capped at MAX_OBSERVATION_PAGE
+///
+/// # Returns
+/// * Observations in time order
+pub fn observation_history(
+    observations: &KeyValueStore<u64, RateObservation>,
+    observation_count: u64,
+    from_time: u64,
+    limit: u64
+) -> Vec<RateObservation> {
+    let stored = stored_observations(observation_count);
+
+    // First logical index with a time at or after `from_time`
+    let (mut low, mut high) = (0, stored);
+    while low < high {
+        let mid = (low + high) / 2;
+        if observation_at(observations, observation_count, mid).time < from_time {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    let end = stored.min(low + limit.min(MAX_OBSERVATION_PAGE));
+    (low..end).map(|index| observation_at(observations, observation_count, index)).collect()
+}
+
+/// Number of observations currently held in the ring buffer
+fn stored_observations(observation_count: u64) -> u64 {
+    observation_count.min(OBSERVATION_CAPACITY)
+}
+
+/// Observation at a logical index, where 0 is the oldest one still stored
+fn observation_at(
+    observations: &KeyValueStore<u64, RateObservation>,
+    observation_count: u64,
+    index: u64
+) -> RateObservation {
+    let oldest_slot = if observation_count > OBSERVATION_CAPACITY {
+        observation_count % OBSERVATION_CAPACITY
+    } else {
+        0
+    };
+
+    observations.get(&((oldest_slot + index) % OBSERVATION_CAPACITY)).unwrap().clone()
+}
+
+/// Latest stored observation at or before `time`, if the buffer reaches back that far
+fn latest_observation_at_or_before(
+    observations: &KeyValueStore<u64, RateObservation>,
+    observation_count: u64,
+    time: u64
+) -> Option<RateObservation> {
+    let stored = stored_observations(observation_count);
+    if observation_at(observations, observation_count, 0).time > time {
+        return None;
+    }
+
+    let (mut low, mut high) = (0, stored - 1);
+    while low < high {
+        let mid = (low + high + 1) / 2;
+        if observation_at(observations, observation_count, mid).time <= time {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    Some(observation_at(observations, observation_count, low))
+}
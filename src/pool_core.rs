@@ -0,0 +1,1293 @@
+use scrypto::prelude::*;
+use crate::types::*;
+use crate::constants::*;
+use crate::helpers::*;
+use crate::curves::*;
+use crate::fees::*;
+
+// Everything in this module is pure pool math: time and share price are explicit
+// inputs and nothing touches buckets, vaults or the Scrypto runtime. The blueprint
+// wraps these functions with resource handling; the simulator drives them directly.
+
+/// Advances to the checkpoint containing `current_time`, minting it if needed
+/// 
+/// # Arguments
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `current_checkpoint` - Current checkpoint ID
+/// * `checkpoint_duration` - Duration of each checkpoint
+/// * `share_price` - Current share price
+/// * `current_time` - Current time
+/// 
+/// # Returns
+/// * New checkpoint ID if updated, otherwise current checkpoint ID
+pub fn advance_checkpoint(
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: u64,
+    checkpoint_duration: u64,
+    share_price: Decimal,
+    current_time: u64
+) -> u64 {
+    let calculated_checkpoint = calculate_current_checkpoint(current_time, checkpoint_duration);
+
+    if should_update_checkpoint(current_checkpoint, calculated_checkpoint) {
+        // Create new checkpoint
+        let new_checkpoint = Checkpoint {
+            start_time: calculated_checkpoint,
+            share_price,
+            long_positions: math::ZERO,
+            short_positions: math::ZERO,
+            avg_long_maturity: math::ZERO,
+            avg_short_maturity: math::ZERO,
+            is_minted: true,
+        };
+
+        checkpoints.insert(calculated_checkpoint, new_checkpoint);
+        calculated_checkpoint
+    } else {
+        current_checkpoint
+    }
+}
+
+/// Mints a maturity checkpoint that was skipped, at the current share price
+/// 
+/// A maturity that fell in a checkpoint nobody traded in has no recorded share
+/// price. Minting it on first settlement fixes the price every later settlement
+/// of the same maturity reads, instead of each one reading the price of its day.
+/// 
+/// # Arguments
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `maturity_time` - Time when the positions mature
+/// * `share_price` - Current share price
+/// 
+/// # Returns
+/// * Share price at maturity
+pub fn mint_maturity_checkpoint(
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    maturity_time: u64,
+    share_price: Decimal
+) -> Decimal {
+    checkpoints.entry(maturity_time)
+        .or_insert(Checkpoint {
+            start_time: maturity_time,
+            share_price,
+            long_positions: math::ZERO,
+            short_positions: math::ZERO,
+            avg_long_maturity: math::ZERO,
+            avg_short_maturity: math::ZERO,
+            is_minted: true,
+        })
+        .share_price
+}
+
+/// Share price a short's variable interest accrues to, which stops moving at maturity
+/// 
+/// A matured short reads the maturity checkpoint, minting it if it was skipped.
+/// 
+/// # Arguments
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `maturity_time` - Time when the position matures
+/// * `current_time` - Current time
+/// * `share_price` - Current share price
+/// 
+/// # Returns
+/// * Share price at maturity once the short has matured, otherwise the current share price
+pub fn short_accrual_share_price(
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    maturity_time: u64,
+    current_time: u64,
+    share_price: Decimal
+) -> Decimal {
+    if current_time >= maturity_time {
+        mint_maturity_checkpoint(checkpoints, maturity_time, share_price)
+    } else {
+        share_price
+    }
+}
+
+/// Mints the first checkpoint for a new pool
+/// 
+/// # Arguments
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `checkpoint_duration` - Duration of each checkpoint
+/// * `initial_share_price` - Initial share price
+/// * `current_time` - Current time
+/// 
+/// # Returns
+/// * Initial checkpoint ID
+pub fn create_first_checkpoint(
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    checkpoint_duration: u64,
+    initial_share_price: Decimal,
+    current_time: u64
+) -> u64 {
+    let current_checkpoint = calculate_current_checkpoint(current_time, checkpoint_duration);
+
+    let initial_checkpoint = Checkpoint {
+        start_time: current_checkpoint,
+        share_price: initial_share_price,
+        long_positions: math::ZERO,
+        short_positions: math::ZERO,
+        avg_long_maturity: math::ZERO,
+        avg_short_maturity: math::ZERO,
+        is_minted: true,
+    };
+
+    checkpoints.insert(current_checkpoint, initial_checkpoint);
+    current_checkpoint
+}
+
+/// Updates checkpoint data when a long position is opened
+/// 
+/// # Arguments
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `checkpoint_id` - Checkpoint ID to update
+/// * `face_value` - Face value of the long position
+/// * `maturity_time` - Maturity time of the position
+pub fn update_checkpoint_long_opened(
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    checkpoint_id: u64,
+    face_value: Decimal,
+    maturity_time: u64
+) {
+    if let Some(checkpoint) = checkpoints.get_mut(&checkpoint_id) {
+        let old_total = checkpoint.long_positions;
+        let new_total = old_total + face_value;
+
+        // Update weighted average maturity
+        if new_total > math::ZERO {
+            let old_weighted_maturity = checkpoint.avg_long_maturity * old_total;
+            let new_weighted_maturity = Decimal::from(maturity_time) * face_value;
+            checkpoint.avg_long_maturity = (old_weighted_maturity + new_weighted_maturity) / new_total;
+        }
+
+        checkpoint.long_positions = new_total;
+    }
+}
+
+/// Updates checkpoint data when a long position is closed
+/// 
+/// # Arguments
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `checkpoint_id` - Checkpoint ID to update
+/// * `face_value` - Face value of the long position being closed
+/// * `time_remaining` - Time remaining for the position
+pub fn update_checkpoint_long_closed(
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    checkpoint_id: u64,
+    face_value: Decimal,
+    time_remaining: Decimal
+) {
+    if let Some(checkpoint) = checkpoints.get_mut(&checkpoint_id) {
+        checkpoint.long_positions -= face_value;
+
+        // Update average maturity (simplified)
+        if checkpoint.long_positions > math::ZERO {
+            // This is a simplification - actual implementation would recalculate
+            // the weighted average maturity properly
+            checkpoint.avg_long_maturity = time_remaining;
+        } else {
+            checkpoint.avg_long_maturity = math::ZERO;
+        }
+    }
+}
+
+/// Updates checkpoint data when a short position is opened
+/// 
+/// # Arguments
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `checkpoint_id` - Checkpoint ID to update
+/// * `face_value` - Face value of the short position
+/// * `maturity_time` - Maturity time of the position
+pub fn update_checkpoint_short_opened(
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    checkpoint_id: u64,
+    face_value: Decimal,
+    maturity_time: u64
+) {
+    if let Some(checkpoint) = checkpoints.get_mut(&checkpoint_id) {
+        let old_total = checkpoint.short_positions;
+        let new_total = old_total + face_value;
+
+        // Update weighted average maturity
+        if new_total > math::ZERO {
+            let old_weighted_maturity = checkpoint.avg_short_maturity * old_total;
+            let new_weighted_maturity = Decimal::from(maturity_time) * face_value;
+            checkpoint.avg_short_maturity = (old_weighted_maturity + new_weighted_maturity) / new_total;
+        }
+
+        checkpoint.short_positions = new_total;
+    }
+}
+
+/// Updates checkpoint data when a short position is closed
+/// 
+/// # Arguments
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `checkpoint_id` - Checkpoint ID to update
+/// * `face_value` - Face value of the short position being closed
+/// * `time_remaining` - Time remaining for the position
+pub fn update_checkpoint_short_closed(
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    checkpoint_id: u64,
+    face_value: Decimal,
+    time_remaining: Decimal
+) {
+    if let Some(checkpoint) = checkpoints.get_mut(&checkpoint_id) {
+        checkpoint.short_positions -= face_value;
+
+        // Update average maturity (simplified)
+        if checkpoint.short_positions > math::ZERO {
+            // This is a simplification - actual implementation would recalculate
+            // the weighted average maturity properly
+            checkpoint.avg_short_maturity = time_remaining;
+        } else {
+            checkpoint.avg_short_maturity = math::ZERO;
+        }
+    }
+}
+
+/// Collects zombie interest and updates pool state
+/// 
+/// # Arguments
+/// * `zombie_share_reserves` - Current zombie share reserves
+/// * `zombie_base_reserves` - Current zombie base reserves
+/// * `share_price` - Current share price
+/// * `zombie_governance_fee` - Zombie governance fee percentage
+/// 
+/// # Returns
+/// * Tuple of (total_zombie_interest, governance_portion, lp_portion, new_zombie_share_reserves)
+#[allow(dead_code)]
+pub fn collect_zombie_interest(
+    zombie_share_reserves: Decimal,
+    zombie_base_reserves: Decimal,
+    share_price: Decimal,
+    zombie_governance_fee: Decimal
+) -> (Decimal, Decimal, Decimal, Decimal) {
+    // Calculate zombie interest
+    let zombie_interest = (share_price * zombie_share_reserves) - zombie_base_reserves;
+
+    if zombie_interest <= math::ZERO {
+        return (math::ZERO, math::ZERO, math::ZERO, zombie_share_reserves);
+    }
+
+    // Calculate governance and LP portions
+    let governance_portion = zombie_interest * zombie_governance_fee;
+    let lp_portion = zombie_interest - governance_portion;
+
+    // Update zombie share reserves
+    let new_zombie_share_reserves = zombie_base_reserves / share_price;
+
+    (zombie_interest, governance_portion, lp_portion, new_zombie_share_reserves)
+}
+
+/// Calculates solvency requirement for active checkpoints
+/// 
+/// # Arguments
+/// * `checkpoints` - Reference to the checkpoints HashMap
+/// * `current_checkpoint` - Current checkpoint ID
+/// * `checkpoint_duration` - Duration of each checkpoint
+/// * `position_duration` - Duration of positions
+/// 
+/// # Returns
+/// * Total solvency requirement
+pub fn calculate_solvency_requirement(
+    checkpoints: &HashMap<u64, Checkpoint>,
+    current_checkpoint: u64,
+    checkpoint_duration: u64,
+    position_duration: u64
+) -> Decimal {
+    let mut solvency_requirement = math::ZERO;
+
+    // Calculate checkpoints per term
+    let checkpoints_per_term = position_duration / checkpoint_duration;
+
+    // Calculate solvency requirement for each active checkpoint
+    for i in 0..checkpoints_per_term {
+        // Stop at the first checkpoint rather than counting it once per remaining step
+        if i * checkpoint_duration > current_checkpoint {
+            break;
+        }
+        let checkpoint_id = current_checkpoint - i * checkpoint_duration;
+
+        if let Some(checkpoint) = checkpoints.get(&checkpoint_id) {
+            // Calculate checkpoint solvency requirement
+            let checkpoint_requirement = (checkpoint.long_positions - checkpoint.short_positions)
+                .max(math::ZERO);
+
+            solvency_requirement += checkpoint_requirement;
+        }
+    }
+
+    solvency_requirement
+}
+
+/// Applies an open long to the pool's reserves and checkpoint
+/// 
+/// # Arguments
+/// * `base_amount` - Base tokens paid by the trader
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `zeta_adjustment` - Zeta adjustment
+/// * `share_price` - Current share price
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `current_checkpoint` - Checkpoint the position opens in
+/// * `position_duration` - Duration of positions
+/// * `fees` - Fee schedule charged on the trade
+/// 
+/// # Returns
+/// * LongOpened with the face value, maturity and fees
+pub fn apply_open_long(
+    base_amount: Decimal,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: u64,
+    position_duration: u64,
+    fees: &FeeSchedule
+) -> LongOpened {
+    let maturity_time = current_checkpoint + position_duration;
+    let time_remaining = math::ONE; // Full term for new positions
+
+    // Convert base tokens to shares
+    let share_amount = base_amount / share_price;
+
+    // Calculate effective share reserves
+    let effective_shares = calculate_effective_share_reserves(*share_reserves, zeta_adjustment);
+
+    // Calculate face value using trading curve
+    let face_value = calculate_long_face_value(share_amount, effective_shares, *bond_reserves);
+
+    // Calculate fees
+    let spot_price = calculate_spot_price(effective_shares, *bond_reserves, share_price);
+    let trade_fees = calculate_trade_fees(face_value, time_remaining, spot_price, fees);
+
+    let governance_fee_amount = trade_fees.governance();
+    let lp_fee = trade_fees.lp();
+
+    // Adjust face value for fees
+    let adjusted_face_value = face_value - lp_fee;
+
+    // Update pool state (the governance fee leaves the pool, so it never enters reserves)
+    *share_reserves += share_amount - (governance_fee_amount / share_price);
+    *bond_reserves -= adjusted_face_value;
+
+    // Update checkpoint data
+    update_checkpoint_long_opened(checkpoints, current_checkpoint, adjusted_face_value, maturity_time);
+
+    LongOpened {
+        face_value: adjusted_face_value,
+        maturity_time,
+        governance_fee: governance_fee_amount,
+        lp_fee,
+    }
+}
+
+/// Applies a long close to the pool's reserves and checkpoint
+/// 
+/// # Arguments
+/// * `face_value` - Face value being closed
+/// * `open_time` - Time when the position was opened
+/// * `maturity_time` - Time when the position matures
+/// * `position_checkpoint` - Checkpoint the position was opened in
+/// * `current_time` - Current time
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `zeta_adjustment` - Zeta adjustment (mutable)
+/// * `share_price` - Current share price
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `fees` - Fee schedule charged on the trade
+/// 
+/// # Returns
+/// * PositionClosed with the proceeds and fees
+pub fn apply_close_long(
+    face_value: Decimal,
+    open_time: u64,
+    maturity_time: u64,
+    position_checkpoint: u64,
+    current_time: u64,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule
+) -> PositionClosed {
+    // Calculate time remaining
+    let time_remaining = calculate_time_remaining(current_time, open_time, maturity_time);
+
+    let effective_shares = calculate_effective_share_reserves(*share_reserves, *zeta_adjustment);
+
+    // Calculate impact on reserves (the pool buys the bonds back)
+    let delta_z = position_sale_delta_z(
+        face_value,
+        effective_shares,
+        *bond_reserves,
+        time_remaining,
+        share_price
+    );
+
+    // Calculate fees
+    let spot_price = calculate_spot_price(effective_shares, *bond_reserves, share_price);
+    let trade_fees = calculate_trade_fees(face_value, time_remaining, spot_price, fees);
+
+    let total_fee = trade_fees.total();
+    let governance_fee_amount = trade_fees.governance();
+    let lp_fee = trade_fees.lp();
+
+    // Calculate base proceeds (the trader pays both the LP and governance fee)
+    let base_proceeds = delta_z * share_price - total_fee;
+
+    // Update pool state (the LP fee stays in the pool)
+    *share_reserves -= delta_z - (lp_fee / share_price);
+    *bond_reserves += face_value * time_remaining;
+
+    // Update zeta adjustment for matured portion
+    let matured_impact = maturity_pricing_delta_z(
+        face_value * (math::ONE - time_remaining),
+        share_price
+    );
+    *zeta_adjustment -= matured_impact - (trade_fees.lp_flat() / share_price);
+
+    // Update checkpoint data
+    update_checkpoint_long_closed(checkpoints, position_checkpoint, face_value, time_remaining);
+
+    PositionClosed {
+        time_remaining,
+        base_proceeds,
+        governance_fee: governance_fee_amount,
+        lp_fee,
+    }
+}
+
+/// Previews a long close without changing the pool's state
+/// 
+/// # Arguments
+/// * Same as `apply_close_long`, with the pool state taken by value
+/// 
+/// # Returns
+/// * PositionClosed with the proceeds and fees the close would pay now
+pub fn preview_close_long(
+    face_value: Decimal,
+    open_time: u64,
+    maturity_time: u64,
+    position_checkpoint: u64,
+    current_time: u64,
+    share_reserves: Decimal,
+    bond_reserves: Decimal,
+    zeta_adjustment: Decimal,
+    share_price: Decimal,
+    checkpoints: &HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule
+) -> PositionClosed {
+    let mut share_reserves = share_reserves;
+    let mut bond_reserves = bond_reserves;
+    let mut zeta_adjustment = zeta_adjustment;
+    let mut checkpoints = checkpoints.clone();
+
+    apply_close_long(
+        face_value,
+        open_time,
+        maturity_time,
+        position_checkpoint,
+        current_time,
+        &mut share_reserves,
+        &mut bond_reserves,
+        &mut zeta_adjustment,
+        share_price,
+        &mut checkpoints,
+        fees
+    )
+}
+
+/// Settles a matured long and reinvests the proceeds in a new long
+/// 
+/// The old position settles at face value like a matured close, without the
+/// flat fee since its proceeds never leave the pool. As in a close or a bond
+/// redemption, nothing accrues to the long after maturity.
+/// 
+/// # Arguments
+/// * `face_value` - Face value of the matured position
+/// * `open_time` - Time when the position was opened
+/// * `maturity_time` - Time when the position matures
+/// * `position_checkpoint` - Checkpoint the position was opened in
+/// * `current_time` - Current time
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `zeta_adjustment` - Zeta adjustment (mutable)
+/// * `share_price` - Current share price
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `current_checkpoint` - Checkpoint the new position opens in
+/// * `position_duration` - Duration of positions
+/// * `fees` - Fee schedule charged on the trade
+/// 
+/// # Returns
+/// * Tuple of (base amount rolled, LongOpened for the new position)
+pub fn apply_rollover_long(
+    face_value: Decimal,
+    open_time: u64,
+    maturity_time: u64,
+    position_checkpoint: u64,
+    current_time: u64,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: u64,
+    position_duration: u64,
+    fees: &FeeSchedule
+) -> (Decimal, LongOpened) {
+    validate_rollover_maturity(current_time, maturity_time);
+
+    // Settle as fully matured, fee free
+    let settled = apply_close_long(
+        face_value,
+        open_time,
+        maturity_time,
+        position_checkpoint,
+        current_time,
+        share_reserves,
+        bond_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        &FeeSchedule::default()
+    );
+
+    let base_rolled = settled.base_proceeds;
+
+    let opened = apply_open_long(
+        base_rolled,
+        share_reserves,
+        bond_reserves,
+        *zeta_adjustment,
+        share_price,
+        checkpoints,
+        current_checkpoint,
+        position_duration,
+        fees
+    );
+
+    (base_rolled, opened)
+}
+
+/// Applies an open short to the pool's reserves and checkpoint
+/// 
+/// # Arguments
+/// * `face_value` - Face value of the short position
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `zeta_adjustment` - Zeta adjustment
+/// * `share_price` - Current share price
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `current_checkpoint` - Checkpoint the position opens in
+/// * `position_duration` - Duration of positions
+/// * `fees` - Fee schedule charged on the trade
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// 
+/// # Returns
+/// * ShortOpened with the required deposit, maturity and fees
+pub fn apply_open_short(
+    face_value: Decimal,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: u64,
+    position_duration: u64,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal
+) -> ShortOpened {
+    let maturity_time = current_checkpoint + position_duration;
+    let time_remaining = math::ONE; // Full term for new positions
+
+    // Calculate effective share reserves
+    let effective_shares = calculate_effective_share_reserves(*share_reserves, zeta_adjustment);
+
+    // Calculate required deposit
+    let deposit_required = calculate_short_deposit(
+        face_value,
+        effective_shares,
+        *bond_reserves,
+        share_price,
+        time_remaining
+    );
+
+    // Calculate fees
+    let spot_price = calculate_spot_price(effective_shares, *bond_reserves, share_price);
+    let trade_fees = calculate_trade_fees(face_value, time_remaining, spot_price, fees);
+
+    let total_fee = trade_fees.total();
+    let governance_fee_amount = trade_fees.governance();
+    let lp_fee = trade_fees.lp();
+
+    // Calculate impact on reserves (the pool buys the shorted bonds)
+    let delta_z = position_sale_delta_z(
+        face_value,
+        effective_shares,
+        *bond_reserves,
+        time_remaining,
+        share_price
+    );
+
+    // Update pool state
+    *share_reserves -= delta_z - (lp_fee / share_price);
+    *bond_reserves += face_value;
+
+    // Book the collateral backing the short's face value, in shares
+    *short_collateral += face_value / share_price;
+
+    // Update checkpoint data
+    update_checkpoint_short_opened(checkpoints, current_checkpoint, face_value, maturity_time);
+
+    ShortOpened {
+        total_deposit: deposit_required + total_fee,
+        maturity_time,
+        governance_fee: governance_fee_amount,
+        lp_fee,
+    }
+}
+
+/// Applies a short close to the pool's reserves and checkpoint
+/// 
+/// # Arguments
+/// * `face_value` - Face value being closed
+/// * `open_time` - Time when the position was opened
+/// * `maturity_time` - Time when the position matures
+/// * `initial_share_price` - Share price when the position was opened
+/// * `position_checkpoint` - Checkpoint the position was opened in
+/// * `current_time` - Current time
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `zeta_adjustment` - Zeta adjustment (mutable)
+/// * `share_price` - Current share price
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `fees` - Fee schedule charged on the trade
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// 
+/// # Returns
+/// * PositionClosed with the proceeds and fees
+pub fn apply_close_short(
+    face_value: Decimal,
+    open_time: u64,
+    maturity_time: u64,
+    initial_share_price: Decimal,
+    position_checkpoint: u64,
+    current_time: u64,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal
+) -> PositionClosed {
+    // Calculate time remaining
+    let time_remaining = calculate_time_remaining(current_time, open_time, maturity_time);
+
+    let effective_shares = calculate_effective_share_reserves(*share_reserves, *zeta_adjustment);
+
+    // Calculate impact on reserves
+    let delta_z = position_impact_delta_z(
+        face_value,
+        effective_shares,
+        *bond_reserves,
+        time_remaining,
+        share_price
+    );
+
+    // Calculate fees
+    let spot_price = calculate_spot_price(effective_shares, *bond_reserves, share_price);
+    let trade_fees = calculate_trade_fees(face_value, time_remaining, spot_price, fees);
+
+    let total_fee = trade_fees.total();
+    let governance_fee_amount = trade_fees.governance();
+    let lp_fee = trade_fees.lp();
+
+    // Calculate base proceeds
+    // For shorts: proceeds = (face_value * accrual_share_price / initial_share_price) - cost - fees,
+    // where interest stops accruing at maturity
+    let accrual_share_price = short_accrual_share_price(checkpoints, maturity_time, current_time, share_price);
+    let share_price_ratio = accrual_share_price / initial_share_price;
+    let base_proceeds = (face_value * share_price_ratio) -
+                       (delta_z * share_price) -
+                       total_fee;
+
+    // Release the collateral shares booked at open; interest owed beyond their
+    // current value is paid out of share reserves (and interest they earned after
+    // maturity stays there)
+    let collateral_released = face_value / initial_share_price;
+    let interest_owed = face_value * share_price_ratio - collateral_released * share_price;
+    *short_collateral -= collateral_released;
+
+    // Update pool state (the LP fee stays in the pool)
+    *share_reserves += delta_z + (lp_fee - interest_owed) / share_price;
+    *bond_reserves -= face_value * time_remaining;
+
+    // Update zeta adjustment for matured portion
+    let matured_impact = maturity_pricing_delta_z(
+        face_value * (math::ONE - time_remaining),
+        share_price
+    );
+    *zeta_adjustment += matured_impact + (trade_fees.lp_flat() / share_price);
+
+    // Update checkpoint data
+    update_checkpoint_short_closed(checkpoints, position_checkpoint, face_value, time_remaining);
+
+    PositionClosed {
+        time_remaining,
+        base_proceeds,
+        governance_fee: governance_fee_amount,
+        lp_fee,
+    }
+}
+
+/// Previews a short close without changing the pool's state
+/// 
+/// # Arguments
+/// * Same as `apply_close_short`, with the pool state taken by value
+/// 
+/// # Returns
+/// * PositionClosed with the proceeds and fees the close would pay now
+pub fn preview_close_short(
+    face_value: Decimal,
+    open_time: u64,
+    maturity_time: u64,
+    initial_share_price: Decimal,
+    position_checkpoint: u64,
+    current_time: u64,
+    share_reserves: Decimal,
+    bond_reserves: Decimal,
+    zeta_adjustment: Decimal,
+    share_price: Decimal,
+    checkpoints: &HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule,
+    short_collateral: Decimal
+) -> PositionClosed {
+    let mut share_reserves = share_reserves;
+    let mut bond_reserves = bond_reserves;
+    let mut zeta_adjustment = zeta_adjustment;
+    let mut checkpoints = checkpoints.clone();
+    let mut short_collateral = short_collateral;
+
+    apply_close_short(
+        face_value,
+        open_time,
+        maturity_time,
+        initial_share_price,
+        position_checkpoint,
+        current_time,
+        &mut share_reserves,
+        &mut bond_reserves,
+        &mut zeta_adjustment,
+        share_price,
+        &mut checkpoints,
+        fees,
+        &mut short_collateral
+    )
+}
+
+/// Totals of a batch of lots sharing one checkpoint
+/// 
+/// # Returns
+/// * Tuple of (time remaining per lot, total face value, face value still on the curve,
+///   face value already matured)
+fn lot_totals(lots: &[CloseLot], current_time: u64) -> (Vec<Decimal>, Decimal, Decimal, Decimal) {
+    let mut times_remaining = Vec::with_capacity(lots.len());
+    let mut total_face = math::ZERO;
+    let mut curve_bonds = math::ZERO;
+    let mut matured_bonds = math::ZERO;
+
+    for lot in lots {
+        let time_remaining = calculate_time_remaining(current_time, lot.open_time, lot.maturity_time);
+        total_face += lot.face_value;
+        curve_bonds += lot.face_value * time_remaining;
+        matured_bonds += lot.face_value * (math::ONE - time_remaining);
+        times_remaining.push(time_remaining);
+    }
+
+    (times_remaining, total_face, curve_bonds, matured_bonds)
+}
+
+/// Applies a batch of long closes from one checkpoint as a single curve trade
+/// 
+/// The bonds still on the curve are sold to the pool in one trade and the curve
+/// proceeds are shared pro rata by each lot's unmatured face value. Matured bonds
+/// and fees are priced per lot exactly as in `apply_close_long`.
+/// 
+/// # Arguments
+/// * `lots` - Positions being closed
+/// * `position_checkpoint` - Checkpoint all lots were opened in
+/// * `current_time` - Current time
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `zeta_adjustment` - Zeta adjustment (mutable)
+/// * `share_price` - Current share price
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `fees` - Fee schedule charged on the trade
+/// 
+/// # Returns
+/// * PositionClosed for each lot, in input order
+pub fn apply_close_long_lots(
+    lots: &[CloseLot],
+    position_checkpoint: u64,
+    current_time: u64,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule
+) -> Vec<PositionClosed> {
+    let (times_remaining, total_face, curve_bonds, matured_bonds) = lot_totals(lots, current_time);
+
+    let effective_shares = calculate_effective_share_reserves(*share_reserves, *zeta_adjustment);
+    let spot_price = calculate_spot_price(effective_shares, *bond_reserves, share_price);
+
+    // One curve trade for every unmatured bond in the batch
+    let curve_delta_z = if curve_bonds > math::ZERO {
+        trading_invariant_sell_delta_z(curve_bonds, effective_shares, *bond_reserves)
+    } else {
+        math::ZERO
+    };
+
+    let mut results = Vec::with_capacity(lots.len());
+    let mut total_lp_fee = math::ZERO;
+    let mut total_lp_flat_fee = math::ZERO;
+
+    for (lot, time_remaining) in lots.iter().zip(times_remaining) {
+        let trade_fees = calculate_trade_fees(lot.face_value, time_remaining, spot_price, fees);
+
+        let total_fee = trade_fees.total();
+        let governance_fee_amount = trade_fees.governance();
+        let lp_fee = trade_fees.lp();
+
+        // Curve proceeds are shared by unmatured face value; matured bonds pay face value
+        let lot_curve_bonds = lot.face_value * time_remaining;
+        let curve_proceeds = if curve_bonds > math::ZERO {
+            curve_delta_z * share_price * lot_curve_bonds / curve_bonds
+        } else {
+            math::ZERO
+        };
+        let base_proceeds = curve_proceeds + lot.face_value - lot_curve_bonds - total_fee;
+
+        total_lp_fee += lp_fee;
+        total_lp_flat_fee += trade_fees.lp_flat();
+        results.push(PositionClosed {
+            time_remaining,
+            base_proceeds,
+            governance_fee: governance_fee_amount,
+            lp_fee,
+        });
+    }
+
+    // Update pool state once for the whole batch (the LP fees stay in the pool)
+    let matured_impact = maturity_pricing_delta_z(matured_bonds, share_price);
+    *share_reserves -= curve_delta_z + matured_impact - (total_lp_fee / share_price);
+    *bond_reserves += curve_bonds;
+    *zeta_adjustment -= matured_impact - (total_lp_flat_fee / share_price);
+
+    // Update checkpoint data with the face-weighted time remaining
+    let average_time_remaining = if total_face > math::ZERO { curve_bonds / total_face } else { math::ZERO };
+    update_checkpoint_long_closed(checkpoints, position_checkpoint, total_face, average_time_remaining);
+
+    results
+}
+
+/// Applies a batch of short closes from one checkpoint as a single curve trade
+/// 
+/// The bonds still on the curve are bought back from the pool in one trade and the
+/// cost is shared pro rata by each lot's unmatured face value. Variable interest,
+/// collateral and fees are settled per lot exactly as in `apply_close_short`.
+/// 
+/// # Arguments
+/// * `lots` - Positions being closed
+/// * `position_checkpoint` - Checkpoint all lots were opened in
+/// * `current_time` - Current time
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `zeta_adjustment` - Zeta adjustment (mutable)
+/// * `share_price` - Current share price
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `fees` - Fee schedule charged on the trade
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// 
+/// # Returns
+/// * PositionClosed for each lot, in input order
+pub fn apply_close_short_lots(
+    lots: &[CloseLot],
+    position_checkpoint: u64,
+    current_time: u64,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal
+) -> Vec<PositionClosed> {
+    let (times_remaining, total_face, curve_bonds, matured_bonds) = lot_totals(lots, current_time);
+
+    let effective_shares = calculate_effective_share_reserves(*share_reserves, *zeta_adjustment);
+    let spot_price = calculate_spot_price(effective_shares, *bond_reserves, share_price);
+
+    // One curve trade for every unmatured bond in the batch
+    let curve_delta_z = if curve_bonds > math::ZERO {
+        trading_invariant_delta_z(curve_bonds, effective_shares, *bond_reserves)
+    } else {
+        math::ZERO
+    };
+
+    let mut results = Vec::with_capacity(lots.len());
+    let mut total_lp_fee = math::ZERO;
+    let mut total_lp_flat_fee = math::ZERO;
+    let mut total_interest_owed = math::ZERO;
+
+    for (lot, time_remaining) in lots.iter().zip(times_remaining) {
+        let trade_fees = calculate_trade_fees(lot.face_value, time_remaining, spot_price, fees);
+
+        let total_fee = trade_fees.total();
+        let governance_fee_amount = trade_fees.governance();
+        let lp_fee = trade_fees.lp();
+
+        // Buy-back cost is shared by unmatured face value; matured bonds cost face value
+        let lot_curve_bonds = lot.face_value * time_remaining;
+        let curve_cost = if curve_bonds > math::ZERO {
+            curve_delta_z * share_price * lot_curve_bonds / curve_bonds
+        } else {
+            math::ZERO
+        };
+        let accrual_share_price = short_accrual_share_price(checkpoints, lot.maturity_time, current_time, share_price);
+        let share_price_ratio = accrual_share_price / lot.initial_share_price;
+        let base_proceeds = (lot.face_value * share_price_ratio) -
+                           (curve_cost + lot.face_value - lot_curve_bonds) -
+                           total_fee;
+
+        // Release the collateral shares booked at open
+        let collateral_released = lot.face_value / lot.initial_share_price;
+        total_interest_owed += lot.face_value * share_price_ratio - collateral_released * share_price;
+        *short_collateral -= collateral_released;
+
+        total_lp_fee += lp_fee;
+        total_lp_flat_fee += trade_fees.lp_flat();
+        results.push(PositionClosed {
+            time_remaining,
+            base_proceeds,
+            governance_fee: governance_fee_amount,
+            lp_fee,
+        });
+    }
+
+    // Update pool state once for the whole batch (the LP fees stay in the pool)
+    let matured_impact = maturity_pricing_delta_z(matured_bonds, share_price);
+    *share_reserves += curve_delta_z + matured_impact + (total_lp_fee - total_interest_owed) / share_price;
+    *bond_reserves -= curve_bonds;
+    *zeta_adjustment += matured_impact + (total_lp_flat_fee / share_price);
+
+    // Update checkpoint data with the face-weighted time remaining
+    let average_time_remaining = if total_face > math::ZERO { curve_bonds / total_face } else { math::ZERO };
+    update_checkpoint_short_closed(checkpoints, position_checkpoint, total_face, average_time_remaining);
+
+    results
+}
+
+/// Rebooks a short's collateral at its checkpoint's share price
+/// 
+/// Yield tokens accrue from the checkpoint share price rather than the price the
+/// short opened at, so the difference moves between the collateral and the share
+/// reserves. The vault balance the pool expects is unchanged.
+/// 
+/// # Arguments
+/// * `face_value` - Face value of the short
+/// * `initial_share_price` - Share price the short opened at
+/// * `checkpoint_share_price` - Share price of the short's checkpoint
+/// * `share_reserves` - Share reserves (mutable)
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+pub fn rebook_short_collateral(
+    face_value: Decimal,
+    initial_share_price: Decimal,
+    checkpoint_share_price: Decimal,
+    share_reserves: &mut Decimal,
+    short_collateral: &mut Decimal
+) {
+    let collateral_change = face_value / checkpoint_share_price - face_value / initial_share_price;
+    *short_collateral += collateral_change;
+    *share_reserves -= collateral_change;
+}
+
+/// Applies a redemption of matured yield tokens to the pool
+/// 
+/// Each token pays the variable interest on one unit of face value between its
+/// checkpoint and maturity, less the flat fee (capped at the interest).
+/// 
+/// # Arguments
+/// * `amount` - Yield tokens redeemed
+/// * `checkpoint_share_price` - Share price of the tokens' checkpoint
+/// * `maturity_share_price` - Share price at maturity
+/// * `position_checkpoint` - Checkpoint the tokens belong to
+/// * `share_reserves` - Share reserves (mutable)
+/// * `zeta_adjustment` - Zeta adjustment (mutable)
+/// * `share_price` - Current share price
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `fees` - Fee schedule charged on the trade
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// 
+/// # Returns
+/// * PositionClosed with the proceeds and fees
+pub fn apply_redeem_yield(
+    amount: Decimal,
+    checkpoint_share_price: Decimal,
+    maturity_share_price: Decimal,
+    position_checkpoint: u64,
+    share_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal
+) -> PositionClosed {
+    // Variable interest earned between the checkpoint and maturity
+    let interest = (amount * (maturity_share_price / checkpoint_share_price - math::ONE)).max(math::ZERO);
+
+    let total_fee = calculate_flat_fee(amount, math::ZERO, fees.flat_fee).min(interest);
+    let governance_fee_amount = total_fee * fees.governance_flat_fee;
+    let lp_fee = total_fee - governance_fee_amount;
+    let base_proceeds = interest - total_fee;
+
+    // The collateral shares backing the matured bonds return to the share reserves
+    let collateral_released = amount / checkpoint_share_price;
+    *short_collateral -= collateral_released;
+    *share_reserves += collateral_released - (base_proceeds + governance_fee_amount) / share_price;
+
+    // Matured bonds leave the pool as in a matured short close
+    *zeta_adjustment += maturity_pricing_delta_z(amount, share_price) + lp_fee / share_price;
+
+    update_checkpoint_short_closed(checkpoints, position_checkpoint, amount, math::ZERO);
+
+    PositionClosed {
+        time_remaining: math::ZERO,
+        base_proceeds,
+        governance_fee: governance_fee_amount,
+        lp_fee,
+    }
+}
+
+/// Settles a matured short and opens a new short in the current checkpoint
+/// 
+/// The old position's variable interest is settled from its initial share price
+/// to the maturity checkpoint price without the flat fee, since the
+/// interest is reused as part of the new position's deposit. A skipped maturity
+/// checkpoint is minted at the current share price first.
+/// 
+/// # Arguments
+/// * `face_value` - Face value of the matured position
+/// * `initial_share_price` - Share price when the matured position was opened
+/// * `position_checkpoint` - Checkpoint the matured position was opened in
+/// * `maturity_time` - Time when the matured position matures
+/// * `current_time` - Current time
+/// * `new_face_value` - Face value of the new short
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `zeta_adjustment` - Zeta adjustment (mutable)
+/// * `share_price` - Current share price
+/// * `checkpoints` - Mutable reference to the checkpoints HashMap
+/// * `current_checkpoint` - Checkpoint the new position opens in
+/// * `position_duration` - Duration of positions
+/// * `fees` - Fee schedule charged on the trade
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// 
+/// # Returns
+/// * Tuple of (interest settled in base, ShortOpened for the new position)
+pub fn apply_rollover_short(
+    face_value: Decimal,
+    initial_share_price: Decimal,
+    position_checkpoint: u64,
+    maturity_time: u64,
+    current_time: u64,
+    new_face_value: Decimal,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: u64,
+    position_duration: u64,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal
+) -> (Decimal, ShortOpened) {
+    validate_rollover_maturity(current_time, maturity_time);
+    let maturity_share_price = mint_maturity_checkpoint(checkpoints, maturity_time, share_price);
+
+    // A matured short settles like yield tokens accruing from the open share price
+    let settled = apply_redeem_yield(
+        face_value,
+        initial_share_price,
+        maturity_share_price,
+        position_checkpoint,
+        share_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        &FeeSchedule::default(),
+        short_collateral
+    );
+
+    let opened = apply_open_short(
+        new_face_value,
+        share_reserves,
+        bond_reserves,
+        *zeta_adjustment,
+        share_price,
+        checkpoints,
+        current_checkpoint,
+        position_duration,
+        fees,
+        short_collateral
+    );
+
+    (settled.base_proceeds, opened)
+}
+
+/// Scales the virtual bond reserves with a change in share reserves
+/// 
+/// The bonds owed to and by traders stay in `bond_reserves`; only the virtual
+/// part grows or shrinks, so liquidity moves keep the curve close to its price.
+/// 
+/// # Arguments
+/// * `share_change` - Shares added (positive) or removed (negative)
+/// * `share_reserves` - Share reserves before the change
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `virtual_bond_reserves` - Virtual bond reserves (mutable)
+pub fn scale_virtual_bond_reserves(
+    share_change: Decimal,
+    share_reserves: Decimal,
+    bond_reserves: &mut Decimal,
+    virtual_bond_reserves: &mut Decimal
+) {
+    if share_reserves <= math::ZERO {
+        return;
+    }
+
+    let virtual_change = *virtual_bond_reserves * share_change / share_reserves;
+    *virtual_bond_reserves += virtual_change;
+    *bond_reserves += virtual_change;
+}
+
+/// Applies a liquidity deposit to the pool's reserves
+/// 
+/// # Arguments
+/// * `base_amount` - Base tokens deposited
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `virtual_bond_reserves` - Virtual bond reserves (mutable)
+/// * `share_price` - Current share price
+/// * `active_lp_shares` - Total active LP shares
+/// 
+/// # Returns
+/// * LP tokens to mint
+pub fn apply_add_liquidity(
+    base_amount: Decimal,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    virtual_bond_reserves: &mut Decimal,
+    share_price: Decimal,
+    active_lp_shares: Decimal
+) -> Decimal {
+    // Convert base tokens to shares
+    let share_amount = base_amount / share_price;
+
+    // Calculate LP tokens to mint
+    let lp_tokens_to_mint = if active_lp_shares <= math::ZERO {
+        // First liquidity provision - mint 1:1 with shares
+        share_amount
+    } else {
+        // Calculate based on current pool value
+        let lp_present_value = calculate_lp_present_value(
+            *share_reserves,
+            *bond_reserves,
+            *virtual_bond_reserves,
+            share_price,
+            active_lp_shares
+        );
+
+        // LP tokens = (share_amount * share_price) / lp_present_value
+        (share_amount * share_price) / lp_present_value
+    };
+
+    // Update pool state, deepening the seeded curve with the new shares
+    scale_virtual_bond_reserves(share_amount, *share_reserves, bond_reserves, virtual_bond_reserves);
+    *share_reserves += share_amount;
+
+    lp_tokens_to_mint
+}
+
+/// Applies a liquidity withdrawal to the pool's reserves
+/// 
+/// # Arguments
+/// * `lp_amount` - LP tokens being redeemed
+/// * `share_reserves` - Share reserves (mutable)
+/// * `bond_reserves` - Bond reserves (mutable)
+/// * `virtual_bond_reserves` - Virtual bond reserves (mutable)
+/// * `share_price` - Current share price
+/// * `active_lp_shares` - Total active LP shares
+/// * `min_share_reserves` - Minimum share reserves
+/// * `checkpoints` - Reference to checkpoints HashMap
+/// * `current_checkpoint` - Current checkpoint ID
+/// * `checkpoint_duration` - Duration of each checkpoint
+/// * `position_duration` - Duration of positions
+/// 
+/// # Returns
+/// * Tuple of (immediate base withdrawal, withdrawal shares owed)
+pub fn apply_remove_liquidity(
+    lp_amount: Decimal,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    virtual_bond_reserves: &mut Decimal,
+    share_price: Decimal,
+    active_lp_shares: Decimal,
+    min_share_reserves: Decimal,
+    checkpoints: &HashMap<u64, Checkpoint>,
+    current_checkpoint: u64,
+    checkpoint_duration: u64,
+    position_duration: u64
+) -> (Decimal, Decimal) {
+    // Calculate LP present value
+    let lp_present_value = calculate_lp_present_value(
+        *share_reserves,
+        *bond_reserves,
+        *virtual_bond_reserves,
+        share_price,
+        active_lp_shares
+    );
+
+    // Calculate total value to withdraw
+    let total_value_to_withdraw = lp_amount * lp_present_value;
+
+    // Calculate solvency requirement
+    let solvency_requirement = calculate_solvency_requirement(
+        checkpoints,
+        current_checkpoint,
+        checkpoint_duration,
+        position_duration
+    );
+
+    // Calculate available liquidity for immediate withdrawal
+    let available_share_value = (*share_reserves - min_share_reserves - (solvency_requirement / share_price))
+        .max(math::ZERO) * share_price;
+
+    // Determine immediate withdrawal amount
+    let immediate_withdrawal = total_value_to_withdraw.min(available_share_value);
+    let withdrawal_shares_amount = total_value_to_withdraw - immediate_withdrawal;
+
+    // Update pool state, thinning the seeded curve with the withdrawn shares
+    if immediate_withdrawal > math::ZERO {
+        let share_amount = immediate_withdrawal / share_price;
+        scale_virtual_bond_reserves(-share_amount, *share_reserves, bond_reserves, virtual_bond_reserves);
+        *share_reserves -= share_amount;
+    }
+
+    (immediate_withdrawal, withdrawal_shares_amount)
+}
@@ -0,0 +1,346 @@
+use scrypto::prelude::*;
+use crate::types::*;
+use crate::constants::*;
+use crate::helpers::*;
+
+/// Merges long positions from one checkpoint into a single NFT
+///
+/// # Arguments
+/// * `position_nfts` - Long position NFTs to merge
+/// * `long_positions_resource` - Resource address for long position NFTs
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+///
+/// # Returns
+/// * Bucket holding the merged long position NFT
+pub fn merge_long_positions(
+    position_nfts: Bucket,
+    long_positions_resource: ResourceAddress,
+    image_base_url: &str,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>
+) -> Bucket {
+    validate_nft_batch(&position_nfts, long_positions_resource, "long position");
+
+    let positions: Vec<LongPosition> = position_nfts.as_non_fungible()
+        .non_fungibles::<LongPosition>()
+        .into_iter()
+        .map(|nft| nft.data())
+        .collect();
+    let checkpoint = positions[0].checkpoint;
+
+    let mut face_value = math::ZERO;
+    let mut weighted_open_time = math::ZERO;
+    let mut cost = positions[0].cost().scaled(math::ZERO);
+    for position in &positions {
+        validate_same_checkpoint(position.checkpoint, checkpoint);
+        face_value += position.face_value;
+        weighted_open_time += Decimal::from(position.open_time) * position.face_value;
+        cost = cost.plus(&position.cost());
+    }
+
+    // The merged position keeps the checkpoint's exposure unchanged
+    let merged = LongPosition {
+        name: position_name("Long", face_value, positions[0].maturity_time),
+        key_image_url: position_image_url(image_base_url, "Long", face_value, positions[0].maturity_time),
+        face_value,
+        checkpoint,
+        open_time: weighted_average_time(weighted_open_time, face_value),
+        maturity_time: positions[0].maturity_time,
+        base_paid: cost.base_paid,
+        shares_paid: cost.shares_paid,
+        lp_fee_paid: cost.lp_fee_paid,
+        governance_fee_paid: cost.governance_fee_paid,
+        fixed_rate_locked: long_fixed_rate(face_value, cost.base_paid),
+        pool_id: positions[0].pool_id,
+    };
+
+    burn_positions(position_nfts, position_summaries);
+    mint_long_position(merged, long_positions_resource, position_counter, position_summaries)
+}
+
+/// Merges short positions from one checkpoint into a single NFT
+///
+/// The initial share price is face-weighted so the collateral booked for the
+/// merged position equals the sum booked for its parts.
+///
+/// # Arguments
+/// * `position_nfts` - Short position NFTs to merge
+/// * `short_positions_resource` - Resource address for short position NFTs
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+///
+/// # Returns
+/// * Bucket holding the merged short position NFT
+pub fn merge_short_positions(
+    position_nfts: Bucket,
+    short_positions_resource: ResourceAddress,
+    image_base_url: &str,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>
+) -> Bucket {
+    validate_nft_batch(&position_nfts, short_positions_resource, "short position");
+
+    let positions: Vec<ShortPosition> = position_nfts.as_non_fungible()
+        .non_fungibles::<ShortPosition>()
+        .into_iter()
+        .map(|nft| nft.data())
+        .collect();
+    let checkpoint = positions[0].checkpoint;
+
+    let mut face_value = math::ZERO;
+    let mut weighted_open_time = math::ZERO;
+    let mut collateral_shares = math::ZERO;
+    let mut cost = positions[0].cost().scaled(math::ZERO);
+    for position in &positions {
+        validate_same_checkpoint(position.checkpoint, checkpoint);
+        face_value += position.face_value;
+        weighted_open_time += Decimal::from(position.open_time) * position.face_value;
+        collateral_shares += position.face_value / position.initial_share_price;
+        cost = cost.plus(&position.cost());
+    }
+
+    let merged = ShortPosition {
+        name: position_name("Short", face_value, positions[0].maturity_time),
+        key_image_url: position_image_url(image_base_url, "Short", face_value, positions[0].maturity_time),
+        face_value,
+        checkpoint,
+        open_time: weighted_average_time(weighted_open_time, face_value),
+        maturity_time: positions[0].maturity_time,
+        // Keeps the collateral shares, and so the interest owed, of the merged lots
+        initial_share_price: face_value / collateral_shares,
+        base_paid: cost.base_paid,
+        shares_paid: cost.shares_paid,
+        lp_fee_paid: cost.lp_fee_paid,
+        governance_fee_paid: cost.governance_fee_paid,
+        fixed_rate_locked: short_fixed_rate(face_value, cost.base_paid),
+        pool_id: positions[0].pool_id,
+    };
+
+    burn_positions(position_nfts, position_summaries);
+    mint_short_position(merged, short_positions_resource, position_counter, position_summaries)
+}
+
+/// Splits a long position into several NFTs with the given face values
+///
+/// # Arguments
+/// * `position_nft` - Long position NFT to split
+/// * `amounts` - Face value of each new position
+/// * `long_positions_resource` - Resource address for long position NFTs
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+///
+/// # Returns
+/// * Bucket holding one long position NFT per amount
+pub fn split_long_position(
+    position_nft: Bucket,
+    amounts: Vec<Decimal>,
+    long_positions_resource: ResourceAddress,
+    image_base_url: &str,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>
+) -> Bucket {
+    validate_single_nft(&position_nft, long_positions_resource, "long position");
+
+    let position: LongPosition = position_nft.as_non_fungible().non_fungible().data();
+    validate_split_amounts(&amounts, position.face_value);
+
+    burn_positions(position_nft, position_summaries);
+    let mut split = Bucket::new(long_positions_resource);
+    for face_value in amounts {
+        // Each part carries its pro-rata share of the cost basis at the same locked rate
+        let cost = position.cost().scaled(face_value / position.face_value);
+        split.put(mint_long_position(LongPosition {
+            name: position_name("Long", face_value, position.maturity_time),
+            key_image_url: position_image_url(image_base_url, "Long", face_value, position.maturity_time),
+            face_value,
+            checkpoint: position.checkpoint,
+            open_time: position.open_time,
+            maturity_time: position.maturity_time,
+            base_paid: cost.base_paid,
+            shares_paid: cost.shares_paid,
+            lp_fee_paid: cost.lp_fee_paid,
+            governance_fee_paid: cost.governance_fee_paid,
+            fixed_rate_locked: position.fixed_rate_locked,
+            pool_id: position.pool_id,
+        }, long_positions_resource, position_counter, position_summaries));
+    }
+    split
+}
+
+/// Splits a short position into several NFTs with the given face values
+///
+/// # Arguments
+/// * `position_nft` - Short position NFT to split
+/// * `amounts` - Face value of each new position
+/// * `short_positions_resource` - Resource address for short position NFTs
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+///
+/// # Returns
+/// * Bucket holding one short position NFT per amount
+pub fn split_short_position(
+    position_nft: Bucket,
+    amounts: Vec<Decimal>,
+    short_positions_resource: ResourceAddress,
+    image_base_url: &str,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>
+) -> Bucket {
+    validate_single_nft(&position_nft, short_positions_resource, "short position");
+
+    let position: ShortPosition = position_nft.as_non_fungible().non_fungible().data();
+    validate_split_amounts(&amounts, position.face_value);
+
+    burn_positions(position_nft, position_summaries);
+    let mut split = Bucket::new(short_positions_resource);
+    for face_value in amounts {
+        // Each part carries its pro-rata share of the cost basis at the same locked rate
+        let cost = position.cost().scaled(face_value / position.face_value);
+        split.put(mint_short_position(ShortPosition {
+            name: position_name("Short", face_value, position.maturity_time),
+            key_image_url: position_image_url(image_base_url, "Short", face_value, position.maturity_time),
+            face_value,
+            checkpoint: position.checkpoint,
+            open_time: position.open_time,
+            maturity_time: position.maturity_time,
+            initial_share_price: position.initial_share_price,
+            base_paid: cost.base_paid,
+            shares_paid: cost.shares_paid,
+            lp_fee_paid: cost.lp_fee_paid,
+            governance_fee_paid: cost.governance_fee_paid,
+            fixed_rate_locked: position.fixed_rate_locked,
+            pool_id: position.pool_id,
+        }, short_positions_resource, position_counter, position_summaries));
+    }
+    split
+}
+
+/// Mints a long position NFT under the next sequential ID and records its summary
+///
+/// # Arguments
+/// * `position_data` - Long position NFT data
+/// * `long_positions_resource` - Resource address for long position NFTs
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+///
+/// # Returns
+/// * Bucket holding the new long position NFT
+pub fn mint_long_position(
+    position_data: LongPosition,
+    long_positions_resource: ResourceAddress,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>
+) -> Bucket {
+    let summary = position_data.summary();
+    mint_position(position_data, summary, long_positions_resource, position_counter, position_summaries)
+}
+
+/// Mints a short position NFT under the next sequential ID and records its summary
+///
+/// # Arguments
+/// * `position_data` - Short position NFT data
+/// * `short_positions_resource` - Resource address for short position NFTs
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+///
+/// # Returns
+/// * Bucket holding the new short position NFT
+pub fn mint_short_position(
+    position_data: ShortPosition,
+    short_positions_resource: ResourceAddress,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>
+) -> Bucket {
+    let summary = position_data.summary();
+    mint_position(position_data, summary, short_positions_resource, position_counter, position_summaries)
+}
+
+/// Burns position NFTs, stamping the burn time on their summaries
+///
+/// # Arguments
+/// * `position_nfts` - Position NFTs to burn
+/// * `position_summaries` - Summary per position NFT
+pub fn burn_positions(
+    position_nfts: Bucket,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>
+) {
+    let burned_time = Runtime::current_epoch().number();
+    for local_id in position_nfts.as_non_fungible().non_fungible_local_ids() {
+        if let Some(mut summary) = position_summaries.get_mut(&local_id) {
+            summary.burned_time = Some(burned_time);
+        }
+    }
+    position_nfts.burn();
+}
+
+/// Sums the face value of the long position NFTs in a bucket
+///
+/// # Arguments
+/// * `position_nfts` - Long position NFTs
+///
+/// # Returns
+/// * Total face value
+pub fn total_long_face_value(position_nfts: &Bucket) -> Decimal {
+    position_nfts.as_non_fungible()
+        .non_fungibles::<LongPosition>()
+        .into_iter()
+        .fold(math::ZERO, |total, nft| total + nft.data().face_value)
+}
+
+/// Sums the face value of the short position NFTs in a bucket
+///
+/// # Arguments
+/// * `position_nfts` - Short position NFTs
+///
+/// # Returns
+/// * Total face value
+pub fn total_short_face_value(position_nfts: &Bucket) -> Decimal {
+    position_nfts.as_non_fungible()
+        .non_fungibles::<ShortPosition>()
+        .into_iter()
+        .fold(math::ZERO, |total, nft| total + nft.data().face_value)
+}
+
+/// Face-weighted average of position open times, rounded down
+fn weighted_average_time(weighted_time: Decimal, face_value: Decimal) -> u64 {
+    // Decimal has no direct u64 conversion; a floored value prints as an integer
+    (weighted_time / face_value)
+        .checked_floor()
+        .and_then(|time| time.to_string().parse().ok())
+        .expect("Invalid average open time")
+}
+
+fn validate_same_checkpoint(checkpoint: u64, expected: u64) {
+    assert!(checkpoint == expected, "Positions must share a checkpoint to be merged");
+}
+
+fn validate_split_amounts(amounts: &[Decimal], face_value: Decimal) {
+    assert!(amounts.len() >= 2, "Split needs at least two amounts");
+
+    let mut total = math::ZERO;
+    for amount in amounts {
+        assert!(*amount > math::ZERO, "Split amounts must be positive");
+        total += *amount;
+    }
+    assert!(total == face_value, "Split amounts must sum to the position face value");
+}
+
+/// Longs and shorts share one counter, so IDs follow open order across both sides
+fn mint_position<T: NonFungibleData>(
+    position_data: T,
+    summary: PositionSummary,
+    positions_resource: ResourceAddress,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>
+) -> Bucket {
+    *position_counter += 1;
+    let local_id = NonFungibleLocalId::integer(*position_counter);
+    position_summaries.insert(local_id.clone(), summary);
+    NonFungibleResourceManager::from(positions_resource)
+        .mint_non_fungible(&local_id, position_data).into()
+}
@@ -0,0 +1,376 @@
+use scrypto::prelude::*;
+use crate::types::*;
+use crate::constants::*;
+use crate::helpers::*;
+use crate::events::*;
+use crate::pool_core::*;
+use crate::positions::*;
+
+/// Creates a fungible token scoped to one maturity, mintable and burnable by the component
+///
+/// # Arguments
+/// * `name` - Token name
+/// * `symbol` - Token symbol
+/// * `maturity_time` - Time when the tokens become redeemable
+///
+/// # Returns
+/// * Resource address of the new token
+pub fn create_maturity_token(name: String, symbol: String, maturity_time: u64) -> ResourceAddress {
+    let global_component_caller_badge =
+        NonFungibleGlobalId::global_caller_badge(Runtime::global_address());
+
+    ResourceBuilder::new_fungible(OwnerRole::None)
+        .divisibility(DIVISIBILITY_MAXIMUM)
+        .metadata(metadata!(
+            init {
+                "name" => name, locked;
+                "symbol" => symbol, locked;
+                "maturity_time" => maturity_time, locked;
+            }
+        ))
+        .mint_roles(mint_roles! {
+            minter => rule!(require(global_component_caller_badge.clone()));
+            minter_updater => rule!(deny_all);
+        })
+        .burn_roles(burn_roles! {
+            burner => rule!(require(global_component_caller_badge.clone()));
+            burner_updater => rule!(deny_all);
+        })
+        .create_with_no_initial_supply()
+        .address()
+}
+
+/// Looks up the token of a checkpoint, creating it on first use
+///
+/// # Arguments
+/// * `checkpoint` - Checkpoint the positions were opened in
+/// * `name` - Token name if it has to be created
+/// * `symbol` - Token symbol if it has to be created
+/// * `maturity_time` - Time when the tokens mature
+/// * `resources` - Token per checkpoint
+/// * `resource_checkpoints` - Checkpoint per token
+///
+/// # Returns
+/// * Resource address of the checkpoint's token
+pub fn maturity_token_for(
+    checkpoint: u64,
+    name: String,
+    symbol: String,
+    maturity_time: u64,
+    resources: &KeyValueStore<u64, ResourceAddress>,
+    resource_checkpoints: &KeyValueStore<ResourceAddress, u64>
+) -> ResourceAddress {
+    if let Some(resource) = resources.get(&checkpoint) {
+        return *resource;
+    }
+
+    let resource = create_maturity_token(name, symbol, maturity_time);
+    resources.insert(checkpoint, resource);
+    resource_checkpoints.insert(resource, checkpoint);
+    resource
+}
+
+/// Looks up the checkpoint a maturity token belongs to
+///
+/// # Arguments
+/// * `tokens` - Bucket of bond or yield tokens
+/// * `resource_checkpoints` - Checkpoint per token
+/// * `token_name` - Name of the token for error messages
+///
+/// # Returns
+/// * Checkpoint ID of the token
+fn token_checkpoint(
+    tokens: &Bucket,
+    resource_checkpoints: &KeyValueStore<ResourceAddress, u64>,
+    token_name: &str
+) -> u64 {
+    let checkpoint = match resource_checkpoints.get(&tokens.resource_address()) {
+        Some(checkpoint) => *checkpoint,
+        None => panic!("Invalid {} resource address", token_name),
+    };
+    assert!(tokens.amount() > math::ZERO, "{} amount must be positive", token_name);
+    checkpoint
+}
+
+/// Burns a long position NFT and mints fungible bond tokens for its maturity
+///
+/// The bonds stay booked in the position's checkpoint, so pool accounting is unchanged.
+///
+/// # Arguments
+/// * `position_nft` - Long position NFT to tokenize
+/// * `pool_id` - Pool ID used in the token name
+/// * `long_positions_resource` - Resource address for long position NFTs
+/// * `position_summaries` - Summary per position NFT
+/// * `bond_resources` - Bond token per checkpoint
+/// * `bond_checkpoints` - Checkpoint per bond token
+///
+/// # Returns
+/// * Bond tokens, one per unit of face value
+pub fn tokenize_long_position(
+    position_nft: Bucket,
+    pool_id: u64,
+    long_positions_resource: ResourceAddress,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    bond_resources: &KeyValueStore<u64, ResourceAddress>,
+    bond_checkpoints: &KeyValueStore<ResourceAddress, u64>
+) -> Bucket {
+    validate_single_nft(&position_nft, long_positions_resource, "long position");
+
+    let position_data: LongPosition = position_nft.as_non_fungible().non_fungible().data();
+    let bond_resource = maturity_token_for(
+        position_data.checkpoint,
+        format!("Hyperdrive Bond Token - Pool {} - Maturity {}", pool_id, position_data.maturity_time),
+        format!("HDB-{}-{}", pool_id, position_data.maturity_time),
+        position_data.maturity_time,
+        bond_resources,
+        bond_checkpoints
+    );
+
+    burn_positions(position_nft, position_summaries);
+    FungibleResourceManager::from(bond_resource).mint(position_data.face_value).into()
+}
+
+/// Burns bond tokens and mints a long position NFT for the same maturity
+///
+/// The position's open time is its checkpoint's start time. Bond tokens carry no
+/// cost basis, so the restored position records none.
+///
+/// # Arguments
+/// * `bond_tokens` - Bond tokens to convert
+/// * `position_duration` - Duration of positions
+/// * `pool_id` - ID of the pool, recorded on the position
+/// * `long_positions_resource` - Resource address for long position NFTs
+/// * `position_counter` - Count of positions minted by the pool (mutable)
+/// * `position_summaries` - Summary per position NFT
+/// * `image_base_url` - Base URL of the NFT image service
+/// * `bond_checkpoints` - Checkpoint per bond token
+///
+/// # Returns
+/// * Long position NFT bucket
+pub fn untokenize_bonds(
+    bond_tokens: Bucket,
+    position_duration: u64,
+    pool_id: u64,
+    long_positions_resource: ResourceAddress,
+    position_counter: &mut u64,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    image_base_url: &str,
+    bond_checkpoints: &KeyValueStore<ResourceAddress, u64>
+) -> Bucket {
+    let checkpoint = token_checkpoint(&bond_tokens, bond_checkpoints, "Bond token");
+
+    let maturity_time = checkpoint + position_duration;
+
+    let position_data = LongPosition {
+        name: position_name("Long", bond_tokens.amount(), maturity_time),
+        key_image_url: position_image_url(image_base_url, "Long", bond_tokens.amount(), maturity_time),
+        face_value: bond_tokens.amount(),
+        checkpoint,
+        open_time: checkpoint,
+        maturity_time,
+        base_paid: math::ZERO,
+        shares_paid: math::ZERO,
+        lp_fee_paid: math::ZERO,
+        governance_fee_paid: math::ZERO,
+        fixed_rate_locked: math::ZERO,
+        pool_id,
+    };
+
+    bond_tokens.burn();
+    mint_long_position(position_data, long_positions_resource, position_counter, position_summaries)
+}
+
+/// Redeems matured bond tokens for base
+///
+/// Redemption is a matured long close: the holder receives face value less the
+/// flat fee, exactly as `close_long` pays at maturity.
+///
+/// # Arguments
+/// * `bond_tokens` - Matured bond tokens
+/// * `pool_state` - Current pool state (mutable)
+/// * `bond_checkpoints` - Checkpoint per bond token
+/// * `governance_vault` - Vault for governance fees (mutable)
+/// * `yield_source_vault` - Vault for yield source tokens (mutable)
+///
+/// # Returns
+/// * Base token proceeds bucket
+pub fn redeem_bond_tokens(
+    bond_tokens: Bucket,
+    share_reserves: &mut Decimal,
+    bond_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: &mut u64,
+    checkpoint_duration: u64,
+    position_duration: u64,
+    fees: &FeeSchedule,
+    bond_checkpoints: &KeyValueStore<ResourceAddress, u64>,
+    governance_vault: &mut Vault,
+    yield_source_vault: &mut Vault
+) -> Bucket {
+    let checkpoint = token_checkpoint(&bond_tokens, bond_checkpoints, "Bond token");
+    let maturity_time = checkpoint + position_duration;
+    let current_time = Runtime::current_epoch().number();
+    assert!(current_time >= maturity_time, "Bonds have not matured");
+
+    // Update checkpoint if needed
+    *current_checkpoint = update_checkpoint_if_needed(
+        checkpoints,
+        *current_checkpoint,
+        checkpoint_duration,
+        share_price
+    );
+
+    // Apply the trade to the pool
+    let trade = apply_close_long(
+        bond_tokens.amount(),
+        checkpoint,
+        maturity_time,
+        checkpoint,
+        current_time,
+        share_reserves,
+        bond_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        fees
+    );
+
+    bond_tokens.burn();
+
+    // Handle governance fee (fees are denominated in base)
+    if trade.governance_fee > math::ZERO {
+        governance_vault.put(yield_source_vault.take(trade.governance_fee));
+    }
+
+    yield_source_vault.take(trade.base_proceeds)
+}
+
+/// Burns a short position NFT and mints fungible yield tokens for its maturity
+///
+/// Yield tokens accrue from the checkpoint's share price, so the short's collateral
+/// is rebooked at that price. The short stays booked in its checkpoint.
+///
+/// # Arguments
+/// * `position_nft` - Short position NFT to tokenize
+/// * `pool_id` - Pool ID used in the token name
+/// * `share_reserves` - Share reserves (mutable)
+/// * `checkpoints` - Reference to the checkpoints HashMap
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// * `short_positions_resource` - Resource address for short position NFTs
+/// * `position_summaries` - Summary per position NFT
+/// * `yield_resources` - Yield token per checkpoint
+/// * `yield_checkpoints` - Checkpoint per yield token
+///
+/// # Returns
+/// * Yield tokens, one per unit of face value
+pub fn tokenize_short_position(
+    position_nft: Bucket,
+    pool_id: u64,
+    share_reserves: &mut Decimal,
+    checkpoints: &HashMap<u64, Checkpoint>,
+    short_collateral: &mut Decimal,
+    short_positions_resource: ResourceAddress,
+    position_summaries: &KeyValueStore<NonFungibleLocalId, PositionSummary>,
+    yield_resources: &KeyValueStore<u64, ResourceAddress>,
+    yield_checkpoints: &KeyValueStore<ResourceAddress, u64>
+) -> Bucket {
+    validate_single_nft(&position_nft, short_positions_resource, "short position");
+
+    let position_data: ShortPosition = position_nft.as_non_fungible().non_fungible().data();
+    let checkpoint_share_price = checkpoints.get(&position_data.checkpoint)
+        .expect("Position checkpoint not found")
+        .share_price;
+
+    rebook_short_collateral(
+        position_data.face_value,
+        position_data.initial_share_price,
+        checkpoint_share_price,
+        share_reserves,
+        short_collateral
+    );
+
+    let yield_resource = maturity_token_for(
+        position_data.checkpoint,
+        format!("Hyperdrive Yield Token - Pool {} - Maturity {}", pool_id, position_data.maturity_time),
+        format!("HDY-{}-{}", pool_id, position_data.maturity_time),
+        position_data.maturity_time,
+        yield_resources,
+        yield_checkpoints
+    );
+
+    burn_positions(position_nft, position_summaries);
+    FungibleResourceManager::from(yield_resource).mint(position_data.face_value).into()
+}
+
+/// Redeems matured yield tokens for the variable interest they accrued
+///
+/// Each token pays `c_maturity / c_checkpoint - 1` in base, less the matured bond
+/// fee. If no checkpoint was minted at maturity the current share price is used.
+///
+/// # Arguments
+/// * `yield_tokens` - Matured yield tokens
+/// * `pool_state` - Current pool state (mutable)
+/// * `short_collateral` - Outstanding short collateral in shares (mutable)
+/// * `yield_checkpoints` - Checkpoint per yield token
+/// * `governance_vault` - Vault for governance fees (mutable)
+/// * `yield_source_vault` - Vault for yield source tokens (mutable)
+///
+/// # Returns
+/// * Base token proceeds bucket
+pub fn redeem_yield_tokens(
+    yield_tokens: Bucket,
+    share_reserves: &mut Decimal,
+    zeta_adjustment: &mut Decimal,
+    share_price: Decimal,
+    checkpoints: &mut HashMap<u64, Checkpoint>,
+    current_checkpoint: &mut u64,
+    checkpoint_duration: u64,
+    position_duration: u64,
+    fees: &FeeSchedule,
+    short_collateral: &mut Decimal,
+    yield_checkpoints: &KeyValueStore<ResourceAddress, u64>,
+    governance_vault: &mut Vault,
+    yield_source_vault: &mut Vault
+) -> Bucket {
+    let checkpoint = token_checkpoint(&yield_tokens, yield_checkpoints, "Yield token");
+    let maturity_time = checkpoint + position_duration;
+    assert!(Runtime::current_epoch().number() >= maturity_time, "Yield tokens have not matured");
+
+    // Update checkpoint if needed
+    *current_checkpoint = update_checkpoint_if_needed(
+        checkpoints,
+        *current_checkpoint,
+        checkpoint_duration,
+        share_price
+    );
+
+    let checkpoint_share_price = checkpoints.get(&checkpoint)
+        .expect("Position checkpoint not found")
+        .share_price;
+    let maturity_share_price = mint_maturity_checkpoint(checkpoints, maturity_time, share_price);
+
+    // Apply the redemption to the pool
+    let trade = apply_redeem_yield(
+        yield_tokens.amount(),
+        checkpoint_share_price,
+        maturity_share_price,
+        checkpoint,
+        share_reserves,
+        zeta_adjustment,
+        share_price,
+        checkpoints,
+        fees,
+        short_collateral
+    );
+
+    yield_tokens.burn();
+
+    // Handle governance fee (fees are denominated in base)
+    if trade.governance_fee > math::ZERO {
+        governance_vault.put(yield_source_vault.take(trade.governance_fee));
+    }
+
+    yield_source_vault.take(trade.base_proceeds)
+}
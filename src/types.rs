@@ -48,10 +48,128 @@ pub struct PoolState {
     pub current_checkpoint: u64,
 }
 
+/// Reconciliation of vault balances against the pool's internal accounting
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct InvariantReport {
+    /// Base tokens actually held in the yield source vault
+    pub vault_balance: Decimal,
+    /// Base tokens the vault should hold according to internal accounting
+    pub expected_balance: Decimal,
+    /// Vault balance minus expected balance (positive is a surplus)
+    pub discrepancy: Decimal,
+    /// Share reserves plus minimum reserves, valued in base ((z + zmin) * c)
+    pub share_reserves_value: Decimal,
+    /// Zombie share reserves, valued in base
+    pub zombie_reserves_value: Decimal,
+    /// Outstanding short collateral, valued in base
+    pub short_collateral_value: Decimal,
+    /// Base credited to tracked shares by share price updates, not held in the vault
+    pub accrued_yield: Decimal,
+    /// Whether the discrepancy is within rounding tolerance
+    pub is_consistent: bool,
+}
+
+/// Outcome of applying an open long to the pool's reserves
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct LongOpened {
+    /// Face value of bonds received after the LP fee
+    pub face_value: Decimal,
+    /// Time when the position matures
+    pub maturity_time: u64,
+    /// Governance fee owed in base
+    pub governance_fee: Decimal,
+    /// LP fee retained by the pool
+    pub lp_fee: Decimal,
+}
+
+/// Outcome of applying an open short to the pool's reserves
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct ShortOpened {
+    /// Total deposit required from the trader, fees included
+    pub total_deposit: Decimal,
+    /// Time when the position matures
+    pub maturity_time: u64,
+    /// Governance fee owed in base
+    pub governance_fee: Decimal,
+    /// LP fee retained by the pool
+    pub lp_fee: Decimal,
+}
+
+/// Outcome of applying a long or short close to the pool's reserves
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct PositionClosed {
+    /// Time remaining on the position when closed (0 to 1)
+    pub time_remaining: Decimal,
+    /// Base proceeds owed to the trader
+    pub base_proceeds: Decimal,
+    /// Governance fee owed in base
+    pub governance_fee: Decimal,
+    /// LP fee retained by the pool
+    pub lp_fee: Decimal,
+}
+
+/// One position being closed as part of a batch
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct CloseLot {
+    /// Face value being closed
+    pub face_value: Decimal,
+    /// Time when the position was opened
+    pub open_time: u64,
+    /// Time when the position matures
+    pub maturity_time: u64,
+    /// Share price when the position was opened (shorts only)
+    pub initial_share_price: Decimal,
+}
+
+/// Per-NFT result of a batch close
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct ClosedPosition {
+    /// Local ID of the burned position NFT
+    pub position_id: NonFungibleLocalId,
+    /// Face value closed
+    pub face_value: Decimal,
+    /// Time remaining on the position when closed (0 to 1)
+    pub time_remaining: Decimal,
+    /// Base proceeds paid for this position
+    pub base_proceeds: Decimal,
+    /// Governance fee charged on this position
+    pub governance_fee: Decimal,
+    /// LP fee charged on this position
+    pub lp_fee: Decimal,
+    /// Base paid to open this position, fees included
+    pub cost_basis: Decimal,
+    /// Base proceeds less cost basis
+    pub realized_pnl: Decimal,
+}
+
+/// Marked value of a long or short position NFT
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct PositionValue {
+    /// Face value of the position
+    pub face_value: Decimal,
+    /// Base proceeds a full close would pay now, net of fees
+    pub close_proceeds: Decimal,
+    /// Time remaining on the position (0 to 1)
+    pub time_remaining: Decimal,
+    /// Variable interest accrued on the face value since open (shorts only)
+    pub accrued_interest: Decimal,
+    /// Base paid to open the position, fees included
+    pub cost_basis: Decimal,
+    /// Close proceeds less cost basis
+    pub pnl: Decimal,
+}
+
 /// Long position data stored in NFT
 #[derive(ScryptoSbor, ManifestSbor, NonFungibleData)]
 pub struct LongPosition {
-    /// Face value of the long position (Δy)
+    /// Display name encoding side, face value and maturity epoch
+    #[mutable]
+    pub name: String,
+    /// Display image encoding side, face value and maturity epoch
+    #[mutable]
+    pub key_image_url: Url,
+    /// Face value of the long position (Δy), reduced by partial closes
+    #[mutable]
     pub face_value: Decimal,
     /// Checkpoint when position was opened
     pub checkpoint: u64,
@@ -59,12 +177,60 @@ pub struct LongPosition {
     pub open_time: u64,
     /// Time when position matures
     pub maturity_time: u64,
+    /// Base paid to open the position, fees included, reduced pro rata by partial closes
+    #[mutable]
+    pub base_paid: Decimal,
+    /// Shares paid to open the position (base paid over the share price at open)
+    #[mutable]
+    pub shares_paid: Decimal,
+    /// LP fee paid at open
+    #[mutable]
+    pub lp_fee_paid: Decimal,
+    /// Governance fee paid at open
+    #[mutable]
+    pub governance_fee_paid: Decimal,
+    /// Fixed rate received over the term, net of fees (face value over base paid, less one)
+    pub fixed_rate_locked: Decimal,
+    /// Pool the position was opened in
+    pub pool_id: u64,
+}
+
+impl LongPosition {
+    /// Summary kept for the position after its NFT is burned
+    pub fn summary(&self) -> PositionSummary {
+        PositionSummary {
+            side: PositionSide::Long,
+            face_value: self.face_value,
+            checkpoint: self.checkpoint,
+            open_time: self.open_time,
+            maturity_time: self.maturity_time,
+            base_paid: self.base_paid,
+            burned_time: None,
+        }
+    }
+
+    /// Cost basis of the position
+    pub fn cost(&self) -> PositionCost {
+        PositionCost {
+            base_paid: self.base_paid,
+            shares_paid: self.shares_paid,
+            lp_fee_paid: self.lp_fee_paid,
+            governance_fee_paid: self.governance_fee_paid,
+        }
+    }
 }
 
 /// Short position data stored in NFT
 #[derive(ScryptoSbor, ManifestSbor, NonFungibleData)]
 pub struct ShortPosition {
-    /// Face value of the short position (Δy)
+    /// Display name encoding side, face value and maturity epoch
+    #[mutable]
+    pub name: String,
+    /// Display image encoding side, face value and maturity epoch
+    #[mutable]
+    pub key_image_url: Url,
+    /// Face value of the short position (Δy), reduced by partial closes
+    #[mutable]
     pub face_value: Decimal,
     /// Checkpoint when position was opened
     pub checkpoint: u64,
@@ -74,6 +240,339 @@ pub struct ShortPosition {
     pub maturity_time: u64,
     /// Share price when position was opened (c0)
     pub initial_share_price: Decimal,
+    /// Base deposited to open the position, fees included, reduced pro rata by partial closes
+    #[mutable]
+    pub base_paid: Decimal,
+    /// Shares paid to open the position (base paid over the share price at open)
+    #[mutable]
+    pub shares_paid: Decimal,
+    /// LP fee paid at open
+    #[mutable]
+    pub lp_fee_paid: Decimal,
+    /// Governance fee paid at open
+    #[mutable]
+    pub governance_fee_paid: Decimal,
+    /// Fixed rate paid over the term, net of fees (face value over face value less deposit, less one)
+    pub fixed_rate_locked: Decimal,
+    /// Pool the position was opened in
+    pub pool_id: u64,
+}
+
+impl ShortPosition {
+    /// Summary kept for the position after its NFT is burned
+    pub fn summary(&self) -> PositionSummary {
+        PositionSummary {
+            side: PositionSide::Short,
+            face_value: self.face_value,
+            checkpoint: self.checkpoint,
+            open_time: self.open_time,
+            maturity_time: self.maturity_time,
+            base_paid: self.base_paid,
+            burned_time: None,
+        }
+    }
+
+    /// Cost basis of the position
+    pub fn cost(&self) -> PositionCost {
+        PositionCost {
+            base_paid: self.base_paid,
+            shares_paid: self.shares_paid,
+            lp_fee_paid: self.lp_fee_paid,
+            governance_fee_paid: self.governance_fee_paid,
+        }
+    }
+}
+
+/// Side of a position
+#[derive(ScryptoSbor, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionSide {
+    Long,
+    Short,
+}
+
+/// Record of a position NFT, kept after the NFT is burned
+#[derive(ScryptoSbor, Clone, Debug, PartialEq)]
+pub struct PositionSummary {
+    /// Long or short
+    pub side: PositionSide,
+    /// Face value when the NFT was minted
+    pub face_value: Decimal,
+    /// Checkpoint when position was opened
+    pub checkpoint: u64,
+    /// Time when position was opened
+    pub open_time: u64,
+    /// Time when position matures
+    pub maturity_time: u64,
+    /// Base paid when the NFT was minted, fees included
+    pub base_paid: Decimal,
+    /// Time the NFT was burned by a close, merge, split, rollover or tokenization
+    pub burned_time: Option<u64>,
+}
+
+/// Cost basis carried by a position NFT
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct PositionCost {
+    /// Base paid, fees included
+    pub base_paid: Decimal,
+    /// Shares paid
+    pub shares_paid: Decimal,
+    /// LP fee paid
+    pub lp_fee_paid: Decimal,
+    /// Governance fee paid
+    pub governance_fee_paid: Decimal,
+}
+
+impl PositionCost {
+    /// Cost basis of the given fraction of the position
+    pub fn scaled(&self, fraction: Decimal) -> Self {
+        Self {
+            base_paid: self.base_paid * fraction,
+            shares_paid: self.shares_paid * fraction,
+            lp_fee_paid: self.lp_fee_paid * fraction,
+            governance_fee_paid: self.governance_fee_paid * fraction,
+        }
+    }
+
+    /// Combined cost basis of two positions
+    pub fn plus(&self, other: &Self) -> Self {
+        Self {
+            base_paid: self.base_paid + other.base_paid,
+            shares_paid: self.shares_paid + other.shares_paid,
+            lp_fee_paid: self.lp_fee_paid + other.lp_fee_paid,
+            governance_fee_paid: self.governance_fee_paid + other.governance_fee_paid,
+        }
+    }
+}
+
+/// Operating mode of the pool, set by the pauser
+#[derive(ScryptoSbor, ManifestSbor, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolMode {
+    /// Every operation is allowed
+    Normal,
+    /// Positions can be closed and liquidity removed, but nothing new opened or added
+    CloseOnly,
+    /// Only the emergency exits are allowed
+    Paused,
+}
+
+impl PoolMode {
+    /// Whether new positions and liquidity are accepted
+    pub fn allows_opens(&self) -> bool {
+        *self == PoolMode::Normal
+    }
+
+    /// Whether closes, withdrawals and other non-emergency operations are accepted
+    pub fn allows_closes(&self) -> bool {
+        *self != PoolMode::Paused
+    }
+}
+
+/// Emitted when the pauser changes the pool mode
+#[derive(ScryptoSbor, ScryptoEvent, Clone, Debug)]
+pub struct ModeChangedEvent {
+    pub previous_mode: PoolMode,
+    pub mode: PoolMode,
+}
+
+/// The pool's fee parameters; the default charges no fees
+#[derive(ScryptoSbor, ManifestSbor, Clone, Debug, Default, PartialEq)]
+pub struct FeeSchedule {
+    /// Curve fee on bonds traded on the curve, scaled by one minus the spot price (ϕc)
+    pub curve_fee: Decimal,
+    /// Flat fee on matured bonds (ϕf)
+    pub flat_fee: Decimal,
+    /// Governance share of the curve fee (ϕg,c)
+    pub governance_curve_fee: Decimal,
+    /// Governance share of the flat fee (ϕg,f)
+    pub governance_flat_fee: Decimal,
+    /// Zombie interest governance fee (ϕg,zombie)
+    pub zombie_governance_fee: Decimal,
+}
+
+/// How the pool sets the curve fee rate, chosen at pool creation
+#[derive(ScryptoSbor, ManifestSbor, Clone, Debug, PartialEq)]
+pub enum FeeMode {
+    /// Every trade pays the fee schedule's curve fee
+    Fixed,
+    /// The curve fee scales with trade size, utilization and rate volatility
+    Dynamic(DynamicFeeConfig),
+}
+
+/// Parameters of the dynamic curve fee
+#[derive(ScryptoSbor, ManifestSbor, Clone, Debug, PartialEq)]
+pub struct DynamicFeeConfig {
+    /// Weight of trade size over effective reserves (ks)
+    pub size_multiplier: Decimal,
+    /// Weight of exposure over effective reserves (ku)
+    pub utilization_multiplier: Decimal,
+    /// Weight of checkpoint rate volatility (kv)
+    pub volatility_multiplier: Decimal,
+    /// Lowest curve fee a trade can pay (ϕmin)
+    pub min_curve_fee: Decimal,
+    /// Highest curve fee a trade can pay (ϕmax)
+    pub max_curve_fee: Decimal,
+    /// Number of checkpoints the volatility is measured over
+    pub volatility_window: u64,
+}
+
+/// Curve fee rate quoted for a trade, with the inputs it was priced from
+#[derive(ScryptoSbor, Clone, Debug, PartialEq)]
+pub struct CurveFeeQuote {
+    /// Curve fee of the fee schedule (ϕc)
+    pub base_curve_fee: Decimal,
+    /// Trade face value over effective reserves
+    pub size: Decimal,
+    /// Exposure over effective reserves
+    pub utilization: Decimal,
+    /// Rate volatility over the volatility window
+    pub volatility: Decimal,
+    /// Curve fee rate the trade pays
+    pub curve_fee: Decimal,
+}
+
+/// Fees charged on a single trade, in base
+#[derive(ScryptoSbor, Clone, Copy, Debug, PartialEq)]
+pub struct TradeFees {
+    /// Curve fee on the unmatured part of the trade
+    pub curve_fee: Decimal,
+    /// Flat fee on the matured part of the trade
+    pub flat_fee: Decimal,
+    /// Governance share of the curve fee
+    pub governance_curve_fee: Decimal,
+    /// Governance share of the flat fee
+    pub governance_flat_fee: Decimal,
+}
+
+impl TradeFees {
+    /// Total fee paid by the trader
+    pub fn total(&self) -> Decimal {
+        self.curve_fee + self.flat_fee
+    }
+
+    /// Part of the fee that leaves the pool for governance
+    pub fn governance(&self) -> Decimal {
+        self.governance_curve_fee + self.governance_flat_fee
+    }
+
+    /// Part of the fee retained by the pool for LPs
+    pub fn lp(&self) -> Decimal {
+        self.total() - self.governance()
+    }
+
+    /// Part of the flat fee retained by the pool for LPs
+    pub fn lp_flat(&self) -> Decimal {
+        self.flat_fee - self.governance_flat_fee
+    }
+}
+
+/// Fee change waiting out the fee update delay
+#[derive(ScryptoSbor, Clone, Debug)]
+pub struct FeeProposal {
+    /// Fees that take effect on execution
+    pub fees: FeeSchedule,
+    /// Time the change was proposed
+    pub proposed_time: u64,
+    /// Earliest time the change can be executed
+    pub executable_time: u64,
+}
+
+/// Emitted when the fee manager proposes a fee change
+#[derive(ScryptoSbor, ScryptoEvent, Clone, Debug)]
+pub struct FeesProposedEvent {
+    pub fees: FeeSchedule,
+    pub executable_time: u64,
+}
+
+/// Emitted when a proposed fee change takes effect
+#[derive(ScryptoSbor, ScryptoEvent, Clone, Debug)]
+pub struct FeesUpdatedEvent {
+    pub previous_fees: FeeSchedule,
+    pub fees: FeeSchedule,
+}
+
+/// Emitted when the fee manager withdraws a pending fee change
+#[derive(ScryptoSbor, ScryptoEvent, Clone, Debug)]
+pub struct FeeProposalCancelledEvent {
+    pub fees: FeeSchedule,
+}
+
+/// Emitted when governance fees are deposited into the fee recipient
+#[derive(ScryptoSbor, ScryptoEvent, Clone, Debug)]
+pub struct GovernanceFeesCollectedEvent {
+    pub fee_recipient: ComponentAddress,
+    pub amount: Decimal,
+}
+
+/// Emitted when a referred trade accrues a share of the governance fee to its referrer
+#[derive(ScryptoSbor, ScryptoEvent, Clone, Debug)]
+pub struct ReferralFeeAccruedEvent {
+    pub referrer: ComponentAddress,
+    /// Face value of the bonds the referred trade moved
+    pub volume: Decimal,
+    pub fee: Decimal,
+}
+
+/// Emitted when a referrer's accrued fees are deposited into their account
+#[derive(ScryptoSbor, ScryptoEvent, Clone, Debug)]
+pub struct ReferralFeesClaimedEvent {
+    pub referrer: ComponentAddress,
+    pub amount: Decimal,
+}
+
+/// Point in the pool's rate history, with the accumulators TWAPs are taken from
+#[derive(ScryptoSbor, Clone, Debug, PartialEq)]
+pub struct RateObservation {
+    /// Time of the observation
+    pub time: u64,
+    /// Annualized fixed rate in effect from this observation until the next
+    pub fixed_rate: Decimal,
+    /// Share price in effect from this observation until the next
+    pub share_price: Decimal,
+    /// Sum of the fixed rate times the epochs it was in effect, up to this observation
+    pub cumulative_rate: Decimal,
+    /// Sum of the share price times the epochs it was in effect, up to this observation
+    pub cumulative_share_price: Decimal,
+}
+
+/// Access rule of a pool role and the rule allowed to change it
+#[derive(ScryptoSbor, ManifestSbor, Clone, Debug)]
+pub struct RoleRules {
+    /// Who holds the role
+    pub rule: AccessRule,
+    /// Who may replace the role's rule, and this updater rule itself
+    pub updater: AccessRule,
+}
+
+/// Role rules set when the pool component is instantiated
+#[derive(ScryptoSbor, ManifestSbor, Clone, Debug)]
+pub struct PoolRoles {
+    /// Withdraws governance fees
+    pub fee_manager: RoleRules,
+    /// Halts trading in an emergency
+    pub pauser: RoleRules,
+    /// Reports the yield source share price
+    pub oracle_updater: RoleRules,
+    /// Collects governance fees from a bot
+    pub keeper: RoleRules,
+    /// Creates the pool
+    pub pool_creator: RoleRules,
+}
+
+impl PoolRoles {
+    /// Gives every role, and every updater, to the holder of one badge
+    pub fn single_badge(badge: ResourceAddress) -> Self {
+        let role = || RoleRules {
+            rule: rule!(require(badge)),
+            updater: rule!(require(badge)),
+        };
+        Self {
+            fee_manager: role(),
+            pauser: role(),
+            oracle_updater: role(),
+            keeper: role(),
+            pool_creator: role(),
+        }
+    }
 }
 
 /// Complete data structure for a Hyperdrive pool
@@ -89,7 +588,6 @@ pub struct HyperdrivePoolData {
     pub share_price: Decimal,            // c: Current share price
     
     // Resource addresses
-    pub bond_resource_address: ResourceAddress,
     pub active_lp_shares_address: ResourceAddress,
     pub withdrawal_shares_address: ResourceAddress,
     pub ready_withdrawal_shares_address: ResourceAddress,
@@ -103,10 +601,7 @@ pub struct HyperdrivePoolData {
     pub current_checkpoint: u64,         // Current checkpoint ID
     
     // Fees
-    pub new_bond_fee: Decimal,           // ϕn: Fee for newly minted bonds
-    pub matured_bond_fee: Decimal,       // ϕm: Fee for matured bonds
-    pub governance_fee: Decimal,         // ϕg: Governance fee portion
-    pub zombie_governance_fee: Decimal,  // ϕg,zombie: Zombie interest governance fee
+    pub fees: FeeSchedule,               // ϕc, ϕf, ϕg,c, ϕg,f and ϕg,zombie
     
     // Minimum reserves
     pub min_share_reserves: Decimal,     // zmin: Minimum share reserves
@@ -117,7 +612,6 @@ pub struct HyperdrivePoolData {
     
     // Vaults
     pub yield_source_vault: Vault,       // Holds the base tokens
-    pub bond_vault: Vault,               // Holds bond tokens
     pub active_lp_vault: Vault,          // Holds active LP tokens
     pub withdrawal_vault: Vault,         // Holds withdrawal tokens
     pub ready_withdrawal_vault: Vault,   // Holds ready withdrawal tokens
@@ -0,0 +1,622 @@
+#![allow(dead_code)]
+
+use scrypto_test::prelude::*;
+use influxpool::types::*;
+
+/// Checkpoint duration used by the test pools (1 week in seconds)
+pub const CHECKPOINT_DURATION: u64 = 604800;
+/// Position duration used by the test pools (52 checkpoints)
+pub const POSITION_DURATION: u64 = CHECKPOINT_DURATION * 52;
+/// Minimum share reserves used by the test pools
+pub const MIN_SHARE_RESERVES: Decimal = dec!("1000");
+/// Initial liquidity seeded into the test pools
+pub const INITIAL_LIQUIDITY: Decimal = dec!("100000");
+/// Annualized fixed rate the test pools open at, so trades run on the curve
+pub const INITIAL_FIXED_RATE: Decimal = dec!("0.05");
+/// Observations kept by the pool's rate oracle before the oldest is overwritten
+pub const OBSERVATION_CAPACITY: u64 = 1024;
+/// Most observations returned by one `get_rate_history` call
+pub const MAX_OBSERVATION_PAGE: u64 = 100;
+/// Display URLs passed to `create_pool`
+pub const INFO_URL: &str = "https://example.com";
+pub const ICON_URL: &str = "https://example.com/icon.png";
+pub const IMAGE_BASE_URL: &str = "https://example.com/position.svg";
+
+/// Fees of the test pools: 1% curve, 0.5% flat and 10% of each to governance
+pub fn default_fees() -> FeeSchedule {
+    FeeSchedule {
+        curve_fee: dec!("0.01"),
+        flat_fee: dec!("0.005"),
+        governance_curve_fee: dec!("0.1"),
+        governance_flat_fee: dec!("0.1"),
+        zombie_governance_fee: dec!("0.1"),
+    }
+}
+
+/// Dynamic fee of the test pools: the curve fee grows with trade size, between 0.5% and 5%
+pub fn dynamic_fee_config() -> DynamicFeeConfig {
+    DynamicFeeConfig {
+        size_multiplier: dec!("1"),
+        utilization_multiplier: dec!("1"),
+        volatility_multiplier: dec!("10"),
+        min_curve_fee: dec!("0.005"),
+        max_curve_fee: dec!("0.05"),
+        volatility_window: 8,
+    }
+}
+
+/// Asserts two amounts agree up to rounding in the last decimal places
+pub fn assert_close(a: Decimal, b: Decimal) {
+    assert!((a - b).checked_abs().unwrap() <= dec!("0.000000001"), "{} != {}", a, b);
+}
+
+/// Face value as shown in position names and image URLs
+pub fn display_face(face_value: Decimal) -> Decimal {
+    face_value.checked_round(2, RoundingMode::ToNearestMidpointAwayFromZero).unwrap()
+}
+
+/// Ledger simulator with a published package and a created pool
+pub struct TestEnv {
+    pub ledger: DefaultLedgerSimulator,
+    pub public_key: Secp256k1PublicKey,
+    pub account: ComponentAddress,
+    pub package: PackageAddress,
+    pub base_token: ResourceAddress,
+    pub admin_badge: ResourceAddress,
+    pub component: ComponentAddress,
+    pub lp_token: ResourceAddress,
+    pub withdrawal_token: ResourceAddress,
+    pub long_nft: ResourceAddress,
+    pub short_nft: ResourceAddress,
+}
+
+impl TestEnv {
+    /// Publishes the package, instantiates the component and creates a pool
+    pub fn new() -> Self {
+        let mut env = Self::without_pool();
+        let receipt = env.create_pool(default_fees(), INITIAL_LIQUIDITY);
+        // Resources are created in order: LP, withdrawal, ready withdrawal, long, short
+        let resources = receipt.expect_commit(true).new_resource_addresses().clone();
+        env.lp_token = resources[0];
+        env.withdrawal_token = resources[1];
+        env.long_nft = resources[3];
+        env.short_nft = resources[4];
+        env
+    }
+
+    /// Publishes the package and instantiates the component without creating a pool
+    pub fn without_pool() -> Self {
+        let mut ledger = LedgerSimulatorBuilder::new().build();
+        let (public_key, _private_key, account) = ledger.new_allocated_account();
+        let package = ledger.compile_and_publish(this_package!());
+        let base_token = ledger.create_fungible_resource(dec!("10000000"), 18, account);
+        let admin_badge = ledger.create_fungible_resource(dec!("1"), 0, account);
+
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_function(
+                package,
+                "HyperdrivePool",
+                "instantiate_dex",
+                manifest_args!(base_token, admin_badge),
+            )
+            .build();
+        let receipt = ledger.execute_manifest(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(&public_key)],
+        );
+        let component = receipt.expect_commit(true).new_component_addresses()[0];
+
+        Self {
+            ledger,
+            public_key,
+            account,
+            package,
+            base_token,
+            admin_badge,
+            component,
+            lp_token: XRD,
+            withdrawal_token: XRD,
+            long_nft: XRD,
+            short_nft: XRD,
+        }
+    }
+
+    /// Executes a manifest signed by the test account
+    pub fn execute(&mut self, manifest: TransactionManifestV1) -> TransactionReceipt {
+        self.ledger.execute_manifest(
+            manifest,
+            vec![NonFungibleGlobalId::from_public_key(&self.public_key)],
+        )
+    }
+
+    /// Calls `create_pool` with the standard durations and the given fees
+    pub fn create_pool(&mut self, fees: FeeSchedule, initial_liquidity: Decimal) -> TransactionReceipt {
+        self.create_pool_with_mode(fees, FeeMode::Fixed, initial_liquidity)
+    }
+
+    /// Calls `create_pool` with the standard durations, the given fees and fee mode
+    pub fn create_pool_with_mode(
+        &mut self,
+        fees: FeeSchedule,
+        fee_mode: FeeMode,
+        initial_liquidity: Decimal,
+    ) -> TransactionReceipt {
+        self.create_pool_at_rate(fees, fee_mode, INITIAL_FIXED_RATE, initial_liquidity)
+    }
+
+    /// Calls `create_pool` with the standard durations and every pricing argument given
+    pub fn create_pool_at_rate(
+        &mut self,
+        fees: FeeSchedule,
+        fee_mode: FeeMode,
+        initial_fixed_rate: Decimal,
+        initial_liquidity: Decimal,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_amount(self.account, self.admin_badge, dec!("1"))
+            .withdraw_from_account(self.account, self.base_token, initial_liquidity)
+            .take_from_worktop(self.base_token, initial_liquidity, "liquidity")
+            .call_method_with_name_lookup(self.component, "create_pool", |lookup| {
+                (
+                    CHECKPOINT_DURATION,
+                    POSITION_DURATION,
+                    fees.clone(),
+                    fee_mode.clone(),
+                    MIN_SHARE_RESERVES,
+                    initial_fixed_rate,
+                    INFO_URL.to_string(),
+                    ICON_URL.to_string(),
+                    IMAGE_BASE_URL.to_string(),
+                    lookup.bucket("liquidity"),
+                )
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Calls a method that takes a single bucket of fungibles withdrawn from the account
+    pub fn call_with_fungible(
+        &mut self,
+        method: &str,
+        resource: ResourceAddress,
+        amount: Decimal,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .withdraw_from_account(self.account, resource, amount)
+            .take_from_worktop(resource, amount, "input")
+            .call_method_with_name_lookup(self.component, method, |lookup| {
+                (lookup.bucket("input"),)
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Calls a method that takes a single bucket holding the given NFTs
+    pub fn call_with_nfts(
+        &mut self,
+        method: &str,
+        resource: ResourceAddress,
+        ids: Vec<NonFungibleLocalId>,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .withdraw_non_fungibles_from_account(self.account, resource, ids)
+            .take_all_from_worktop(resource, "nfts")
+            .call_method_with_name_lookup(self.component, method, |lookup| {
+                (lookup.bucket("nfts"),)
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Opens a long position with the given base amount
+    pub fn open_long(&mut self, base_amount: Decimal) -> TransactionReceipt {
+        self.open_long_with(self.base_token, base_amount, None)
+    }
+
+    /// Calls `open_long` with an amount of any resource and an optional referrer
+    pub fn open_long_with(
+        &mut self,
+        resource: ResourceAddress,
+        amount: Decimal,
+        referrer: Option<ComponentAddress>,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .withdraw_from_account(self.account, resource, amount)
+            .take_from_worktop(resource, amount, "input")
+            .call_method_with_name_lookup(self.component, "open_long", |lookup| {
+                (lookup.bucket("input"), referrer)
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Opens a short position with the given deposit and face value
+    pub fn open_short(&mut self, deposit: Decimal, face_value: Decimal) -> TransactionReceipt {
+        self.open_short_with(deposit, face_value, None)
+    }
+
+    /// Opens a short position with the given deposit, face value and referrer
+    pub fn open_short_with(
+        &mut self,
+        deposit: Decimal,
+        face_value: Decimal,
+        referrer: Option<ComponentAddress>,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .withdraw_from_account(self.account, self.base_token, deposit)
+            .take_from_worktop(self.base_token, deposit, "deposit")
+            .call_method_with_name_lookup(self.component, "open_short", |lookup| {
+                (lookup.bucket("deposit"), face_value, referrer)
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Calls a close method with the given NFTs and bond amount
+    pub fn call_close(
+        &mut self,
+        method: &str,
+        resource: ResourceAddress,
+        ids: Vec<NonFungibleLocalId>,
+        bond_amount: Decimal,
+    ) -> TransactionReceipt {
+        self.call_close_with(method, resource, ids, bond_amount, None)
+    }
+
+    /// Calls a close method with the given NFTs, bond amount and referrer
+    pub fn call_close_with(
+        &mut self,
+        method: &str,
+        resource: ResourceAddress,
+        ids: Vec<NonFungibleLocalId>,
+        bond_amount: Decimal,
+        referrer: Option<ComponentAddress>,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .withdraw_non_fungibles_from_account(self.account, resource, ids)
+            .take_all_from_worktop(resource, "nfts")
+            .call_method_with_name_lookup(self.component, method, |lookup| {
+                (lookup.bucket("nfts"), bond_amount, referrer)
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Calls a batch close method with the given NFTs and referrer
+    pub fn call_batch_close(
+        &mut self,
+        method: &str,
+        resource: ResourceAddress,
+        ids: Vec<NonFungibleLocalId>,
+        referrer: Option<ComponentAddress>,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .withdraw_non_fungibles_from_account(self.account, resource, ids)
+            .take_all_from_worktop(resource, "nfts")
+            .call_method_with_name_lookup(self.component, method, |lookup| {
+                (lookup.bucket("nfts"), referrer)
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Closes the whole long position with the given ID
+    pub fn close_long(&mut self, id: NonFungibleLocalId) -> TransactionReceipt {
+        let face_value = self.nft_data::<LongPosition>(self.long_nft, &id).face_value;
+        self.partial_close_long(id, face_value)
+    }
+
+    /// Closes `bond_amount` of the long position with the given ID
+    pub fn partial_close_long(&mut self, id: NonFungibleLocalId, bond_amount: Decimal) -> TransactionReceipt {
+        self.call_close("close_long", self.long_nft, vec![id], bond_amount)
+    }
+
+    /// Closes the whole short position with the given ID
+    pub fn close_short(&mut self, id: NonFungibleLocalId) -> TransactionReceipt {
+        let face_value = self.nft_data::<ShortPosition>(self.short_nft, &id).face_value;
+        self.partial_close_short(id, face_value)
+    }
+
+    /// Closes `bond_amount` of the short position with the given ID
+    pub fn partial_close_short(&mut self, id: NonFungibleLocalId, bond_amount: Decimal) -> TransactionReceipt {
+        self.call_close("close_short", self.short_nft, vec![id], bond_amount)
+    }
+
+    /// Closes the given long positions in one batch
+    pub fn close_longs(&mut self, ids: Vec<NonFungibleLocalId>) -> TransactionReceipt {
+        self.call_batch_close("close_longs", self.long_nft, ids, None)
+    }
+
+    /// Closes the given short positions in one batch
+    pub fn close_shorts(&mut self, ids: Vec<NonFungibleLocalId>) -> TransactionReceipt {
+        self.call_batch_close("close_shorts", self.short_nft, ids, None)
+    }
+
+    /// Merges the given position NFTs into one
+    pub fn merge_positions(&mut self, resource: ResourceAddress, ids: Vec<NonFungibleLocalId>) -> TransactionReceipt {
+        self.call_with_nfts("merge_positions", resource, ids)
+    }
+
+    /// Splits a position NFT into one NFT per face value
+    pub fn split_position(
+        &mut self,
+        resource: ResourceAddress,
+        id: NonFungibleLocalId,
+        amounts: Vec<Decimal>,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .withdraw_non_fungibles_from_account(self.account, resource, vec![id])
+            .take_all_from_worktop(resource, "nft")
+            .call_method_with_name_lookup(self.component, "split_position", |lookup| {
+                (lookup.bucket("nft"), amounts)
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Rolls the long position with the given ID into a new long
+    pub fn rollover_long(&mut self, id: NonFungibleLocalId, min_face_value: Decimal) -> TransactionReceipt {
+        self.call_close("rollover_long", self.long_nft, vec![id], min_face_value)
+    }
+
+    /// Rolls the short position with the given ID into a new short, offering `max_deposit`
+    pub fn rollover_short(
+        &mut self,
+        id: NonFungibleLocalId,
+        new_face_value: Decimal,
+        max_deposit: Decimal,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .withdraw_non_fungibles_from_account(self.account, self.short_nft, vec![id])
+            .take_all_from_worktop(self.short_nft, "nft")
+            .withdraw_from_account(self.account, self.base_token, max_deposit)
+            .take_from_worktop(self.base_token, max_deposit, "deposit")
+            .call_method_with_name_lookup(self.component, "rollover_short", |lookup| {
+                (lookup.bucket("nft"), new_face_value, lookup.bucket("deposit"), None::<ComponentAddress>)
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Redeems matured bond (`redeem_bonds`) or yield (`redeem_yield`) tokens
+    pub fn redeem(&mut self, method: &str, token: ResourceAddress, amount: Decimal) -> TransactionReceipt {
+        self.redeem_with(method, token, amount, None)
+    }
+
+    /// Redeems matured bond or yield tokens with an optional referrer
+    pub fn redeem_with(
+        &mut self,
+        method: &str,
+        token: ResourceAddress,
+        amount: Decimal,
+        referrer: Option<ComponentAddress>,
+    ) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .withdraw_from_account(self.account, token, amount)
+            .take_from_worktop(token, amount, "tokens")
+            .call_method_with_name_lookup(self.component, method, |lookup| {
+                (lookup.bucket("tokens"), referrer)
+            })
+            .try_deposit_entire_worktop_or_abort(self.account, None)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Adds liquidity with the given base amount
+    pub fn add_liquidity(&mut self, base_amount: Decimal) -> TransactionReceipt {
+        self.call_with_fungible("add_liquidity", self.base_token, base_amount)
+    }
+
+    /// Removes liquidity with the given amount of LP tokens
+    pub fn remove_liquidity(&mut self, lp_amount: Decimal) -> TransactionReceipt {
+        self.call_with_fungible("remove_liquidity", self.lp_token, lp_amount)
+    }
+
+    /// Pushes a new share price from the yield source as the admin
+    pub fn update_share_price(&mut self, share_price: Decimal) -> TransactionReceipt {
+        let admin_badge = self.admin_badge;
+        self.update_share_price_as(Some(admin_badge), share_price)
+    }
+
+    /// Pushes a new share price presenting `badge`, if any
+    pub fn update_share_price_as(&mut self, badge: Option<ResourceAddress>, share_price: Decimal) -> TransactionReceipt {
+        let mut builder = ManifestBuilder::new().lock_fee_from_faucet();
+        if let Some(badge) = badge {
+            builder = builder.create_proof_from_account_of_amount(self.account, badge, dec!("1"));
+        }
+        let manifest = builder
+            .call_method(self.component, "update_share_price", manifest_args!(share_price))
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Sets the pool mode as the admin
+    pub fn set_mode(&mut self, mode: PoolMode) -> TransactionReceipt {
+        self.call_as_admin("set_mode", manifest_args!(mode))
+    }
+
+    /// Proposes a fee schedule as the admin
+    pub fn propose_fees(&mut self, fees: &FeeSchedule) -> TransactionReceipt {
+        self.call_as_admin(
+            "propose_fees",
+            manifest_args!(fees.clone()),
+        )
+    }
+
+    /// Calls a method with the given arguments, presenting the admin badge
+    pub fn call_as_admin(&mut self, method: &str, args: impl ResolvableArguments) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_amount(self.account, self.admin_badge, dec!("1"))
+            .call_method(self.component, method, args)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Creates a badge held by the test account
+    pub fn new_badge(&mut self) -> ResourceAddress {
+        self.ledger.create_fungible_resource(dec!("1"), 0, self.account)
+    }
+
+    /// Replaces a role's rule on the component as the admin
+    pub fn set_role(&mut self, role: &str, rule: AccessRule) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .create_proof_from_account_of_amount(self.account, self.admin_badge, dec!("1"))
+            .set_role(self.component, ModuleId::Main, role, rule)
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Calls a read-only method and decodes its output
+    pub fn query<T: ScryptoDecode>(&mut self, method: &str) -> T {
+        self.call(method).expect_commit_success().output(1)
+    }
+
+    /// Calls a method that takes no arguments, without presenting any badge
+    pub fn call(&mut self, method: &str) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_method(self.component, method, manifest_args!())
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Values a position NFT through `get_long_value` or `get_short_value`
+    pub fn position_value(&mut self, method: &str, id: NonFungibleLocalId) -> PositionValue {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_method(self.component, method, manifest_args!(id))
+            .build();
+        self.execute(manifest).expect_commit_success().output(1)
+    }
+
+    /// Looks up a position through `get_position_summary`, burned or not
+    pub fn position_summary(&mut self, id: NonFungibleLocalId) -> Option<PositionSummary> {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_method(self.component, "get_position_summary", manifest_args!(id))
+            .build();
+        self.execute(manifest).expect_commit_success().output(1)
+    }
+
+    /// Quotes the curve fee rate of a trade through `quote_curve_fee`
+    pub fn quote_curve_fee(&mut self, trade_size: Decimal) -> CurveFeeQuote {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_method(self.component, "quote_curve_fee", manifest_args!(trade_size))
+            .build();
+        self.execute(manifest).expect_commit_success().output(1)
+    }
+
+    /// Returns the fees accrued to a referrer and not yet claimed
+    pub fn referral_fees(&mut self, referrer: ComponentAddress) -> Decimal {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_method(self.component, "get_referral_fees", manifest_args!(referrer))
+            .build();
+        self.execute(manifest).expect_commit_success().output(1)
+    }
+
+    /// Calls `get_twap_rate` or `get_twap_share_price` over the given window of epochs
+    pub fn twap(&mut self, method: &str, window_epochs: u64) -> TransactionReceipt {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_method(self.component, method, manifest_args!(window_epochs))
+            .build();
+        self.execute(manifest)
+    }
+
+    /// Returns up to `limit` rate observations from `from_time` on
+    pub fn rate_history(&mut self, from_time: u64, limit: u64) -> Vec<RateObservation> {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_method(self.component, "get_rate_history", manifest_args!(from_time, limit))
+            .build();
+        self.execute(manifest).expect_commit_success().output(1)
+    }
+
+    /// Returns the bond token of a checkpoint
+    pub fn bond_resource(&mut self, checkpoint: u64) -> Option<ResourceAddress> {
+        self.checkpoint_resource("get_bond_resource", checkpoint)
+    }
+
+    /// Returns the yield token of a checkpoint
+    pub fn yield_resource(&mut self, checkpoint: u64) -> Option<ResourceAddress> {
+        self.checkpoint_resource("get_yield_resource", checkpoint)
+    }
+
+    fn checkpoint_resource(&mut self, method: &str, checkpoint: u64) -> Option<ResourceAddress> {
+        let manifest = ManifestBuilder::new()
+            .lock_fee_from_faucet()
+            .call_method(self.component, method, manifest_args!(checkpoint))
+            .build();
+        self.execute(manifest).expect_commit_success().output(1)
+    }
+
+    /// Returns the current pool state
+    pub fn pool_state(&mut self) -> PoolState {
+        self.query("get_pool_state")
+    }
+
+    /// Returns the vault reconciliation report
+    pub fn invariants(&mut self) -> InvariantReport {
+        self.query("check_invariants")
+    }
+
+    /// Returns the account balance of a resource
+    pub fn balance(&mut self, resource: ResourceAddress) -> Decimal {
+        self.ledger.get_component_balance(self.account, resource)
+    }
+
+    /// Returns the IDs of all NFTs of a resource held by the account
+    pub fn nft_ids(&mut self, resource: ResourceAddress) -> Vec<NonFungibleLocalId> {
+        let mut ids = Vec::new();
+        for vault_id in self.ledger.get_component_vaults(self.account, resource) {
+            if let Some((_, vault_ids)) = self.ledger.inspect_non_fungible_vault(vault_id) {
+                ids.extend(vault_ids);
+            }
+        }
+        ids
+    }
+
+    /// Returns the NFT data of a position
+    pub fn nft_data<T: NonFungibleData>(
+        &mut self,
+        resource: ResourceAddress,
+        id: &NonFungibleLocalId,
+    ) -> T {
+        self.ledger.get_non_fungible_data(resource, id.clone())
+    }
+
+    /// Asserts that a transaction was rejected with the given panic message
+    pub fn assert_fails_with(receipt: TransactionReceipt, message: &str) {
+        receipt.expect_specific_failure(|error| format!("{:?}", error).contains(message));
+    }
+
+    /// Advances the ledger by a number of epochs (the pool uses the epoch number as time)
+    pub fn advance_time(&mut self, epochs: u64) {
+        let current = self.ledger.get_current_epoch().number();
+        self.ledger.set_current_epoch(Epoch::of(current + epochs));
+    }
+}
@@ -0,0 +1,215 @@
+use proptest::prelude::*;
+use scrypto::prelude::*;
+use influxpool::curves::*;
+use influxpool::fees::*;
+use influxpool::types::FeeSchedule;
+
+/// Absolute tolerance for rounding in 18-decimal fixed point division
+const TOLERANCE: Decimal = dec!("0.000001");
+
+/// Builds a decimal from an integer numerator and denominator
+fn ratio(numerator: u64, denominator: u64) -> Decimal {
+    Decimal::from(numerator) / Decimal::from(denominator)
+}
+
+/// Pool reserves within validated bounds: both positive and a bond price
+/// (z / y) at or below one, i.e. a non-negative fixed rate
+fn reserves() -> impl Strategy<Value = (Decimal, Decimal)> {
+    (1_000u64..1_000_000_000, 0u64..10_000).prop_map(|(z, premium_bps)| {
+        let z = Decimal::from(z);
+        let y = z + z * ratio(premium_bps, 10_000);
+        (z, y)
+    })
+}
+
+/// A trade size strictly between zero and the bond reserves, in basis points of y
+fn trade_bps() -> impl Strategy<Value = u64> {
+    1u64..9_000
+}
+
+/// Time remaining between 0 and 1 inclusive
+fn time_remaining() -> impl Strategy<Value = Decimal> {
+    (0u64..=1_000).prop_map(|t| ratio(t, 1_000))
+}
+
+/// Share price between 1 and 2
+fn share_price() -> impl Strategy<Value = Decimal> {
+    (1_000u64..=2_000).prop_map(|c| ratio(c, 1_000))
+}
+
+/// A fee percentage between 0 and 10%
+fn fee() -> impl Strategy<Value = Decimal> {
+    (0u64..=1_000).prop_map(|f| ratio(f, 10_000))
+}
+
+proptest! {
+    #[test]
+    fn long_round_trip_costs_at_least_fees(
+        (z, y) in reserves(),
+        share_bps in 1u64..5_000,
+        curve_fee in fee(),
+    ) {
+        let share_amount = z * ratio(share_bps, 10_000);
+
+        // Open: buy bonds with shares, paying the curve fee in bonds
+        let face_value = calculate_long_face_value(share_amount, z, y);
+        let spot_price = calculate_spot_price(z, y, Decimal::ONE);
+        let open_fee = calculate_curve_fee(face_value, Decimal::ONE, spot_price, curve_fee);
+        let adjusted_face_value = face_value - open_fee;
+
+        // Close immediately: sell the bonds back into the post-trade pool
+        let z_after = z + share_amount;
+        let y_after = y - face_value;
+        let close_price = calculate_spot_price(z_after, y_after, Decimal::ONE);
+        let close_fee = calculate_curve_fee(adjusted_face_value, Decimal::ONE, close_price, curve_fee);
+        let proceeds = trading_invariant_sell_delta_z(adjusted_face_value, z_after, y_after) - close_fee;
+
+        let cost = share_amount - proceeds;
+        prop_assert!(cost >= close_fee - TOLERANCE, "cost {} below close fee {}", cost, close_fee);
+        prop_assert!(cost >= -TOLERANCE, "round trip made a profit of {}", -cost);
+    }
+
+    #[test]
+    fn long_round_trip_without_fees_breaks_even((z, y) in reserves(), share_bps in 1u64..5_000) {
+        let share_amount = z * ratio(share_bps, 10_000);
+        let face_value = calculate_long_face_value(share_amount, z, y);
+        let proceeds = trading_invariant_sell_delta_z(face_value, z + share_amount, y - face_value);
+
+        prop_assert!(proceeds <= share_amount + TOLERANCE);
+        prop_assert!(share_amount - proceeds <= TOLERANCE);
+    }
+
+    #[test]
+    fn trading_impact_is_monotonic_in_size((z, y) in reserves(), a in trade_bps(), b in trade_bps()) {
+        let (small, large) = if a <= b { (a, b) } else { (b, a) };
+        let small = y * ratio(small, 10_000);
+        let large = y * ratio(large, 10_000);
+
+        prop_assert!(trading_invariant_delta_z(small, z, y) <= trading_invariant_delta_z(large, z, y));
+        prop_assert!(trading_invariant_sell_delta_z(small, z, y) <= trading_invariant_sell_delta_z(large, z, y));
+        prop_assert!(calculate_long_face_value(small, z, y) <= calculate_long_face_value(large, z, y));
+    }
+
+    #[test]
+    fn position_impact_is_monotonic_in_size(
+        (z, y) in reserves(),
+        a in trade_bps(),
+        b in trade_bps(),
+        t in time_remaining(),
+        c in share_price(),
+    ) {
+        let (small, large) = if a <= b { (a, b) } else { (b, a) };
+        let small = y * ratio(small, 10_000);
+        let large = y * ratio(large, 10_000);
+
+        prop_assert!(position_impact_delta_z(small, z, y, t, c) <= position_impact_delta_z(large, z, y, t, c) + TOLERANCE);
+        prop_assert!(position_sale_delta_z(small, z, y, t, c) <= position_sale_delta_z(large, z, y, t, c) + TOLERANCE);
+    }
+
+    #[test]
+    fn time_interpolation_is_bounded(
+        (z, y) in reserves(),
+        bps in trade_bps(),
+        t in time_remaining(),
+        c in share_price(),
+    ) {
+        let delta_y = y * ratio(bps, 10_000);
+        let pure_curve = trading_invariant_delta_z(delta_y, z, y);
+        let pure_sale = trading_invariant_sell_delta_z(delta_y, z, y);
+        let pure_maturity = maturity_pricing_delta_z(delta_y, c);
+        let spot_curve = delta_y * z / y;
+        let matured = (Decimal::ONE - t) * pure_maturity;
+
+        // The buy curve is convex through the origin, so the curve leg costs at most
+        // t of the pure-curve price and at least t of the spot price. The bound by
+        // the larger pure price follows; see `min_price_does_not_bound_interpolation`
+        // for why the smaller one is not a lower bound.
+        let impact = position_impact_delta_z(delta_y, z, y, t, c);
+        prop_assert!(impact <= t * pure_curve + matured + TOLERANCE);
+        prop_assert!(impact >= t * spot_curve + matured - TOLERANCE);
+        prop_assert!(impact <= pure_curve.max(pure_maturity) + TOLERANCE);
+
+        // The sell curve is concave through the origin, which flips both bounds
+        let sale = position_sale_delta_z(delta_y, z, y, t, c);
+        prop_assert!(sale >= t * pure_sale + matured - TOLERANCE);
+        prop_assert!(sale <= t * spot_curve + matured + TOLERANCE);
+        prop_assert!(sale >= pure_sale.min(pure_maturity) - TOLERANCE);
+    }
+
+    #[test]
+    fn time_interpolation_matches_endpoints((z, y) in reserves(), bps in trade_bps(), c in share_price()) {
+        let delta_y = y * ratio(bps, 10_000);
+
+        prop_assert_eq!(position_impact_delta_z(delta_y, z, y, Decimal::ONE, c), trading_invariant_delta_z(delta_y, z, y));
+        prop_assert_eq!(position_impact_delta_z(delta_y, z, y, Decimal::ZERO, c), maturity_pricing_delta_z(delta_y, c));
+        prop_assert_eq!(position_sale_delta_z(delta_y, z, y, Decimal::ONE, c), trading_invariant_sell_delta_z(delta_y, z, y));
+    }
+
+    #[test]
+    fn outputs_are_never_negative(
+        (z, y) in reserves(),
+        bps in trade_bps(),
+        t in time_remaining(),
+        c in share_price(),
+        curve_fee in fee(),
+        flat_fee in fee(),
+    ) {
+        let delta_y = y * ratio(bps, 10_000);
+        validate_trading_parameters(z, y, delta_y);
+
+        prop_assert!(trading_invariant_delta_z(delta_y, z, y) >= Decimal::ZERO);
+        prop_assert!(trading_invariant_sell_delta_z(delta_y, z, y) >= Decimal::ZERO);
+        prop_assert!(maturity_pricing_delta_z(delta_y, c) >= Decimal::ZERO);
+        prop_assert!(position_impact_delta_z(delta_y, z, y, t, c) >= Decimal::ZERO);
+        prop_assert!(position_sale_delta_z(delta_y, z, y, t, c) >= Decimal::ZERO);
+        // Bonds cost c * z / y in base, so scale the bond reserves to keep that at or below one
+        prop_assert!(calculate_short_deposit(delta_y, z, y * c, c, t) >= -TOLERANCE);
+
+        let face_value = calculate_long_face_value(delta_y, z, y);
+        prop_assert!(face_value >= Decimal::ZERO && face_value < y);
+
+        let fees = FeeSchedule { curve_fee, flat_fee, ..FeeSchedule::default() };
+        let trade_fees = calculate_trade_fees(delta_y, t, calculate_spot_price(z, y, Decimal::ONE), &fees);
+        prop_assert!(trade_fees.curve_fee >= Decimal::ZERO);
+        prop_assert!(trade_fees.flat_fee >= Decimal::ZERO);
+    }
+
+    #[test]
+    fn curve_fee_is_bounded_by_its_rate(
+        (z, y) in reserves(),
+        bps in trade_bps(),
+        t in time_remaining(),
+        c in share_price(),
+        curve_fee in fee(),
+    ) {
+        let delta_y = y * ratio(bps, 10_000);
+        let spot_price = calculate_spot_price(z, y, c);
+        prop_assert!(spot_price > Decimal::ZERO && spot_price <= Decimal::ONE);
+
+        // The fee is a share of the discount, never of the face value itself
+        let charged = calculate_curve_fee(delta_y, t, spot_price, curve_fee);
+        prop_assert!(charged <= curve_fee * delta_y * t);
+    }
+}
+
+/// Interpolating over time remaining prices the curve leg on Δy·tr bonds, not on Δy
+/// bonds scaled by tr. On a convex curve that leg is cheaper than tr of the pure-curve
+/// price, so a position can cost less than both pure prices (and a sale can pay more
+/// than both). The exact bounds are checked in `time_interpolation_is_bounded`.
+#[test]
+fn min_price_does_not_bound_interpolation() {
+    let (z, y, delta_y, t) = (dec!("1000"), dec!("1100"), dec!("100"), dec!("0.5"));
+
+    // I(100) = 1000 · 1100 / 1000 - 1000 = 100 = M(100), but I(50) + M(50) ≈ 97.62
+    let impact = position_impact_delta_z(delta_y, z, y, t, Decimal::ONE);
+    assert_eq!(trading_invariant_delta_z(delta_y, z, y), dec!("100"));
+    assert_eq!(maturity_pricing_delta_z(delta_y, Decimal::ONE), dec!("100"));
+    assert!(impact < dec!("97.62") && impact > dec!("97.61"));
+
+    // S(100) = 1000 - 1100000 / 1200 ≈ 83.33 = M(100) at c = 1.2, but S(50) + M(50) ≈ 85.14
+    let c = dec!("1.2");
+    let sale = position_sale_delta_z(delta_y, z, y, t, c);
+    let pure_sale = trading_invariant_sell_delta_z(delta_y, z, y);
+    assert!((pure_sale - maturity_pricing_delta_z(delta_y, c)).checked_abs().unwrap() < TOLERANCE);
+    assert!(sale > pure_sale + dec!("1.8"));
+}
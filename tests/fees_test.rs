@@ -0,0 +1,179 @@
+use scrypto::prelude::*;
+use influxpool::fees::*;
+use influxpool::types::*;
+
+// Hand-computed vectors for the fee formulas in `influxpool::fees`:
+//
+//   p                     = min(c·ze / y, 1)
+//   curve fee             = ϕc · (1 - p) · Δy · tr
+//   flat fee              = ϕf · Δy · (1 - tr)
+//   governance curve fee  = ϕg,c · curve fee
+//   governance flat fee   = ϕg,f · flat fee
+
+fn schedule(curve_fee: Decimal, flat_fee: Decimal, governance_curve_fee: Decimal, governance_flat_fee: Decimal) -> FeeSchedule {
+    FeeSchedule {
+        curve_fee,
+        flat_fee,
+        governance_curve_fee,
+        governance_flat_fee,
+        zombie_governance_fee: Decimal::ZERO,
+    }
+}
+
+#[test]
+fn test_spot_price_vectors() {
+    // 900 / 1000 = 0.9
+    assert_eq!(calculate_spot_price(dec!("900"), dec!("1000"), Decimal::ONE), dec!("0.9"));
+    // 800 * 1.25 = 1000; 1000 / 1050 of a pool seeded at 5% over one term
+    assert_eq!(calculate_spot_price(dec!("800"), dec!("1050"), dec!("1.25")), dec!("1000") / dec!("1050"));
+    // Bonds never cost more than their face value
+    assert_eq!(calculate_spot_price(dec!("900"), dec!("100"), Decimal::ONE), Decimal::ONE);
+    // Without both reserves the curve trades one to one
+    assert_eq!(calculate_spot_price(dec!("900"), Decimal::ZERO, Decimal::ONE), Decimal::ONE);
+    assert_eq!(calculate_spot_price(Decimal::ZERO, dec!("100"), Decimal::ONE), Decimal::ONE);
+}
+
+#[test]
+fn test_curve_fee_uses_spot_price_not_rate() {
+    // 0.01 * (1 - 0.9) * 1000 * 1 = 1, where pricing with the -0.1 spot rate
+    // would have charged 0.01 * (1 + 0.1) * 1000 = 11
+    assert_eq!(calculate_curve_fee(dec!("1000"), Decimal::ONE, dec!("0.9"), dec!("0.01")), dec!("1"));
+
+    // Only the unmatured part pays: 0.01 * 0.1 * 1000 * 0.25 = 0.25
+    assert_eq!(calculate_curve_fee(dec!("1000"), dec!("0.25"), dec!("0.9"), dec!("0.01")), dec!("0.25"));
+
+    // Bonds priced at one carry no discount to charge on
+    assert_eq!(calculate_curve_fee(dec!("1000"), Decimal::ONE, Decimal::ONE, dec!("0.01")), Decimal::ZERO);
+}
+
+#[test]
+fn test_flat_fee_vectors() {
+    // 0.005 * 1000 * (1 - 0.25) = 3.75
+    assert_eq!(calculate_flat_fee(dec!("1000"), dec!("0.25"), dec!("0.005")), dec!("3.75"));
+    // Fully matured: 0.005 * 1000 = 5
+    assert_eq!(calculate_flat_fee(dec!("1000"), Decimal::ZERO, dec!("0.005")), dec!("5"));
+    // New bonds pay no flat fee
+    assert_eq!(calculate_flat_fee(dec!("1000"), Decimal::ONE, dec!("0.005")), Decimal::ZERO);
+}
+
+#[test]
+fn test_trade_fees_split_governance_per_fee() {
+    let fees = schedule(dec!("0.02"), dec!("0.01"), dec!("0.1"), dec!("0.5"));
+
+    // Δy = 2000, tr = 0.5, p = 0.8
+    let trade_fees = calculate_trade_fees(dec!("2000"), dec!("0.5"), dec!("0.8"), &fees);
+
+    // curve: 0.02 * 0.2 * 2000 * 0.5 = 4; flat: 0.01 * 2000 * 0.5 = 10
+    assert_eq!(trade_fees.curve_fee, dec!("4"));
+    assert_eq!(trade_fees.flat_fee, dec!("10"));
+    // governance: 0.1 * 4 = 0.4 and 0.5 * 10 = 5
+    assert_eq!(trade_fees.governance_curve_fee, dec!("0.4"));
+    assert_eq!(trade_fees.governance_flat_fee, dec!("5"));
+
+    assert_eq!(trade_fees.total(), dec!("14"));
+    assert_eq!(trade_fees.governance(), dec!("5.4"));
+    assert_eq!(trade_fees.lp(), dec!("8.6"));
+    assert_eq!(trade_fees.lp_flat(), dec!("5"));
+}
+
+#[test]
+fn test_default_schedule_charges_nothing() {
+    let trade_fees = calculate_trade_fees(dec!("1000"), dec!("0.5"), dec!("0.9"), &FeeSchedule::default());
+    assert_eq!(trade_fees.total(), Decimal::ZERO);
+    assert_eq!(trade_fees.governance(), Decimal::ZERO);
+}
+
+// Dynamic curve fee:
+//
+//   ϕc' = clamp(ϕc · (1 + ks · size + ku · utilization + kv · σ), ϕmin, ϕmax)
+
+fn dynamic_config() -> DynamicFeeConfig {
+    DynamicFeeConfig {
+        size_multiplier: dec!("2"),
+        utilization_multiplier: dec!("1"),
+        volatility_multiplier: dec!("100"),
+        min_curve_fee: dec!("0.005"),
+        max_curve_fee: dec!("0.05"),
+        volatility_window: 4,
+    }
+}
+
+fn checkpoint(start_time: u64, share_price: Decimal) -> Checkpoint {
+    Checkpoint {
+        start_time,
+        share_price,
+        long_positions: Decimal::ZERO,
+        short_positions: Decimal::ZERO,
+        avg_long_maturity: Decimal::ZERO,
+        avg_short_maturity: Decimal::ZERO,
+        is_minted: true,
+    }
+}
+
+#[test]
+fn test_reserve_ratio_vectors() {
+    // 1000 / (8000 * 1.25) = 0.1
+    assert_eq!(calculate_reserve_ratio(dec!("1000"), dec!("8000"), dec!("1.25")), dec!("0.1"));
+    // An empty pool has no ratio to scale by
+    assert_eq!(calculate_reserve_ratio(dec!("1000"), Decimal::ZERO, Decimal::ONE), Decimal::ZERO);
+}
+
+#[test]
+fn test_dynamic_curve_fee_vectors() {
+    let config = dynamic_config();
+
+    // 0.01 * (1 + 2 * 0.1 + 1 * 0.3 + 100 * 0.001) = 0.016
+    assert_eq!(
+        calculate_dynamic_curve_fee(&config, dec!("0.01"), dec!("0.1"), dec!("0.3"), dec!("0.001")),
+        dec!("0.016")
+    );
+    // 0.01 * (1 + 2 * 5) = 0.11, capped at 0.05
+    assert_eq!(
+        calculate_dynamic_curve_fee(&config, dec!("0.01"), dec!("5"), Decimal::ZERO, Decimal::ZERO),
+        dec!("0.05")
+    );
+    // A zero base fee is lifted to the minimum
+    assert_eq!(
+        calculate_dynamic_curve_fee(&config, Decimal::ZERO, dec!("0.1"), Decimal::ZERO, Decimal::ZERO),
+        dec!("0.005")
+    );
+}
+
+#[test]
+fn test_quote_curve_fee_by_mode() {
+    // size = 1000 / 10000 = 0.1, utilization = 3000 / 10000 = 0.3
+    let fixed = quote_curve_fee(&FeeMode::Fixed, dec!("0.01"), dec!("1000"), dec!("10000"), Decimal::ONE, dec!("3000"), dec!("0.001"));
+    assert_eq!(fixed.size, dec!("0.1"));
+    assert_eq!(fixed.utilization, dec!("0.3"));
+    assert_eq!(fixed.curve_fee, dec!("0.01"));
+
+    let dynamic = FeeMode::Dynamic(dynamic_config());
+    let quote = quote_curve_fee(&dynamic, dec!("0.01"), dec!("1000"), dec!("10000"), Decimal::ONE, dec!("3000"), dec!("0.001"));
+    assert_eq!(quote.base_curve_fee, dec!("0.01"));
+    assert_eq!(quote.curve_fee, dec!("0.016"));
+
+    let fees = trade_fee_schedule(&schedule(dec!("0.01"), dec!("0.005"), dec!("0.1"), dec!("0.1")), &quote);
+    assert_eq!(fees.curve_fee, dec!("0.016"));
+    assert_eq!(fees.flat_fee, dec!("0.005"));
+}
+
+#[test]
+fn test_rate_volatility_vectors() {
+    let mut checkpoints = HashMap::new();
+    // Per-checkpoint rates 0.01, 0.03, 0.01: mean 0.05 / 3, mean absolute deviation 0.08 / 9
+    for (i, share_price) in [dec!("1"), dec!("1.01"), dec!("1.0403"), dec!("1.050703")].into_iter().enumerate() {
+        checkpoints.insert(i as u64 * 10, checkpoint(i as u64 * 10, share_price));
+    }
+    let volatility = calculate_rate_volatility(&checkpoints, 30, 10, 4);
+    assert!((volatility - dec!("0.08") / dec!("9")).checked_abs().unwrap() <= dec!("0.000000001"));
+
+    // A steady rate has no volatility
+    let mut steady = HashMap::new();
+    for (i, share_price) in [dec!("1"), dec!("1.01"), dec!("1.0201"), dec!("1.030301")].into_iter().enumerate() {
+        steady.insert(i as u64 * 10, checkpoint(i as u64 * 10, share_price));
+    }
+    assert!(calculate_rate_volatility(&steady, 30, 10, 4) <= dec!("0.000000001"));
+
+    // One rate is not enough to measure
+    assert_eq!(calculate_rate_volatility(&checkpoints, 10, 10, 4), Decimal::ZERO);
+}
@@ -0,0 +1,1055 @@
+mod common;
+
+use scrypto_test::prelude::*;
+use influxpool::curves::calculate_bond_reserves_for_rate;
+use influxpool::types::*;
+use common::*;
+
+#[test]
+fn test_create_pool_mints_initial_lp_tokens() {
+    let mut env = TestEnv::new();
+
+    // Initial LP tokens exclude the minimum share reserves
+    assert_eq!(env.balance(env.lp_token), INITIAL_LIQUIDITY - MIN_SHARE_RESERVES);
+
+    let state = env.pool_state();
+    assert_eq!(state.share_reserves, INITIAL_LIQUIDITY - MIN_SHARE_RESERVES);
+    assert_eq!(
+        state.bond_reserves,
+        calculate_bond_reserves_for_rate(state.share_reserves, Decimal::ONE, INITIAL_FIXED_RATE, POSITION_DURATION)
+    );
+    assert_eq!(state.share_price, Decimal::ONE);
+}
+
+#[test]
+fn test_create_pool_rejects_negative_fixed_rate() {
+    let mut env = TestEnv::without_pool();
+    let receipt = env.create_pool_at_rate(default_fees(), FeeMode::Fixed, dec!("-0.01"), INITIAL_LIQUIDITY);
+    TestEnv::assert_fails_with(receipt, "Initial fixed rate cannot be negative");
+}
+
+#[test]
+fn test_create_pool_twice_fails() {
+    let mut env = TestEnv::new();
+    let receipt = env.create_pool(default_fees(), INITIAL_LIQUIDITY);
+    TestEnv::assert_fails_with(receipt, "Pool already initialized");
+}
+
+#[test]
+fn test_create_pool_rejects_invalid_fee() {
+    let mut env = TestEnv::without_pool();
+    let fees = FeeSchedule { curve_fee: dec!("0.15"), ..default_fees() };
+    let receipt = env.create_pool(fees, INITIAL_LIQUIDITY);
+    TestEnv::assert_fails_with(receipt, "Curve fee must be between 0 and 0.1");
+}
+
+#[test]
+fn test_create_pool_rejects_invalid_dynamic_fee() {
+    let mut env = TestEnv::without_pool();
+    let config = DynamicFeeConfig { min_curve_fee: dec!("0.06"), ..dynamic_fee_config() };
+    let receipt = env.create_pool_with_mode(default_fees(), FeeMode::Dynamic(config), INITIAL_LIQUIDITY);
+    TestEnv::assert_fails_with(receipt, "Minimum dynamic curve fee must be between 0 and the maximum");
+
+    let config = DynamicFeeConfig { max_curve_fee: dec!("0.2"), ..dynamic_fee_config() };
+    let receipt = env.create_pool_with_mode(default_fees(), FeeMode::Dynamic(config), INITIAL_LIQUIDITY);
+    TestEnv::assert_fails_with(receipt, "Maximum dynamic curve fee must be between 0 and 0.1");
+}
+
+#[test]
+fn test_create_pool_rejects_liquidity_below_minimum() {
+    let mut env = TestEnv::without_pool();
+    let receipt = env.create_pool(default_fees(), dec!("500"));
+    TestEnv::assert_fails_with(receipt, "Liquidity amount must be at least 1000");
+}
+
+#[test]
+fn test_methods_require_initialized_pool() {
+    let mut env = TestEnv::without_pool();
+    let base_token = env.base_token;
+    let receipt = env.open_long_with(base_token, dec!("100"), None);
+    TestEnv::assert_fails_with(receipt, "Pool not initialized");
+}
+
+#[test]
+fn test_open_long_mints_position_nft() {
+    let mut env = TestEnv::new();
+    let balance_before = env.balance(env.base_token);
+
+    env.open_long(dec!("1000")).expect_commit_success();
+
+    assert_eq!(env.balance(env.base_token), balance_before - dec!("1000"));
+
+    let ids = env.nft_ids(env.long_nft);
+    assert_eq!(ids.len(), 1);
+
+    // Bonds trade at a discount on the seeded curve, so the base buys more face value
+    let position: LongPosition = env.nft_data(env.long_nft, &ids[0]);
+    assert!(position.face_value > dec!("1000"));
+    assert_eq!(position.checkpoint, 0);
+    assert_eq!(position.maturity_time, POSITION_DURATION);
+
+    // Cost basis is the full base paid, fees included
+    assert_eq!(position.base_paid, dec!("1000"));
+    assert_eq!(position.shares_paid, dec!("1000"));
+    assert!(position.lp_fee_paid > Decimal::ZERO);
+    assert!(position.governance_fee_paid > Decimal::ZERO);
+    assert!(position.fixed_rate_locked > Decimal::ZERO);
+    assert_eq!(position.pool_id, 1);
+
+    // Wallet display fields encode side, face value and maturity epoch
+    let face = display_face(position.face_value);
+    assert_eq!(position.name, format!("Long {} maturing at epoch {}", face, position.maturity_time));
+    assert_eq!(
+        position.key_image_url,
+        Url::of(format!("{}?side=long&face={}&maturity_epoch={}", IMAGE_BASE_URL, face, position.maturity_time))
+    );
+}
+
+#[test]
+fn test_open_long_rejects_wrong_token() {
+    let mut env = TestEnv::new();
+    let lp_token = env.lp_token;
+    let receipt = env.open_long_with(lp_token, dec!("100"), None);
+    TestEnv::assert_fails_with(receipt, "Invalid yield source resource address");
+}
+
+#[test]
+fn test_close_long_before_maturity() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let balance_before = env.balance(env.base_token);
+
+    env.advance_time(CHECKPOINT_DURATION / 2);
+    env.close_long(id).expect_commit_success();
+
+    assert!(env.nft_ids(env.long_nft).is_empty());
+    assert!(env.balance(env.base_token) > balance_before);
+}
+
+#[test]
+fn test_close_long_at_maturity_pays_face_value_less_fee() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let face_value = env.nft_data::<LongPosition>(env.long_nft, &id).face_value;
+    let balance_before = env.balance(env.base_token);
+
+    env.advance_time(POSITION_DURATION + CHECKPOINT_DURATION);
+    env.close_long(id).expect_commit_success();
+
+    // Fully matured: the face value less the 0.5% flat fee
+    assert_close(env.balance(env.base_token), balance_before + face_value * dec!("0.995"));
+}
+
+#[test]
+fn test_partial_close_long_keeps_remainder() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let face_value = env.nft_data::<LongPosition>(env.long_nft, &id).face_value;
+    let balance_before = env.balance(env.base_token);
+
+    env.advance_time(CHECKPOINT_DURATION / 2);
+    env.partial_close_long(id.clone(), dec!("400")).expect_commit_success();
+
+    let position: LongPosition = env.nft_data(env.long_nft, &id);
+    assert_eq!(position.face_value, face_value - dec!("400"));
+    assert_eq!(position.base_paid, dec!("1000") * position.face_value / face_value);
+    assert_eq!(position.name, format!("Long {} maturing at epoch {}", display_face(position.face_value), position.maturity_time));
+    assert!(env.balance(env.base_token) > balance_before);
+    assert!(env.invariants().is_consistent);
+
+    env.close_long(id).expect_commit_success();
+    assert!(env.nft_ids(env.long_nft).is_empty());
+}
+
+#[test]
+fn test_partial_close_rejects_excess_bond_amount() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+
+    let receipt = env.partial_close_long(id.clone(), dec!("5000"));
+    TestEnv::assert_fails_with(receipt, "Bond amount exceeds position face value");
+
+    let receipt = env.partial_close_long(id, Decimal::ZERO);
+    TestEnv::assert_fails_with(receipt, "Bond amount must be positive");
+}
+
+#[test]
+fn test_close_long_rejects_multiple_nfts() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let ids = env.nft_ids(env.long_nft);
+    let long_nft = env.long_nft;
+
+    let receipt = env.call_close("close_long", long_nft, ids, dec!("1"));
+    TestEnv::assert_fails_with(receipt, "Can only process one long position at a time");
+}
+
+#[test]
+fn test_close_long_rejects_short_nft() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    let ids = env.nft_ids(env.short_nft);
+    let short_nft = env.short_nft;
+
+    let receipt = env.call_close("close_long", short_nft, ids, dec!("1"));
+    TestEnv::assert_fails_with(receipt, "Invalid long position resource address");
+}
+
+#[test]
+fn test_close_longs_across_checkpoints() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    env.open_long(dec!("500")).expect_commit_success();
+    env.advance_time(CHECKPOINT_DURATION);
+    env.open_long(dec!("750")).expect_commit_success();
+    let ids = env.nft_ids(env.long_nft);
+    let balance_before = env.balance(env.base_token);
+
+    env.advance_time(CHECKPOINT_DURATION);
+    env.close_longs(ids).expect_commit_success();
+
+    assert!(env.nft_ids(env.long_nft).is_empty());
+    assert!(env.balance(env.base_token) > balance_before);
+    assert!(env.invariants().is_consistent);
+}
+
+#[test]
+fn test_close_shorts_in_one_call() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    env.open_short(dec!("2000"), dec!("500")).expect_commit_success();
+    let ids = env.nft_ids(env.short_nft);
+
+    env.advance_time(CHECKPOINT_DURATION);
+    env.close_shorts(ids).expect_commit_success();
+
+    assert!(env.nft_ids(env.short_nft).is_empty());
+    assert!(env.invariants().is_consistent);
+}
+
+#[test]
+fn test_merge_and_split_long_positions() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    env.open_long(dec!("500")).expect_commit_success();
+    let ids = env.nft_ids(env.long_nft);
+    let total_face = ids.iter()
+        .map(|id| env.nft_data::<LongPosition>(env.long_nft, id).face_value)
+        .fold(Decimal::ZERO, |total, face| total + face);
+    let state_before = env.pool_state();
+    let long_nft = env.long_nft;
+
+    env.merge_positions(long_nft, ids).expect_commit_success();
+    let merged = env.nft_ids(long_nft);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(env.nft_data::<LongPosition>(long_nft, &merged[0]).face_value, total_face);
+
+    let amounts = vec![dec!("100"), total_face - dec!("100")];
+    env.split_position(long_nft, merged[0].clone(), amounts).expect_commit_success();
+    assert_eq!(env.nft_ids(long_nft).len(), 2);
+
+    let state_after = env.pool_state();
+    assert_eq!(state_after.share_reserves, state_before.share_reserves);
+    assert_eq!(state_after.bond_reserves, state_before.bond_reserves);
+}
+
+#[test]
+fn test_merge_rejects_different_checkpoints() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    env.advance_time(CHECKPOINT_DURATION);
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    let ids = env.nft_ids(env.short_nft);
+    let short_nft = env.short_nft;
+
+    let receipt = env.merge_positions(short_nft, ids);
+    TestEnv::assert_fails_with(receipt, "Positions must share a checkpoint to be merged");
+}
+
+#[test]
+fn test_split_rejects_amounts_not_matching_face_value() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.short_nft)[0].clone();
+    let short_nft = env.short_nft;
+
+    let receipt = env.split_position(short_nft, id, vec![dec!("600"), dec!("600")]);
+    TestEnv::assert_fails_with(receipt, "Split amounts must sum to the position face value");
+}
+
+#[test]
+fn test_tokenize_untokenize_and_redeem_bonds() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let position: LongPosition = env.nft_data(env.long_nft, &id);
+    let long_nft = env.long_nft;
+
+    env.call_with_nfts("tokenize_long", long_nft, vec![id]).expect_commit_success();
+    let bond_token = env.bond_resource(position.checkpoint).unwrap();
+    assert_eq!(env.balance(bond_token), position.face_value);
+    assert!(env.nft_ids(long_nft).is_empty());
+
+    // Round trip back into an NFT and out again
+    env.call_with_fungible("untokenize", bond_token, dec!("400")).expect_commit_success();
+    let restored = env.nft_ids(long_nft);
+    assert_eq!(env.nft_data::<LongPosition>(long_nft, &restored[0]).face_value, dec!("400"));
+    env.call_with_nfts("tokenize_long", long_nft, restored).expect_commit_success();
+
+    let receipt = env.redeem("redeem_bonds", bond_token, position.face_value);
+    TestEnv::assert_fails_with(receipt, "Bonds have not matured");
+
+    env.advance_time(POSITION_DURATION + CHECKPOINT_DURATION);
+    let balance_before = env.balance(env.base_token);
+    env.redeem("redeem_bonds", bond_token, position.face_value).expect_commit_success();
+
+    assert_eq!(env.balance(bond_token), Decimal::ZERO);
+    // Same payout as closing the matured long: face value less the 0.5% flat fee
+    assert_close(env.balance(env.base_token), balance_before + position.face_value * dec!("0.995"));
+    assert!(env.invariants().is_consistent);
+}
+
+#[test]
+fn test_tokenize_short_and_redeem_yield() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.short_nft)[0].clone();
+    let position: ShortPosition = env.nft_data(env.short_nft, &id);
+    let short_nft = env.short_nft;
+
+    env.call_with_nfts("tokenize_short", short_nft, vec![id]).expect_commit_success();
+    let yield_token = env.yield_resource(position.checkpoint).unwrap();
+    assert_eq!(env.balance(yield_token), dec!("1000"));
+    assert!(env.invariants().is_consistent);
+
+    let receipt = env.redeem("redeem_yield", yield_token, dec!("1000"));
+    TestEnv::assert_fails_with(receipt, "Yield tokens have not matured");
+
+    // 5% variable yield over the term, less the 0.5% flat fee on face value
+    env.advance_time(POSITION_DURATION);
+    env.update_share_price(dec!("1.05")).expect_commit_success();
+    let balance_before = env.balance(env.base_token);
+    env.redeem("redeem_yield", yield_token, dec!("1000")).expect_commit_success();
+
+    assert_eq!(env.balance(env.base_token), balance_before + dec!("45"));
+    assert!(env.invariants().is_consistent);
+}
+
+#[test]
+fn test_yield_redemptions_share_a_skipped_maturity_price() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    let short_nft = env.short_nft;
+    for id in env.nft_ids(short_nft) {
+        env.call_with_nfts("tokenize_short", short_nft, vec![id]).expect_commit_success();
+    }
+    let yield_token = env.yield_resource(0).unwrap();
+
+    // Nothing touches the pool's checkpoints at maturity, so the first redemption
+    // mints the maturity price at 1.05 and the second is paid at it too
+    env.advance_time(POSITION_DURATION + CHECKPOINT_DURATION);
+    env.update_share_price(dec!("1.05")).expect_commit_success();
+    let balance_before = env.balance(env.base_token);
+    env.redeem("redeem_yield", yield_token, dec!("1000")).expect_commit_success();
+    assert_eq!(env.balance(env.base_token), balance_before + dec!("45"));
+
+    env.update_share_price(dec!("1.1")).expect_commit_success();
+    let balance_before = env.balance(env.base_token);
+    env.redeem("redeem_yield", yield_token, dec!("1000")).expect_commit_success();
+    assert_eq!(env.balance(env.base_token), balance_before + dec!("45"));
+    assert!(env.invariants().is_consistent);
+}
+
+#[test]
+fn test_rollover_long_into_new_term() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let position: LongPosition = env.nft_data(env.long_nft, &id);
+
+    let receipt = env.rollover_long(id.clone(), Decimal::ZERO);
+    TestEnv::assert_fails_with(receipt, "Position must be matured to roll over");
+    env.advance_time(POSITION_DURATION - 1);
+    let receipt = env.rollover_long(id.clone(), Decimal::ZERO);
+    TestEnv::assert_fails_with(receipt, "Position must be matured to roll over");
+
+    env.advance_time(1);
+    let balance_before = env.balance(env.base_token);
+    let receipt = env.rollover_long(id.clone(), position.face_value * dec!("2"));
+    TestEnv::assert_fails_with(receipt, "Rollover face value below minimum");
+    env.rollover_long(id, dec!("980")).expect_commit_success();
+
+    // The settled face value is reinvested at a discount, so the new term holds more bonds
+    let ids = env.nft_ids(env.long_nft);
+    assert_eq!(ids.len(), 1);
+    let rolled: LongPosition = env.nft_data(env.long_nft, &ids[0]);
+    assert!(rolled.face_value > position.face_value);
+    assert_eq!(rolled.maturity_time, rolled.checkpoint + POSITION_DURATION);
+    assert_eq!(env.balance(env.base_token), balance_before);
+    assert!(env.invariants().is_consistent);
+}
+
+#[test]
+fn test_rollover_short_reuses_interest_as_deposit() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.short_nft)[0].clone();
+
+    let receipt = env.rollover_short(id.clone(), dec!("1000"), dec!("2000"));
+    TestEnv::assert_fails_with(receipt, "Position must be matured to roll over");
+    env.advance_time(POSITION_DURATION - 1);
+    let receipt = env.rollover_short(id.clone(), dec!("1000"), dec!("2000"));
+    TestEnv::assert_fails_with(receipt, "Position must be matured to roll over");
+
+    // 5% variable yield over the term settles into the new deposit
+    env.advance_time(1);
+    env.update_share_price(dec!("1.05")).expect_commit_success();
+    let balance_before = env.balance(env.base_token);
+    env.rollover_short(id, dec!("1000"), dec!("2000")).expect_commit_success();
+    let spent = balance_before - env.balance(env.base_token);
+
+    let ids = env.nft_ids(env.short_nft);
+    assert_eq!(ids.len(), 1);
+    let rolled: ShortPosition = env.nft_data(env.short_nft, &ids[0]);
+    assert_eq!(rolled.face_value, dec!("1000"));
+    assert_eq!(rolled.initial_share_price, dec!("1.05"));
+    // Only the top-up beyond the settled interest is kept; the rest comes back as change
+    assert!(spent < dec!("2000"));
+    assert!(env.invariants().is_consistent);
+}
+
+#[test]
+fn test_long_value_matches_close_proceeds() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let face_value = env.nft_data::<LongPosition>(env.long_nft, &id).face_value;
+
+    env.advance_time(CHECKPOINT_DURATION * 10);
+    let value = env.position_value("get_long_value", id.clone());
+    assert_eq!(value.face_value, face_value);
+    assert_eq!(value.cost_basis, dec!("1000"));
+    assert_eq!(value.pnl, value.close_proceeds - dec!("1000"));
+    assert_eq!(value.accrued_interest, Decimal::ZERO);
+    assert!(value.time_remaining > Decimal::ZERO && value.time_remaining < Decimal::ONE);
+
+    let balance_before = env.balance(env.base_token);
+    env.close_long(id).expect_commit_success();
+    assert_eq!(env.balance(env.base_token), balance_before + value.close_proceeds);
+}
+
+#[test]
+fn test_short_value_reports_accrued_interest() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.short_nft)[0].clone();
+
+    env.advance_time(CHECKPOINT_DURATION * 2);
+    env.update_share_price(dec!("1.01")).expect_commit_success();
+    let value = env.position_value("get_short_value", id.clone());
+    assert_eq!(value.accrued_interest, dec!("10"));
+
+    let balance_before = env.balance(env.base_token);
+    env.close_short(id).expect_commit_success();
+    assert_eq!(env.balance(env.base_token), balance_before + value.close_proceeds);
+}
+
+#[test]
+fn test_position_ids_are_sequential_across_sides() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+
+    assert_eq!(env.nft_ids(env.long_nft), vec![NonFungibleLocalId::integer(1)]);
+    assert_eq!(env.nft_ids(env.short_nft), vec![NonFungibleLocalId::integer(2)]);
+    assert_eq!(env.query::<u64>("get_position_count"), 2);
+}
+
+#[test]
+fn test_position_summary_survives_burn() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = NonFungibleLocalId::integer(1);
+    let face_value = env.nft_data::<LongPosition>(env.long_nft, &id).face_value;
+
+    let summary = env.position_summary(id.clone()).unwrap();
+    assert_eq!(summary.side, PositionSide::Long);
+    assert_eq!(summary.face_value, face_value);
+    assert_eq!(summary.base_paid, dec!("1000"));
+    assert_eq!(summary.burned_time, None);
+
+    env.advance_time(CHECKPOINT_DURATION);
+    env.close_long(id.clone()).expect_commit_success();
+    let summary = env.position_summary(id).unwrap();
+    assert_eq!(summary.face_value, face_value);
+    assert!(summary.burned_time.is_some());
+    assert_eq!(env.position_summary(NonFungibleLocalId::integer(2)), None);
+}
+
+#[test]
+fn test_open_short_returns_change() {
+    let mut env = TestEnv::new();
+    let balance_before = env.balance(env.base_token);
+
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+
+    let ids = env.nft_ids(env.short_nft);
+    assert_eq!(ids.len(), 1);
+
+    let position: ShortPosition = env.nft_data(env.short_nft, &ids[0]);
+    assert_eq!(position.face_value, dec!("1000"));
+    assert_eq!(position.initial_share_price, Decimal::ONE);
+    assert_eq!(position.maturity_time, POSITION_DURATION);
+
+    // Only the required deposit is kept; the rest comes back as change
+    let spent = balance_before - env.balance(env.base_token);
+    assert!(spent > Decimal::ZERO && spent < dec!("2000"));
+}
+
+#[test]
+fn test_open_short_rejects_zero_face_value() {
+    let mut env = TestEnv::new();
+    let receipt = env.open_short(dec!("100"), Decimal::ZERO);
+    TestEnv::assert_fails_with(receipt, "Face value must be positive");
+}
+
+#[test]
+fn test_open_short_rejects_insufficient_deposit() {
+    let mut env = TestEnv::new();
+    let receipt = env.open_short(dec!("1"), dec!("1000"));
+    TestEnv::assert_fails_with(receipt, "Insufficient deposit for short position");
+}
+
+#[test]
+fn test_close_short_after_share_price_growth() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.short_nft)[0].clone();
+    let balance_before = env.balance(env.base_token);
+
+    env.advance_time(CHECKPOINT_DURATION * 2);
+    env.update_share_price(dec!("1.01")).expect_commit_success();
+    env.close_short(id).expect_commit_success();
+
+    assert!(env.nft_ids(env.short_nft).is_empty());
+    assert!(env.balance(env.base_token) > balance_before);
+}
+
+#[test]
+fn test_partial_close_short_keeps_remainder() {
+    let mut env = TestEnv::new();
+    env.open_short(dec!("2000"), dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.short_nft)[0].clone();
+
+    env.advance_time(CHECKPOINT_DURATION);
+    env.partial_close_short(id.clone(), dec!("250")).expect_commit_success();
+
+    let position: ShortPosition = env.nft_data(env.short_nft, &id);
+    assert_eq!(position.face_value, dec!("750"));
+    assert!(env.invariants().is_consistent);
+}
+
+#[test]
+fn test_add_liquidity_mints_lp_tokens() {
+    let mut env = TestEnv::new();
+    let lp_before = env.balance(env.lp_token);
+
+    env.add_liquidity(dec!("5000")).expect_commit_success();
+
+    assert_eq!(env.balance(env.lp_token), lp_before + dec!("5000"));
+    assert_eq!(env.pool_state().share_reserves, INITIAL_LIQUIDITY - MIN_SHARE_RESERVES + dec!("5000"));
+}
+
+#[test]
+fn test_add_liquidity_rejects_dust() {
+    let mut env = TestEnv::new();
+    let receipt = env.add_liquidity(dec!("0.5"));
+    TestEnv::assert_fails_with(receipt, "Liquidity amount must be at least 1");
+}
+
+#[test]
+fn test_remove_liquidity_returns_base() {
+    let mut env = TestEnv::new();
+    let base_before = env.balance(env.base_token);
+    let lp_before = env.balance(env.lp_token);
+
+    env.remove_liquidity(dec!("1000")).expect_commit_success();
+
+    assert_eq!(env.balance(env.lp_token), lp_before - dec!("1000"));
+    assert_eq!(env.balance(env.base_token), base_before + dec!("1000"));
+    assert_eq!(env.balance(env.withdrawal_token), Decimal::ZERO);
+}
+
+#[test]
+fn test_remove_liquidity_rejects_wrong_token() {
+    let mut env = TestEnv::new();
+    let base_token = env.base_token;
+    let receipt = env.call_with_fungible("remove_liquidity", base_token, dec!("1000"));
+    TestEnv::assert_fails_with(receipt, "Invalid LP token resource address");
+}
+
+#[test]
+fn test_update_share_price_rejects_zero() {
+    let mut env = TestEnv::new();
+    let receipt = env.update_share_price(Decimal::ZERO);
+    TestEnv::assert_fails_with(receipt, "Share price must be positive");
+}
+
+#[test]
+fn test_update_share_price_requires_oracle_updater() {
+    let mut env = TestEnv::new();
+    let receipt = env.update_share_price_as(None, dec!("1.01"));
+    receipt.expect_commit_failure();
+}
+
+#[test]
+fn test_oracle_updater_role_moves_to_its_own_badge() {
+    let mut env = TestEnv::new();
+    let oracle_badge = env.new_badge();
+    env.set_role("oracle_updater", rule!(require(oracle_badge))).expect_commit_success();
+
+    // The admin badge no longer holds the role, the oracle badge alone does
+    let admin_badge = env.admin_badge;
+    env.update_share_price_as(Some(admin_badge), dec!("1.01")).expect_commit_failure();
+    env.update_share_price_as(Some(oracle_badge), dec!("1.01")).expect_commit_success();
+    assert_eq!(env.pool_state().share_price, dec!("1.01"));
+}
+
+#[test]
+fn test_set_mode_requires_pauser() {
+    let mut env = TestEnv::new();
+    let manifest = ManifestBuilder::new()
+        .lock_fee_from_faucet()
+        .call_method(env.component, "set_mode", manifest_args!(PoolMode::Paused))
+        .build();
+    env.execute(manifest).expect_commit_failure();
+
+    let receipt = env.set_mode(PoolMode::CloseOnly);
+    assert!(!receipt.expect_commit_success().application_events.is_empty());
+    assert_eq!(env.query::<PoolMode>("get_mode"), PoolMode::CloseOnly);
+}
+
+#[test]
+fn test_close_only_blocks_opens_but_allows_closes() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    env.set_mode(PoolMode::CloseOnly).expect_commit_success();
+
+    TestEnv::assert_fails_with(env.open_long(dec!("1000")), "Pool is not accepting new positions or liquidity");
+    TestEnv::assert_fails_with(env.add_liquidity(dec!("1000")), "Pool is not accepting new positions or liquidity");
+    env.close_long(id).expect_commit_success();
+    env.remove_liquidity(dec!("1000")).expect_commit_success();
+}
+
+#[test]
+fn test_paused_allows_only_emergency_exits() {
+    let mut env = TestEnv::new();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let long_nft = env.long_nft;
+    let lp_token = env.lp_token;
+
+    TestEnv::assert_fails_with(
+        env.call_with_nfts("emergency_close", long_nft, vec![id.clone()]),
+        "Emergency exits are only available while the pool is paused"
+    );
+
+    // A price pushed after the checkpoint is ignored by the emergency exits
+    env.update_share_price(dec!("1.5")).expect_commit_success();
+    env.set_mode(PoolMode::Paused).expect_commit_success();
+    TestEnv::assert_fails_with(env.close_long(id.clone()), "Pool is paused");
+    TestEnv::assert_fails_with(env.update_share_price(dec!("2")), "Pool is paused");
+
+    let balance_before = env.balance(env.base_token);
+    env.call_with_nfts("emergency_close", long_nft, vec![id]).expect_commit_success();
+    let received = env.balance(env.base_token) - balance_before;
+    assert!(received > dec!("900") && received <= dec!("1000"));
+    assert!(env.nft_ids(long_nft).is_empty());
+
+    env.call_with_fungible("emergency_remove_liquidity", lp_token, dec!("1000")).expect_commit_success();
+}
+
+#[test]
+fn test_fee_change_takes_effect_after_delay() {
+    let mut env = TestEnv::new();
+    let fees = FeeSchedule {
+        curve_fee: dec!("0.02"),
+        flat_fee: dec!("0.01"),
+        governance_curve_fee: dec!("0.2"),
+        governance_flat_fee: dec!("0.3"),
+        zombie_governance_fee: dec!("0.2"),
+    };
+    env.propose_fees(&fees).expect_commit_success();
+
+    // Traders see the pending change and when it lands
+    let pending = env.query::<Option<FeeProposal>>("get_pending_fees").unwrap();
+    assert_eq!(pending.fees, fees);
+    assert_eq!(pending.executable_time, pending.proposed_time + CHECKPOINT_DURATION);
+
+    TestEnv::assert_fails_with(env.call("execute_fees"), "Fee proposal delay has not passed");
+    env.advance_time(CHECKPOINT_DURATION);
+    env.call("execute_fees").expect_commit_success();
+
+    assert_eq!(env.query::<FeeSchedule>("get_fees"), fees);
+    assert!(env.query::<Option<FeeProposal>>("get_pending_fees").is_none());
+}
+
+#[test]
+fn test_fee_update_delay_is_counted_in_epochs() {
+    let mut env = TestEnv::new();
+    TestEnv::assert_fails_with(
+        env.call_as_admin("set_fee_update_delay", manifest_args!(287u64)),
+        "Fee update delay must be at least 288 epochs"
+    );
+    env.call_as_admin("set_fee_update_delay", manifest_args!(288u64)).expect_commit_success();
+
+    let fees = env.query::<FeeSchedule>("get_fees");
+    env.propose_fees(&fees).expect_commit_success();
+    env.advance_time(287);
+    TestEnv::assert_fails_with(env.call("execute_fees"), "Fee proposal delay has not passed");
+    env.advance_time(1);
+    env.call("execute_fees").expect_commit_success();
+}
+
+#[test]
+fn test_fee_proposal_bounds_and_cancel() {
+    let mut env = TestEnv::new();
+    let mut fees = env.query::<FeeSchedule>("get_fees");
+    fees.curve_fee = dec!("0.11");
+    TestEnv::assert_fails_with(env.propose_fees(&fees), "Curve fee must be between 0 and 0.1");
+    fees.curve_fee = dec!("0.05");
+    fees.governance_flat_fee = dec!("0.6");
+    TestEnv::assert_fails_with(env.propose_fees(&fees), "Governance flat fee must be between 0 and 0.5");
+
+    fees.governance_flat_fee = dec!("0.5");
+    env.propose_fees(&fees).expect_commit_success();
+    TestEnv::assert_fails_with(env.propose_fees(&fees), "A fee proposal is already pending");
+
+    env.call_as_admin("cancel_fee_proposal", manifest_args!()).expect_commit_success();
+    assert!(env.query::<Option<FeeProposal>>("get_pending_fees").is_none());
+    env.advance_time(CHECKPOINT_DURATION);
+    TestEnv::assert_fails_with(env.call("execute_fees"), "No fee proposal pending");
+}
+
+#[test]
+fn test_collect_governance_fees_deposits_into_recipient() {
+    let mut env = TestEnv::new();
+    let (_, _, recipient) = env.ledger.new_allocated_account();
+    env.call_as_admin("set_fee_recipient", manifest_args!(recipient)).expect_commit_success();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let position: LongPosition = env.nft_data(env.long_nft, &id);
+    env.advance_time(POSITION_DURATION + CHECKPOINT_DURATION);
+    env.close_long(id).expect_commit_success();
+
+    // The governance fee charged at open, plus 10% of the 0.5% flat fee on closing at maturity
+    env.call("collect_governance_fees").expect_commit_failure();
+    env.call_as_admin("collect_governance_fees", manifest_args!()).expect_commit_success();
+    assert_close(
+        env.ledger.get_component_balance(recipient, env.base_token),
+        position.governance_fee_paid + position.face_value * dec!("0.0005")
+    );
+}
+
+#[test]
+fn test_fee_recipient_must_be_an_account() {
+    let mut env = TestEnv::new();
+    let component = env.component;
+
+    TestEnv::assert_fails_with(
+        env.call_as_admin("set_fee_recipient", manifest_args!(component)),
+        "Fee recipient must be an account",
+    );
+    assert!(env.query::<Option<ComponentAddress>>("get_fee_recipient").is_none());
+}
+
+#[test]
+fn test_referred_close_accrues_share_of_governance_fee() {
+    let mut env = TestEnv::new();
+    let (_, _, referrer) = env.ledger.new_allocated_account();
+    env.open_long_with(env.base_token, dec!("1000"), Some(referrer)).expect_commit_success();
+    let long_nft = env.long_nft;
+    let id = env.nft_ids(long_nft)[0].clone();
+    let position: LongPosition = env.nft_data(long_nft, &id);
+    assert_close(env.referral_fees(referrer), position.governance_fee_paid * dec!("0.2"));
+
+    env.advance_time(POSITION_DURATION + CHECKPOINT_DURATION);
+    env.call_close_with("close_long", long_nft, vec![id], position.face_value, Some(referrer)).expect_commit_success();
+
+    // 20% of the governance fees charged at open and on closing at maturity
+    let governance_fees = position.governance_fee_paid + position.face_value * dec!("0.0005");
+    let referral_fees = env.referral_fees(referrer);
+    assert_close(referral_fees, governance_fees * dec!("0.2"));
+    let (_, _, recipient) = env.ledger.new_allocated_account();
+    env.call_as_admin("set_fee_recipient", manifest_args!(recipient)).expect_commit_success();
+    env.call_as_admin("collect_governance_fees", manifest_args!()).expect_commit_success();
+    assert_close(env.ledger.get_component_balance(recipient, env.base_token), governance_fees * dec!("0.8"));
+
+    let manifest = ManifestBuilder::new()
+        .lock_fee_from_faucet()
+        .call_method(env.component, "claim_referral_fees", manifest_args!(referrer))
+        .build();
+    env.execute(manifest).expect_commit_success();
+    assert_eq!(env.ledger.get_component_balance(referrer, env.base_token), referral_fees);
+    assert_eq!(env.referral_fees(referrer), Decimal::ZERO);
+}
+
+#[test]
+fn test_referral_share_is_bounded_and_gated() {
+    let mut env = TestEnv::new();
+    assert_eq!(env.query::<Decimal>("get_referral_share"), dec!("0.2"));
+
+    TestEnv::assert_fails_with(
+        env.call_as_admin("set_referral_share", manifest_args!(dec!("0.6"))),
+        "Referral share must be between 0 and 0.5",
+    );
+    let manifest = ManifestBuilder::new()
+        .lock_fee_from_faucet()
+        .call_method(env.component, "set_referral_share", manifest_args!(dec!("0.5")))
+        .build();
+    env.execute(manifest).expect_commit_failure();
+
+    env.call_as_admin("set_referral_share", manifest_args!(dec!("0.5"))).expect_commit_success();
+    assert_eq!(env.query::<Decimal>("get_referral_share"), dec!("0.5"));
+}
+
+#[test]
+fn test_referrer_must_be_an_account_other_than_fee_recipient() {
+    let mut env = TestEnv::new();
+    let (base_token, component) = (env.base_token, env.component);
+
+    TestEnv::assert_fails_with(
+        env.open_long_with(base_token, dec!("1000"), Some(component)),
+        "Referrer must be an account",
+    );
+
+    let (_, _, recipient) = env.ledger.new_allocated_account();
+    env.call_as_admin("set_fee_recipient", manifest_args!(recipient)).expect_commit_success();
+    TestEnv::assert_fails_with(
+        env.open_long_with(base_token, dec!("1000"), Some(recipient)),
+        "Referrer cannot be the fee recipient",
+    );
+}
+
+#[test]
+fn test_zero_referral_fee_accrues_nothing() {
+    let mut env = TestEnv::new();
+    let (_, _, referrer) = env.ledger.new_allocated_account();
+    env.call_as_admin("set_referral_share", manifest_args!(Decimal::ZERO)).expect_commit_success();
+
+    let long_nft = env.long_nft;
+    env.open_long_with(env.base_token, dec!("1000"), Some(referrer)).expect_commit_success();
+    let id = env.nft_ids(long_nft)[0].clone();
+    env.advance_time(POSITION_DURATION + CHECKPOINT_DURATION);
+    let face_value = env.nft_data::<LongPosition>(long_nft, &id).face_value;
+    env.call_close_with("close_long", long_nft, vec![id], face_value, Some(referrer)).expect_commit_success();
+
+    // No referral vault is opened, so there is nothing to claim
+    assert_eq!(env.referral_fees(referrer), Decimal::ZERO);
+    let manifest = ManifestBuilder::new()
+        .lock_fee_from_faucet()
+        .call_method(env.component, "claim_referral_fees", manifest_args!(referrer))
+        .build();
+    TestEnv::assert_fails_with(env.execute(manifest), "No referral fees accrued");
+}
+
+#[test]
+fn test_referred_redemption_accrues_share_of_governance_fee() {
+    let mut env = TestEnv::new();
+    let (_, _, referrer) = env.ledger.new_allocated_account();
+    env.open_long(dec!("1000")).expect_commit_success();
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let position: LongPosition = env.nft_data(env.long_nft, &id);
+    let long_nft = env.long_nft;
+    env.call_with_nfts("tokenize_long", long_nft, vec![id]).expect_commit_success();
+    let bond_token = env.bond_resource(position.checkpoint).unwrap();
+
+    env.advance_time(POSITION_DURATION + CHECKPOINT_DURATION);
+    env.redeem_with("redeem_bonds", bond_token, position.face_value, Some(referrer)).expect_commit_success();
+
+    // 20% of the governance fee, which is 10% of the 0.5% flat fee on face value
+    assert_close(env.referral_fees(referrer), position.face_value * dec!("0.0001"));
+}
+
+#[test]
+fn test_sweep_recovers_stray_tokens_only() {
+    let mut env = TestEnv::new();
+    let stray_token = env.ledger.create_fungible_resource(dec!("100"), 18, env.account);
+
+    let manifest = ManifestBuilder::new()
+        .lock_fee_from_faucet()
+        .withdraw_from_account(env.account, stray_token, dec!("100"))
+        .take_from_worktop(stray_token, dec!("100"), "stray")
+        .call_method_with_name_lookup(env.component, "deposit_stray", |lookup| {
+            (lookup.bucket("stray"),)
+        })
+        .build();
+    env.execute(manifest).expect_commit_success();
+    assert_eq!(env.balance(stray_token), Decimal::ZERO);
+
+    let (base_token, lp_token) = (env.base_token, env.lp_token);
+    TestEnv::assert_fails_with(env.call_as_admin("sweep", manifest_args!(base_token)), "Pool resources cannot be swept");
+    TestEnv::assert_fails_with(env.call_as_admin("sweep", manifest_args!(lp_token)), "Pool resources cannot be swept");
+
+    let manifest = ManifestBuilder::new()
+        .lock_fee_from_faucet()
+        .create_proof_from_account_of_amount(env.account, env.admin_badge, dec!("1"))
+        .call_method(env.component, "sweep", manifest_args!(stray_token))
+        .try_deposit_entire_worktop_or_abort(env.account, None)
+        .build();
+    env.execute(manifest).expect_commit_success();
+    assert_eq!(env.balance(stray_token), dec!("100"));
+}
+
+#[test]
+fn test_checkpoint_advances_with_clock() {
+    let mut env = TestEnv::new();
+    env.advance_time(CHECKPOINT_DURATION * 3);
+    env.open_long(dec!("1000")).expect_commit_success();
+
+    let state = env.pool_state();
+    assert_eq!(state.current_checkpoint, CHECKPOINT_DURATION * 3);
+
+    let id = env.nft_ids(env.long_nft)[0].clone();
+    let position: LongPosition = env.nft_data(env.long_nft, &id);
+    assert_eq!(position.checkpoint, CHECKPOINT_DURATION * 3);
+    assert_eq!(position.maturity_time, CHECKPOINT_DURATION * 3 + POSITION_DURATION);
+}
+
+#[test]
+fn test_fixed_fee_mode_quotes_schedule_curve_fee() {
+    let mut env = TestEnv::new();
+    assert_eq!(env.query::<FeeMode>("get_fee_mode"), FeeMode::Fixed);
+
+    let quote = env.quote_curve_fee(dec!("50000"));
+    assert_eq!(quote.curve_fee, dec!("0.01"));
+    assert_eq!(quote.volatility, Decimal::ZERO);
+}
+
+#[test]
+fn test_dynamic_fee_scales_with_trade_size_and_is_capped() {
+    let mut env = TestEnv::without_pool();
+    let fee_mode = FeeMode::Dynamic(dynamic_fee_config());
+    env.create_pool_with_mode(default_fees(), fee_mode.clone(), INITIAL_LIQUIDITY).expect_commit_success();
+    assert_eq!(env.query::<FeeMode>("get_fee_mode"), fee_mode);
+
+    let effective_share_reserves: Decimal = env.query("effective_share_reserves");
+    let small = env.quote_curve_fee(dec!("1000"));
+    assert_eq!(small.base_curve_fee, dec!("0.01"));
+    assert_eq!(small.size, dec!("1000") / effective_share_reserves);
+    assert_eq!(small.utilization, Decimal::ZERO);
+    assert_eq!(small.curve_fee, dec!("0.01") * (Decimal::ONE + small.size));
+
+    // Larger trades pay a higher rate, up to the configured maximum
+    let large = env.quote_curve_fee(dec!("50000"));
+    assert!(large.curve_fee > small.curve_fee);
+    let huge = env.quote_curve_fee(dec!("10000000"));
+    assert_eq!(huge.curve_fee, dec!("0.05"));
+
+    // Open longs add exposure, which raises the quote for the same trade
+    env.open_long(dec!("10000")).expect_commit_success();
+    let after = env.quote_curve_fee(dec!("1000"));
+    assert!(after.utilization > Decimal::ZERO);
+    assert!(after.curve_fee > small.curve_fee);
+}
+
+#[test]
+fn test_twap_share_price_weights_prices_by_time() {
+    let mut env = TestEnv::new();
+    let start = env.ledger.get_current_epoch().number();
+    let rate_before: Decimal = env.query("get_fixed_rate");
+    assert_close(rate_before, INITIAL_FIXED_RATE);
+    env.advance_time(100);
+    env.update_share_price(dec!("1.1")).expect_commit_success();
+    env.advance_time(100);
+
+    // (1 * 100 + 1.1 * 100) / 200
+    let twap: Decimal = env.twap("get_twap_share_price", 200).expect_commit_success().output(1);
+    assert_eq!(twap, dec!("1.05"));
+    let twap: Decimal = env.twap("get_twap_share_price", 100).expect_commit_success().output(1);
+    assert_eq!(twap, dec!("1.1"));
+
+    // Growing share reserves against the same bonds lower the fixed rate, which then
+    // holds for the second half of the window
+    let fixed_rate: Decimal = env.query("get_fixed_rate");
+    assert!(fixed_rate < rate_before);
+    let twap_rate: Decimal = env.twap("get_twap_rate", 200).expect_commit_success().output(1);
+    assert_close(twap_rate, (rate_before + fixed_rate) / 2);
+
+    TestEnv::assert_fails_with(env.twap("get_twap_share_price", 201), "TWAP window exceeds the observation history");
+    TestEnv::assert_fails_with(env.twap("get_twap_rate", 0), "TWAP window must be positive");
+
+    let history = env.rate_history(0, 10);
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].time, start);
+    assert_eq!(history[0].fixed_rate, rate_before);
+    assert_eq!(history[1].time, start + 100);
+    assert_eq!(history[1].fixed_rate, fixed_rate);
+    assert_eq!(history[1].share_price, dec!("1.1"));
+    assert_eq!(history[1].cumulative_share_price, dec!("100"));
+    assert_eq!(env.rate_history(start + 1, 10).len(), 1);
+    assert_eq!(env.rate_history(0, 1).len(), 1);
+}
+
+#[test]
+fn test_trades_at_one_time_share_an_observation() {
+    let mut env = TestEnv::new();
+    env.advance_time(10);
+    env.open_long(dec!("1000")).expect_commit_success();
+    env.open_long(dec!("1000")).expect_commit_success();
+    env.add_liquidity(dec!("1000")).expect_commit_success();
+
+    let history = env.rate_history(0, 10);
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].time, history[0].time + 10);
+
+    // The last trade's rate replaces the earlier ones; the sums only cover the 10 epochs before
+    let fixed_rate: Decimal = env.query("get_fixed_rate");
+    assert_eq!(history[1].fixed_rate, fixed_rate);
+    assert_eq!(history[1].cumulative_rate, history[0].fixed_rate * dec!("10"));
+    assert_eq!(history[1].cumulative_share_price, dec!("10"));
+}
+
+#[test]
+fn test_rate_history_wraps_and_pages() {
+    let mut env = TestEnv::new();
+    let start = env.ledger.get_current_epoch().number();
+
+    // One observation a second, at a share price rising by 0.0001 each time, until the
+    // buffer has overwritten the creation observation and the first five updates
+    let updates = OBSERVATION_CAPACITY + 5;
+    for i in 1..=updates {
+        env.advance_time(1);
+        env.update_share_price(Decimal::ONE + dec!("0.0001") * Decimal::from(i)).expect_commit_success();
+    }
+    let oldest = start + 6;
+
+    // A page never holds more than MAX_OBSERVATION_PAGE observations, oldest first
+    let page = env.rate_history(0, OBSERVATION_CAPACITY);
+    assert_eq!(page.len() as u64, MAX_OBSERVATION_PAGE);
+    assert_eq!(page[0].time, oldest);
+    assert_eq!(page[0].share_price, dec!("1.0006"));
+    assert!(page.windows(2).all(|pair| pair[1].time == pair[0].time + 1));
+
+    let next = env.rate_history(page.last().unwrap().time + 1, OBSERVATION_CAPACITY);
+    assert_eq!(next[0].time, oldest + MAX_OBSERVATION_PAGE);
+    let last = env.rate_history(start + updates - 9, OBSERVATION_CAPACITY);
+    assert_eq!(last.len(), 10);
+    assert_eq!(last[9].time, start + updates);
+
+    // TWAPs reach back to the oldest surviving observation and no further: the
+    // average of 1.0006 through 1.1028, each held for one second
+    let twap: Decimal = env.twap("get_twap_share_price", updates - 6).expect_commit_success().output(1);
+    assert_close(twap, dec!("1.0517"));
+    TestEnv::assert_fails_with(
+        env.twap("get_twap_share_price", updates - 5),
+        "TWAP window exceeds the observation history"
+    );
+}
@@ -0,0 +1,39 @@
+use scrypto::prelude::*;
+use influxpool::oracle::*;
+use influxpool::types::*;
+
+fn observation(time: u64, fixed_rate: Decimal, share_price: Decimal) -> RateObservation {
+    RateObservation {
+        time,
+        fixed_rate,
+        share_price,
+        cumulative_rate: dec!("2"),
+        cumulative_share_price: dec!("100"),
+    }
+}
+
+#[test]
+fn test_cumulatives_extrapolate_the_last_values() {
+    let last = observation(50, dec!("0.05"), dec!("1.2"));
+
+    // 2 + 0.05 * 10 and 100 + 1.2 * 10
+    assert_eq!(cumulatives_at(&last, 60), (dec!("2.5"), dec!("112")));
+    assert_eq!(cumulatives_at(&last, 50), (dec!("2"), dec!("100")));
+}
+
+#[test]
+fn test_accumulate_observation_carries_sums_forward() {
+    let last = observation(50, dec!("0.05"), dec!("1.2"));
+    let next = accumulate_observation(&last, dec!("0.04"), dec!("1.25"), 70);
+
+    assert_eq!(next.time, 70);
+    assert_eq!(next.fixed_rate, dec!("0.04"));
+    assert_eq!(next.share_price, dec!("1.25"));
+    // 2 + 0.05 * 20 and 100 + 1.2 * 20
+    assert_eq!(next.cumulative_rate, dec!("3"));
+    assert_eq!(next.cumulative_share_price, dec!("124"));
+
+    // Averaging between the two recovers the rate in effect
+    let (end_rate, _) = cumulatives_at(&next, 70);
+    assert_eq!((end_rate - last.cumulative_rate) / dec!("20"), dec!("0.05"));
+}
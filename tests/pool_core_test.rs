@@ -0,0 +1,426 @@
+use scrypto::prelude::*;
+use influxpool::curves::*;
+use influxpool::fees::*;
+use influxpool::helpers::*;
+use influxpool::pool_core::*;
+use influxpool::types::*;
+
+const CHECKPOINT_DURATION: u64 = 604800;
+const POSITION_DURATION: u64 = CHECKPOINT_DURATION * 52;
+
+/// 1% curve, 0.5% flat and 10% of each to governance
+fn fees() -> FeeSchedule {
+    FeeSchedule {
+        curve_fee: dec!("0.01"),
+        flat_fee: dec!("0.005"),
+        governance_curve_fee: dec!("0.1"),
+        governance_flat_fee: dec!("0.1"),
+        zombie_governance_fee: dec!("0.1"),
+    }
+}
+
+/// Reserves and checkpoints of a pool with some open longs and shorts
+#[derive(Clone)]
+struct Pool {
+    share_reserves: Decimal,
+    bond_reserves: Decimal,
+    zeta_adjustment: Decimal,
+    checkpoints: HashMap<u64, Checkpoint>,
+    short_collateral: Decimal,
+}
+
+fn seeded_pool() -> Pool {
+    let mut pool = Pool {
+        share_reserves: dec!("99000"),
+        bond_reserves: calculate_bond_reserves_for_rate(dec!("99000"), Decimal::ONE, dec!("0.05"), POSITION_DURATION),
+        zeta_adjustment: Decimal::ZERO,
+        checkpoints: HashMap::new(),
+        short_collateral: Decimal::ZERO,
+    };
+    create_first_checkpoint(&mut pool.checkpoints, CHECKPOINT_DURATION, Decimal::ONE, 0);
+    apply_open_short(
+        dec!("5000"),
+        &mut pool.share_reserves,
+        &mut pool.bond_reserves,
+        pool.zeta_adjustment,
+        Decimal::ONE,
+        &mut pool.checkpoints,
+        0,
+        POSITION_DURATION,
+        &fees(),
+        &mut pool.short_collateral,
+    );
+    apply_open_long(
+        dec!("3000"),
+        &mut pool.share_reserves,
+        &mut pool.bond_reserves,
+        pool.zeta_adjustment,
+        Decimal::ONE,
+        &mut pool.checkpoints,
+        0,
+        POSITION_DURATION,
+        &fees(),
+    );
+    pool
+}
+
+fn lot(face_value: Decimal) -> CloseLot {
+    CloseLot {
+        face_value,
+        open_time: 0,
+        maturity_time: POSITION_DURATION,
+        initial_share_price: Decimal::ONE,
+    }
+}
+
+fn assert_close(a: Decimal, b: Decimal) {
+    assert!((a - b).checked_abs().unwrap() <= dec!("0.000000001"), "{} != {}", a, b);
+}
+
+#[test]
+fn test_single_long_lot_matches_apply_close_long() {
+    let now = POSITION_DURATION / 3;
+    let mut single = seeded_pool();
+    let mut batch = single.clone();
+
+    let expected = apply_close_long(
+        dec!("1000"), 0, POSITION_DURATION, 0, now,
+        &mut single.share_reserves, &mut single.bond_reserves, &mut single.zeta_adjustment,
+        dec!("1.02"), &mut single.checkpoints, &fees(),
+    );
+    let actual = apply_close_long_lots(
+        &[lot(dec!("1000"))], 0, now,
+        &mut batch.share_reserves, &mut batch.bond_reserves, &mut batch.zeta_adjustment,
+        dec!("1.02"), &mut batch.checkpoints, &fees(),
+    );
+
+    assert_close(actual[0].base_proceeds, expected.base_proceeds);
+    assert_close(actual[0].governance_fee, expected.governance_fee);
+    assert_close(batch.share_reserves, single.share_reserves);
+    assert_close(batch.bond_reserves, single.bond_reserves);
+    assert_close(batch.zeta_adjustment, single.zeta_adjustment);
+}
+
+#[test]
+fn test_single_short_lot_matches_apply_close_short() {
+    let now = POSITION_DURATION / 2;
+    let mut single = seeded_pool();
+    let mut batch = single.clone();
+
+    let expected = apply_close_short(
+        dec!("2000"), 0, POSITION_DURATION, Decimal::ONE, 0, now,
+        &mut single.share_reserves, &mut single.bond_reserves, &mut single.zeta_adjustment,
+        dec!("1.03"), &mut single.checkpoints, &fees(),
+        &mut single.short_collateral,
+    );
+    let actual = apply_close_short_lots(
+        &[lot(dec!("2000"))], 0, now,
+        &mut batch.share_reserves, &mut batch.bond_reserves, &mut batch.zeta_adjustment,
+        dec!("1.03"), &mut batch.checkpoints, &fees(),
+        &mut batch.short_collateral,
+    );
+
+    assert_close(actual[0].base_proceeds, expected.base_proceeds);
+    assert_close(batch.share_reserves, single.share_reserves);
+    assert_close(batch.bond_reserves, single.bond_reserves);
+    assert_close(batch.zeta_adjustment, single.zeta_adjustment);
+    assert_close(batch.short_collateral, single.short_collateral);
+}
+
+#[test]
+fn test_identical_long_lots_split_one_trade_evenly() {
+    let now = POSITION_DURATION / 4;
+    let mut merged = seeded_pool();
+    let mut batch = merged.clone();
+
+    let whole = apply_close_long(
+        dec!("1000"), 0, POSITION_DURATION, 0, now,
+        &mut merged.share_reserves, &mut merged.bond_reserves, &mut merged.zeta_adjustment,
+        Decimal::ONE, &mut merged.checkpoints, &fees(),
+    );
+    let halves = apply_close_long_lots(
+        &[lot(dec!("500")), lot(dec!("500"))], 0, now,
+        &mut batch.share_reserves, &mut batch.bond_reserves, &mut batch.zeta_adjustment,
+        Decimal::ONE, &mut batch.checkpoints, &fees(),
+    );
+
+    assert_close(halves[0].base_proceeds, halves[1].base_proceeds);
+    assert_close(halves[0].base_proceeds + halves[1].base_proceeds, whole.base_proceeds);
+    assert_close(batch.share_reserves, merged.share_reserves);
+}
+
+#[test]
+fn test_matured_long_close_books_flat_fee_in_shares() {
+    let mut single = seeded_pool();
+    let mut batch = single.clone();
+
+    // At c = 2 the matured 1000 face costs 500 shares and the 4.5 base LP flat fee
+    // (0.5% of 1000, less 10% to governance) is worth 2.25 shares: ζ -= 497.75
+    apply_close_long(
+        dec!("1000"), 0, POSITION_DURATION, 0, POSITION_DURATION,
+        &mut single.share_reserves, &mut single.bond_reserves, &mut single.zeta_adjustment,
+        dec!("2"), &mut single.checkpoints, &fees(),
+    );
+    apply_close_long_lots(
+        &[lot(dec!("1000"))], 0, POSITION_DURATION,
+        &mut batch.share_reserves, &mut batch.bond_reserves, &mut batch.zeta_adjustment,
+        dec!("2"), &mut batch.checkpoints, &fees(),
+    );
+
+    assert_close(single.zeta_adjustment, dec!("-497.75"));
+    assert_close(batch.zeta_adjustment, dec!("-497.75"));
+}
+
+#[test]
+fn test_matured_short_stops_accruing_at_maturity_checkpoint() {
+    let mut single = seeded_pool();
+    advance_checkpoint(&mut single.checkpoints, 0, CHECKPOINT_DURATION, dec!("1.05"), POSITION_DURATION);
+    let mut batch = single.clone();
+    let now = POSITION_DURATION + CHECKPOINT_DURATION;
+
+    // Interest stops at the maturity price of 1.05 even though c is now 1.1:
+    // 2000 * 0.05 of interest less the 0.5% flat fee on 2000 face value
+    let closed = apply_close_short(
+        dec!("2000"), 0, POSITION_DURATION, Decimal::ONE, 0, now,
+        &mut single.share_reserves, &mut single.bond_reserves, &mut single.zeta_adjustment,
+        dec!("1.1"), &mut single.checkpoints, &fees(),
+        &mut single.short_collateral,
+    );
+    let lots = apply_close_short_lots(
+        &[lot(dec!("2000"))], 0, now,
+        &mut batch.share_reserves, &mut batch.bond_reserves, &mut batch.zeta_adjustment,
+        dec!("1.1"), &mut batch.checkpoints, &fees(),
+        &mut batch.short_collateral,
+    );
+
+    assert_close(closed.base_proceeds, dec!("90"));
+    assert_close(lots[0].base_proceeds, dec!("90"));
+    assert_close(batch.share_reserves, single.share_reserves);
+}
+
+#[test]
+#[should_panic(expected = "Position must be matured to roll over")]
+fn test_rollover_long_rejects_unmatured_position() {
+    // A second before maturity the bonds are still worth less than their face value
+    let mut pool = seeded_pool();
+    apply_rollover_long(
+        dec!("1000"), 0, POSITION_DURATION, 0, POSITION_DURATION - 1,
+        &mut pool.share_reserves, &mut pool.bond_reserves, &mut pool.zeta_adjustment,
+        Decimal::ONE, &mut pool.checkpoints, 0, POSITION_DURATION, &fees(),
+    );
+}
+
+#[test]
+fn test_rollover_long_reinvests_at_the_curve_price() {
+    let mut pool = seeded_pool();
+    let current_checkpoint = advance_checkpoint(
+        &mut pool.checkpoints, 0, CHECKPOINT_DURATION, Decimal::ONE, POSITION_DURATION
+    );
+
+    // The matured face value settles fee free, then buys bonds on the curve as it stands
+    let mut settled = pool.clone();
+    apply_close_long(
+        dec!("1000"), 0, POSITION_DURATION, 0, POSITION_DURATION,
+        &mut settled.share_reserves, &mut settled.bond_reserves, &mut settled.zeta_adjustment,
+        Decimal::ONE, &mut settled.checkpoints, &FeeSchedule::default(),
+    );
+    let effective_shares = calculate_effective_share_reserves(settled.share_reserves, settled.zeta_adjustment);
+    let bonds_bought = calculate_long_face_value(dec!("1000"), effective_shares, settled.bond_reserves);
+    let spot_price = calculate_spot_price(effective_shares, settled.bond_reserves, Decimal::ONE);
+    // The new term pays the 1% curve fee on the discount, less the 10% governance share
+    let expected_face_value = bonds_bought - dec!("0.01") * (Decimal::ONE - spot_price) * bonds_bought * dec!("0.9");
+
+    let (base_rolled, opened) = apply_rollover_long(
+        dec!("1000"), 0, POSITION_DURATION, 0, POSITION_DURATION,
+        &mut pool.share_reserves, &mut pool.bond_reserves, &mut pool.zeta_adjustment,
+        Decimal::ONE, &mut pool.checkpoints, current_checkpoint, POSITION_DURATION, &fees(),
+    );
+
+    assert_close(base_rolled, dec!("1000"));
+    assert!(opened.face_value > dec!("1000"));
+    assert_close(opened.face_value, expected_face_value);
+}
+
+#[test]
+fn test_rollover_long_settles_like_a_matured_close() {
+    let mut rolled = seeded_pool();
+    advance_checkpoint(&mut rolled.checkpoints, 0, CHECKPOINT_DURATION, Decimal::ONE, POSITION_DURATION);
+    let now = POSITION_DURATION + CHECKPOINT_DURATION;
+    let current_checkpoint = advance_checkpoint(&mut rolled.checkpoints, POSITION_DURATION, CHECKPOINT_DURATION, dec!("1.1"), now);
+    let mut closed = rolled.clone();
+
+    // The share price grew 10% after maturity, which accrues to neither path
+    let close = apply_close_long(
+        dec!("1000"), 0, POSITION_DURATION, 0, now,
+        &mut closed.share_reserves, &mut closed.bond_reserves, &mut closed.zeta_adjustment,
+        dec!("1.1"), &mut closed.checkpoints, &fees(),
+    );
+    let (base_rolled, _) = apply_rollover_long(
+        dec!("1000"), 0, POSITION_DURATION, 0, now,
+        &mut rolled.share_reserves, &mut rolled.bond_reserves, &mut rolled.zeta_adjustment,
+        dec!("1.1"), &mut rolled.checkpoints, current_checkpoint, POSITION_DURATION, &fees(),
+    );
+
+    // The rollover only skips the flat fee the close pays
+    assert_close(close.base_proceeds, dec!("995"));
+    assert_close(base_rolled, close.base_proceeds + close.governance_fee + close.lp_fee);
+}
+
+#[test]
+#[should_panic(expected = "Position must be matured to roll over")]
+fn test_rollover_short_rejects_unmatured_position() {
+    let mut pool = seeded_pool();
+    apply_rollover_short(
+        dec!("1000"), Decimal::ONE, 0, POSITION_DURATION, POSITION_DURATION - 1, dec!("1000"),
+        &mut pool.share_reserves, &mut pool.bond_reserves, &mut pool.zeta_adjustment,
+        Decimal::ONE, &mut pool.checkpoints, 0, POSITION_DURATION, &fees(),
+        &mut pool.short_collateral,
+    );
+}
+
+#[test]
+fn test_rollover_short_fixes_a_skipped_maturity_price() {
+    let mut pool = seeded_pool();
+    let now = POSITION_DURATION + CHECKPOINT_DURATION;
+    let current_checkpoint = advance_checkpoint(&mut pool.checkpoints, 0, CHECKPOINT_DURATION, dec!("1.1"), now);
+    assert!(!pool.checkpoints.contains_key(&POSITION_DURATION));
+
+    // Nobody traded at maturity, so the roll mints the maturity checkpoint at c = 1.1
+    let (interest, _) = apply_rollover_short(
+        dec!("1000"), Decimal::ONE, 0, POSITION_DURATION, now, dec!("1000"),
+        &mut pool.share_reserves, &mut pool.bond_reserves, &mut pool.zeta_adjustment,
+        dec!("1.1"), &mut pool.checkpoints, current_checkpoint, POSITION_DURATION, &fees(),
+        &mut pool.short_collateral,
+    );
+    assert_close(interest, dec!("100"));
+    assert_eq!(pool.checkpoints[&POSITION_DURATION].share_price, dec!("1.1"));
+
+    // A later close of the same maturity reads that price, not c = 1.2: 2000 * 0.1
+    // of interest less the 0.5% flat fee on 2000 face value
+    let closed = apply_close_short(
+        dec!("2000"), 0, POSITION_DURATION, Decimal::ONE, 0, now + CHECKPOINT_DURATION,
+        &mut pool.share_reserves, &mut pool.bond_reserves, &mut pool.zeta_adjustment,
+        dec!("1.2"), &mut pool.checkpoints, &fees(),
+        &mut pool.short_collateral,
+    );
+    assert_close(closed.base_proceeds, dec!("190"));
+}
+
+#[test]
+fn test_matured_short_closes_fix_a_skipped_maturity_price() {
+    let mut pool = seeded_pool();
+    let now = POSITION_DURATION + CHECKPOINT_DURATION;
+    advance_checkpoint(&mut pool.checkpoints, 0, CHECKPOINT_DURATION, dec!("1.1"), now);
+
+    // The first close mints the maturity checkpoint at c = 1.1: 2000 * 0.1 of
+    // interest less the 0.5% flat fee on 2000 face value
+    let first = apply_close_short(
+        dec!("2000"), 0, POSITION_DURATION, Decimal::ONE, 0, now,
+        &mut pool.share_reserves, &mut pool.bond_reserves, &mut pool.zeta_adjustment,
+        dec!("1.1"), &mut pool.checkpoints, &fees(),
+        &mut pool.short_collateral,
+    );
+    assert_close(first.base_proceeds, dec!("190"));
+    assert_eq!(pool.checkpoints[&POSITION_DURATION].share_price, dec!("1.1"));
+
+    // A later close of the same maturity at c = 1.2 is paid the same
+    let second = apply_close_short_lots(
+        &[lot(dec!("2000"))], 0, now + CHECKPOINT_DURATION,
+        &mut pool.share_reserves, &mut pool.bond_reserves, &mut pool.zeta_adjustment,
+        dec!("1.2"), &mut pool.checkpoints, &fees(),
+        &mut pool.short_collateral,
+    );
+    assert_close(second[0].base_proceeds, dec!("190"));
+}
+
+#[test]
+fn test_open_short_charges_curve_fee_at_spot_price() {
+    let mut share_reserves = dec!("9000");
+    let mut bond_reserves = dec!("10000");
+    let mut checkpoints = HashMap::new();
+    let mut short_collateral = Decimal::ZERO;
+    create_first_checkpoint(&mut checkpoints, CHECKPOINT_DURATION, Decimal::ONE, 0);
+
+    // p = 9000 / 10000 = 0.9, so the curve fee is 1% of the 0.1 discount on
+    // 100 face value: 0.1, of which governance takes 10%
+    let opened = apply_open_short(
+        dec!("100"),
+        &mut share_reserves,
+        &mut bond_reserves,
+        Decimal::ZERO,
+        Decimal::ONE,
+        &mut checkpoints,
+        0,
+        POSITION_DURATION,
+        &fees(),
+        &mut short_collateral,
+    );
+
+    assert_eq!(opened.governance_fee, dec!("0.01"));
+    assert_eq!(opened.lp_fee, dec!("0.09"));
+}
+
+#[test]
+fn test_short_collateral_is_booked_in_shares() {
+    let mut share_reserves = dec!("9000");
+    let mut bond_reserves = dec!("20000");
+    let mut zeta_adjustment = Decimal::ZERO;
+    let mut checkpoints = HashMap::new();
+    let mut short_collateral = Decimal::ZERO;
+    create_first_checkpoint(&mut checkpoints, CHECKPOINT_DURATION, dec!("2"), 0);
+
+    // 1000 face value is backed by 1000 base, i.e. 500 shares at c = 2
+    apply_open_short(
+        dec!("1000"),
+        &mut share_reserves,
+        &mut bond_reserves,
+        zeta_adjustment,
+        dec!("2"),
+        &mut checkpoints,
+        0,
+        POSITION_DURATION,
+        &fees(),
+        &mut short_collateral,
+    );
+    assert_eq!(short_collateral, dec!("500"));
+
+    let closed = apply_close_short(
+        dec!("1000"),
+        0,
+        POSITION_DURATION,
+        dec!("2"),
+        0,
+        POSITION_DURATION,
+        &mut share_reserves,
+        &mut bond_reserves,
+        &mut zeta_adjustment,
+        dec!("2"),
+        &mut checkpoints,
+        &FeeSchedule::default(),
+        &mut short_collateral,
+    );
+    assert_eq!(short_collateral, Decimal::ZERO);
+
+    // Without share price growth a matured short earns nothing
+    assert_close(closed.base_proceeds, Decimal::ZERO);
+}
+
+#[test]
+fn test_solvency_requirement_counts_first_checkpoint_once() {
+    let mut checkpoints = HashMap::new();
+    create_first_checkpoint(&mut checkpoints, CHECKPOINT_DURATION, Decimal::ONE, 0);
+    checkpoints.get_mut(&0).unwrap().long_positions = dec!("1000");
+
+    // Stepping back from checkpoint 0 used to saturate at 0 and count its longs
+    // once per checkpoint in the term, 52000 instead of 1000
+    let requirement = calculate_solvency_requirement(&checkpoints, 0, CHECKPOINT_DURATION, POSITION_DURATION);
+    assert_eq!(requirement, dec!("1000"));
+
+    let requirement = calculate_solvency_requirement(
+        &checkpoints,
+        CHECKPOINT_DURATION * 3,
+        CHECKPOINT_DURATION,
+        POSITION_DURATION
+    );
+    assert_eq!(requirement, dec!("1000"));
+}